@@ -0,0 +1,37 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use rust_nes_emu::bus::TestBus;
+use rust_nes_emu::cpu::CPU;
+
+// Mix of addressing modes and instruction kinds, looping forever
+const PROGRAM: [u8; 20] = [
+    0xA2, 0x00,       // LDX #$00
+    0xE8,             // INX
+    0xBD, 0x00, 0x02, // LDA $0200,X
+    0x69, 0x01,       // ADC #$01
+    0x9D, 0x00, 0x03, // STA $0300,X
+    0x26, 0x10,       // ROL $10
+    0x48,             // PHA
+    0x68,             // PLA
+    0xD0, 0xF1,       // BNE -15, back to INX
+    0x4C, 0x00, 0x80, // JMP $8000
+];
+
+fn tight_loop(c: &mut Criterion) {
+    let mut bus = TestBus::new();
+    bus.load(PROGRAM.to_vec());
+
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    cpu.stack_pointer = 0xFD;
+
+    c.bench_function("cpu 1000 instructions", |b| {
+        b.iter(|| {
+            for _ in 0..1000 {
+                cpu.next();
+            }
+        })
+    });
+}
+
+criterion_group!(benches, tight_loop);
+criterion_main!(benches);
@@ -106,6 +106,8 @@ fn main() {
 
     let mut cpu = CPU::new(bus);
     cpu.reset();
+    // Game over jumps to empty memory, stop there
+    cpu.stop_on_brk = true;
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
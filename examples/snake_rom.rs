@@ -1,6 +1,8 @@
 use rust_nes_emu::cpu::CPU;
 use rust_nes_emu::rom::Rom;
 use rust_nes_emu::bus::NesBus;
+use rust_nes_emu::controller::{Button, Buttons};
+use rust_nes_emu::save::SaveFile;
 
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
@@ -39,7 +41,17 @@ fn read_screen_state(cpu: &mut CPU, frame: &mut [u8; 32 * 32 * 3]) -> bool {
     update
 }
 
-fn handle_user_input(cpu: &mut CPU, event_pump: &mut EventPump) {
+fn button(keycode: Keycode) -> Option<Button> {
+    match keycode {
+        Keycode::W => Some(Button::Up),
+        Keycode::S => Some(Button::Down),
+        Keycode::A => Some(Button::Left),
+        Keycode::D => Some(Button::Right),
+        _ => None,
+    }
+}
+
+fn handle_user_input(buttons: &Buttons, event_pump: &mut EventPump, save: &SaveFile) {
     for event in event_pump.poll_iter() {
         match event {
             Event::Quit { .. }
@@ -47,50 +59,92 @@ fn handle_user_input(cpu: &mut CPU, event_pump: &mut EventPump) {
                 keycode: Some(Keycode::Escape),
                 ..
             } => {
+                save.flush().unwrap();
                 std::process::exit(0);
             }
             Event::KeyDown {
-                keycode: Some(Keycode::W),
-                ..
-            } => {
-                cpu.bus.mem_write(0xff, 0x77);
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::S),
+                keycode: Some(keycode),
                 ..
             } => {
-                cpu.bus.mem_write(0xff, 0x73);
+                if let Some(button) = button(keycode) {
+                    buttons.set(button, true);
+                }
             }
-            Event::KeyDown {
-                keycode: Some(Keycode::A),
+            Event::KeyUp {
+                keycode: Some(keycode),
                 ..
             } => {
-                cpu.bus.mem_write(0xff, 0x61);
-            }
-            Event::KeyDown {
-                keycode: Some(Keycode::D),
-                ..
-            } => {
-                cpu.bus.mem_write(0xff, 0x64);
+                if let Some(button) = button(keycode) {
+                    buttons.set(button, false);
+                }
             }
             _ => {}
         }
     }
 }
 
+// The game comes from a 6502 tutorial, where keys are written to $FF
+// as ASCII codes. Main loop is redirected through a routine filling
+// $FF from the controller instead.
+fn read_controller_into_zero_page(rom: &mut Rom) {
+    let routine = [
+        0xA9, 0x01,       // LDA #$01
+        0x8D, 0x16, 0x40, // STA $4016
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x16, 0x40, // STA $4016
+        0xA2, 0x08,       // LDX #$08
+        0xAD, 0x16, 0x40, // LDA $4016
+        0x4A,             // LSR A
+        0x6E, 0x00, 0x07, // ROR $0700
+        0xCA,             // DEX
+        0xD0, 0xF6,       // BNE -10
+        0xAD, 0x00, 0x07, // LDA $0700
+        0x29, 0x10,       // AND #$10 (Up)
+        0xF0, 0x04,       // BEQ +4
+        0xA9, 0x77,       // LDA #'w'
+        0x85, 0xFF,       // STA $FF
+        0xAD, 0x00, 0x07, // LDA $0700
+        0x29, 0x20,       // AND #$20 (Down)
+        0xF0, 0x04,       // BEQ +4
+        0xA9, 0x73,       // LDA #'s'
+        0x85, 0xFF,       // STA $FF
+        0xAD, 0x00, 0x07, // LDA $0700
+        0x29, 0x40,       // AND #$40 (Left)
+        0xF0, 0x04,       // BEQ +4
+        0xA9, 0x61,       // LDA #'a'
+        0x85, 0xFF,       // STA $FF
+        0xAD, 0x00, 0x07, // LDA $0700
+        0x29, 0x80,       // AND #$80 (Right)
+        0xF0, 0x04,       // BEQ +4
+        0xA9, 0x64,       // LDA #'d'
+        0x85, 0xFF,       // STA $FF
+        0x4C, 0x4D, 0x86, // JMP $864D, original key handling
+    ];
+    rom.prg_rom[0x0800..0x0800 + routine.len()].copy_from_slice(&routine);
+    // JSR $864D in the main loop becomes JSR $8800
+    rom.prg_rom[0x0638..0x063B].copy_from_slice(&[0x20, 0x00, 0x88]);
+}
+
 fn main() {
     // let bytes = std::fs::read("./examples/snake.nes").unwrap().try_into().unwrap();
     // let rom: Rom = (&bytes).try_into().unwrap();
     //
-    let rom: Rom = std::fs::read("./examples/snake.nes")
+    let rom_path = "./examples/snake.nes";
+    let mut rom: Rom = std::fs::read(rom_path)
         .unwrap()
         .try_into()
         .unwrap();
+    read_controller_into_zero_page(&mut rom);
 
-    let bus = NesBus::new(rom);
+    let bus = NesBus::new(rom).unwrap();
+    let buttons = bus.buttons(0);
+    // Battery backed RAM is loaded from and saved to snake.sav
+    let save = SaveFile::open(rom_path, bus.cartridge().clone()).unwrap();
 
     let mut cpu = CPU::new(bus);
     cpu.reset();
+    // Game over jumps to empty memory, stop there
+    cpu.stop_on_brk = true;
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
@@ -114,7 +168,7 @@ fn main() {
     let mut rng = rand::thread_rng();
 
     cpu.run_with_callback(move |cpu: &mut CPU, cycles| {
-        handle_user_input(cpu, &mut event_pump);
+        handle_user_input(&buttons, &mut event_pump, &save);
         cpu.bus.mem_write(0xfe, rng.gen_range(1..=16));
 
         if read_screen_state(cpu, &mut screen_state) {
@@ -0,0 +1,31 @@
+use rust_nes_emu::apu::DEFAULT_SAMPLE_RATE;
+use rust_nes_emu::rom::Rom;
+use rust_nes_emu::wav::{capture_audio, save_wav};
+
+const USAGE: &str = "Usage: wav_capture <rom> <output.wav> [--frames N] [--sample-rate HZ]";
+const DEFAULT_FRAMES: usize = 600;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut paths = Vec::new();
+    let mut frames = DEFAULT_FRAMES;
+    let mut sample_rate = DEFAULT_SAMPLE_RATE;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => frames = args.next().and_then(|value| value.parse().ok()).expect(USAGE),
+            "--sample-rate" => sample_rate = args.next().and_then(|value| value.parse().ok()).expect(USAGE),
+            _ => paths.push(arg),
+        }
+    }
+    let [rom_path, wav_path] = <[String; 2]>::try_from(paths).expect(USAGE);
+
+    let rom: Rom = std::fs::read(&rom_path)
+        .unwrap()
+        .try_into()
+        .unwrap();
+
+    let samples = capture_audio(rom, frames, sample_rate).unwrap();
+    save_wav(&wav_path, sample_rate, &samples).unwrap();
+    println!("Wrote {} samples to {}", samples.len(), wav_path);
+}
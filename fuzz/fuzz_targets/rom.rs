@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_nes_emu::bus::NesBus;
+use rust_nes_emu::rom::Rom;
+
+// Loading arbitrary files may fail, but must never panic
+fuzz_target!(|data: &[u8]| {
+    if let Ok(rom) = Rom::try_from(data.to_vec()) {
+        let _ = NesBus::new(rom);
+    }
+});
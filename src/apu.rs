@@ -0,0 +1,245 @@
+// Audio Processing Unit of 2A03, described here:
+// https://www.nesdev.org/wiki/APU
+//
+// Implemented here:
+// - Envelope and length counter shared by channels
+mod units;
+// - Two pulse channels, $4000-$4007
+mod pulse;
+// - Triangle channel, $4008-$400B
+mod triangle;
+// - Noise channel, $400C-$400F
+mod noise;
+// - Delta modulation channel, $4010-$4013
+mod dmc;
+// - Frame sequencer, $4017
+mod frame_counter;
+// - Nonlinear mixing of channel outputs
+pub mod mixer;
+// - Band-limited resampling to output sample rate
+pub mod blip;
+// - High-pass and low-pass filters of the output stage
+pub mod filter;
+
+use blip::BlipBuffer;
+use dmc::Dmc;
+use filter::OutputFilter;
+use frame_counter::{FrameClock, FrameCounter};
+use noise::Noise;
+use pulse::Pulse;
+use std::cell::RefCell;
+use std::rc::Rc;
+use triangle::Triangle;
+
+// NTSC CPU clock rate, APU is clocked with each CPU cycle
+pub const CPU_CLOCK: u32 = 1_789_773;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44_100;
+
+const STATUS: u16 = 0x4015;
+const FRAME_COUNTER: u16 = 0x4017;
+
+const STATUS_PULSE_1: u8 = 0b0000_0001;
+const STATUS_PULSE_2: u8 = 0b0000_0010;
+const STATUS_TRIANGLE: u8 = 0b0000_0100;
+const STATUS_NOISE: u8 = 0b0000_1000;
+const STATUS_DMC: u8 = 0b0001_0000;
+const STATUS_FRAME_IRQ: u8 = 0b0100_0000;
+const STATUS_DMC_IRQ: u8 = 0b1000_0000;
+
+// Output samples, shared so they can be drained by audio backend
+// after the bus is moved into the CPU
+pub type SampleBuffer = Rc<RefCell<Vec<f32>>>;
+
+// Without anyone draining the buffer only the latest second of output
+// is kept. Oldest samples are dropped once twice that piles up, so
+// trimming stays cheap.
+const BUFFERED_SECONDS: usize = 1;
+
+pub struct APU {
+    pulse_1: Pulse,
+    pulse_2: Pulse,
+    triangle: Triangle,
+    noise: Noise,
+    dmc: Dmc,
+    frame_counter: FrameCounter,
+    // Pulse timers tick every other CPU cycle
+    even_cycle: bool,
+
+    sample_rate: u32,
+    samples: SampleBuffer,
+    // Mixer output changes are fed to band-limited synthesis, which
+    // produces samples at output rate
+    blip: BlipBuffer,
+    level: f32,
+    filter: OutputFilter,
+    resampled: Vec<f32>,
+}
+
+impl APU {
+    pub fn new(sample_rate: u32) -> Self {
+        APU {
+            pulse_1: Pulse::new(true),
+            pulse_2: Pulse::new(false),
+            triangle: Triangle::default(),
+            noise: Noise::new(),
+            dmc: Dmc::new(),
+            frame_counter: FrameCounter::default(),
+            even_cycle: false,
+            sample_rate,
+            samples: Rc::new(RefCell::new(Vec::new())),
+            blip: BlipBuffer::new(CPU_CLOCK, sample_rate),
+            level: 0.0,
+            filter: OutputFilter::new(sample_rate),
+            resampled: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.blip = BlipBuffer::new(CPU_CLOCK, sample_rate);
+        self.level = 0.0;
+        self.filter = OutputFilter::new(sample_rate);
+    }
+
+    pub fn samples(&self) -> SampleBuffer {
+        self.samples.clone()
+    }
+
+    // Handles $4000-$4013, $4015 and $4017
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x4000..=0x4003 => self.pulse_1.write(addr & 0b11, data),
+            0x4004..=0x4007 => self.pulse_2.write(addr & 0b11, data),
+            0x4008..=0x400B => self.triangle.write(addr & 0b11, data),
+            0x400C..=0x400F => self.noise.write(addr & 0b11, data),
+            0x4010..=0x4013 => self.dmc.write(addr & 0b11, data),
+            STATUS => {
+                self.pulse_1.length.set_enabled(data & STATUS_PULSE_1 != 0);
+                self.pulse_2.length.set_enabled(data & STATUS_PULSE_2 != 0);
+                self.triangle.length.set_enabled(data & STATUS_TRIANGLE != 0);
+                self.noise.length.set_enabled(data & STATUS_NOISE != 0);
+                self.dmc.set_enabled(data & STATUS_DMC != 0);
+            }
+            FRAME_COUNTER => self.frame_counter.write(data, self.even_cycle),
+            _ => {}
+        }
+    }
+
+    // $4015 reads report which channels are still playing and pending
+    // interrupts. Reading acknowledges frame IRQ.
+    pub fn read_status(&mut self) -> u8 {
+        let mut status = 0;
+        if self.pulse_1.length.active() {
+            status |= STATUS_PULSE_1;
+        }
+        if self.pulse_2.length.active() {
+            status |= STATUS_PULSE_2;
+        }
+        if self.triangle.length.active() {
+            status |= STATUS_TRIANGLE;
+        }
+        if self.noise.length.active() {
+            status |= STATUS_NOISE;
+        }
+        if self.dmc.active() {
+            status |= STATUS_DMC;
+        }
+        if self.frame_counter.irq {
+            status |= STATUS_FRAME_IRQ;
+        }
+        if self.dmc.irq {
+            status |= STATUS_DMC_IRQ;
+        }
+        self.frame_counter.acknowledge_irq();
+        status
+    }
+
+    pub fn frame_irq(&self) -> bool {
+        self.frame_counter.irq
+    }
+
+    pub fn dmc_irq(&self) -> bool {
+        self.dmc.irq
+    }
+
+    // Address DMC wants to read its next sample byte from. Bus halts
+    // CPU to read it, and passes the byte back with dmc_fill.
+    pub fn dmc_request(&self) -> Option<u16> {
+        self.dmc.dma_request()
+    }
+
+    pub fn dmc_fill(&mut self, data: u8) {
+        self.dmc.dma_complete(data);
+    }
+
+    // Called every CPU cycle, with output of cartridge expansion audio
+    pub fn tick(&mut self, expansion: f32) {
+        if let Some(clock) = self.frame_counter.tick() {
+            self.clock_quarter_frame();
+            if clock == FrameClock::Half {
+                self.clock_half_frame();
+            }
+        }
+
+        if self.even_cycle {
+            self.pulse_1.clock_timer();
+            self.pulse_2.clock_timer();
+        }
+        self.even_cycle = !self.even_cycle;
+        self.triangle.clock_timer();
+        self.noise.clock_timer();
+        self.dmc.clock_timer();
+
+        self.sample(self.output() + expansion);
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        self.pulse_1.envelope.clock();
+        self.pulse_2.envelope.clock();
+        self.noise.envelope.clock();
+        self.triangle.clock_linear_counter();
+    }
+
+    fn clock_half_frame(&mut self) {
+        self.pulse_1.length.clock();
+        self.pulse_2.length.clock();
+        self.triangle.length.clock();
+        self.noise.length.clock();
+        self.pulse_1.clock_sweep();
+        self.pulse_2.clock_sweep();
+    }
+
+    // Mixer output before resampling and filtering, within 0.0..1.0
+    pub fn output(&self) -> f32 {
+        mixer::pulse_out(self.pulse_1.output(), self.pulse_2.output())
+            + mixer::tnd_out(self.triangle.output(), self.noise.output(), self.dmc.output())
+    }
+
+    // Passes level of the current CPU cycle to resampler, and filtered
+    // samples it completes to sample buffer
+    fn sample(&mut self, level: f32) {
+        if level != self.level {
+            self.blip.add_delta(0, level - self.level);
+            self.level = level;
+        }
+        self.blip.end_frame(1);
+
+        if self.blip.samples_available() > 0 {
+            self.blip.read_samples(&mut self.resampled);
+            let mut samples = self.samples.borrow_mut();
+            for sample in self.resampled.drain(..) {
+                samples.push(self.filter.process(sample));
+            }
+
+            let limit = self.sample_rate as usize * BUFFERED_SECONDS;
+            if samples.len() > 2 * limit {
+                let excess = samples.len() - limit;
+                samples.drain(..excess);
+            }
+        }
+    }
+}
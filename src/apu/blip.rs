@@ -0,0 +1,121 @@
+// Band-limited synthesis in the style of blargg's blip_buffer:
+// http://slack.net/~ant/libs/audio.html#Blip_Buffer
+//
+// Signal is described by amplitude changes at source clock times.
+// Each change is added as the difference of a band-limited step
+// between consecutive samples, positioned with sub-sample precision,
+// and samples are the running sum of those. That yields steps without
+// frequencies above Nyquist, which would otherwise alias into audible
+// range.
+use std::f64::consts::PI;
+
+// Taps of each impulse, output lags input by half of them
+const KERNEL_WIDTH: usize = 32;
+// Sub-sample positions impulses are precomputed for
+const PHASES: usize = 512;
+// Passband edge as a fraction of Nyquist frequency
+const CUTOFF: f64 = 0.9;
+
+pub struct BlipBuffer {
+    clock_rate: u64,
+    sample_rate: u64,
+    // Time since the first unread sample, counted in 1/clock_rate
+    // of a sample, so clock and sample boundaries are both exact
+    time: u64,
+    // Impulses added to unread samples and ones following them
+    deltas: Vec<f32>,
+    integrator: f32,
+    kernel: Vec<[f32; KERNEL_WIDTH]>,
+}
+
+impl BlipBuffer {
+    pub fn new(clock_rate: u32, sample_rate: u32) -> Self {
+        BlipBuffer {
+            clock_rate: clock_rate as u64,
+            sample_rate: sample_rate as u64,
+            time: 0,
+            deltas: vec![0.0; KERNEL_WIDTH],
+            integrator: 0.0,
+            kernel: kernel(),
+        }
+    }
+
+    // Adds change of amplitude happening given number of clocks
+    // after the current time
+    pub fn add_delta(&mut self, clock_time: u32, delta: f32) {
+        let time = self.time + clock_time as u64 * self.sample_rate;
+        let index = (time / self.clock_rate) as usize;
+        let phase = ((time % self.clock_rate) * PHASES as u64 / self.clock_rate) as usize;
+
+        if self.deltas.len() < index + KERNEL_WIDTH {
+            self.deltas.resize(index + KERNEL_WIDTH, 0.0);
+        }
+        for (sample, tap) in self.deltas[index..].iter_mut().zip(&self.kernel[phase]) {
+            *sample += delta * tap;
+        }
+    }
+
+    // Advances current time, making samples before it available
+    pub fn end_frame(&mut self, clocks: u32) {
+        self.time += clocks as u64 * self.sample_rate;
+    }
+
+    pub fn samples_available(&self) -> usize {
+        (self.time / self.clock_rate) as usize
+    }
+
+    // Appends available samples to given buffer, returns their count
+    pub fn read_samples(&mut self, out: &mut Vec<f32>) -> usize {
+        let count = self.samples_available();
+        if self.deltas.len() < count + KERNEL_WIDTH {
+            self.deltas.resize(count + KERNEL_WIDTH, 0.0);
+        }
+
+        for delta in self.deltas.drain(..count) {
+            self.integrator += delta;
+            out.push(self.integrator);
+        }
+        self.time -= count as u64 * self.clock_rate;
+        count
+    }
+}
+
+// Blackman windowed sinc, at given distance from its center in samples
+fn impulse(x: f64) -> f64 {
+    let half_width = (KERNEL_WIDTH / 2) as f64;
+    if x.abs() >= half_width {
+        return 0.0;
+    }
+    let sinc = if x == 0.0 { 1.0 } else { (PI * CUTOFF * x).sin() / (PI * CUTOFF * x) };
+    let position = PI * (x + half_width) / half_width;
+    sinc * (0.42 - 0.5 * position.cos() + 0.08 * (2.0 * position).cos())
+}
+
+// Taps for each sub-sample position of a change. Tap is the growth of
+// band-limited step, integral of the impulse, over its sample. Taking
+// impulse values directly would boost high frequencies, as their sum
+// only approximates the integral.
+fn kernel() -> Vec<[f32; KERNEL_WIDTH]> {
+    let points = KERNEL_WIDTH * PHASES;
+    let dx = 1.0 / PHASES as f64;
+    let x = |i: usize| i as f64 * dx - (KERNEL_WIDTH / 2) as f64;
+
+    // Step sampled every 1/PHASES of a sample, by trapezoidal rule
+    let mut step = vec![0.0f64; points + 1];
+    for i in 1..=points {
+        step[i] = step[i - 1] + (impulse(x(i - 1)) + impulse(x(i))) * dx / 2.0;
+    }
+    let height = step[points];
+    let step_at = |i: isize| step[i.clamp(0, points as isize) as usize] / height;
+
+    (0..PHASES)
+        .map(|phase| {
+            let mut taps = [0.0f32; KERNEL_WIDTH];
+            for (k, tap) in taps.iter_mut().enumerate() {
+                let end = (k * PHASES) as isize - phase as isize;
+                *tap = (step_at(end) - step_at(end - PHASES as isize)) as f32;
+            }
+            taps
+        })
+        .collect()
+}
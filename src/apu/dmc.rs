@@ -0,0 +1,143 @@
+// Delta modulation channel at $4010-$4013, described here:
+// https://www.nesdev.org/wiki/APU_DMC
+//
+// Plays 1-bit delta encoded samples read from $C000-$FFFF. Sample
+// bytes are fetched by DMA, which halts CPU for a few cycles.
+
+// NTSC output rates, in CPU cycles per bit
+const RATES: [u16; 16] = [428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54];
+
+const IRQ_ENABLE: u8 = 0b1000_0000;
+const LOOP: u8 = 0b0100_0000;
+const RATE: u8 = 0b0000_1111;
+
+pub(super) struct Dmc {
+    irq_enabled: bool,
+    looping: bool,
+    rate: u16,
+    timer: u16,
+    pub(super) irq: bool,
+
+    output_level: u8,
+    shift_register: u8,
+    bits_remaining: u8,
+    silence: bool,
+    sample_buffer: Option<u8>,
+
+    sample_address: u16,
+    sample_length: u16,
+    current_address: u16,
+    bytes_remaining: u16,
+}
+
+impl Dmc {
+    pub(super) fn new() -> Self {
+        Self {
+            irq_enabled: false,
+            looping: false,
+            rate: RATES[0],
+            timer: RATES[0] - 1,
+            irq: false,
+            output_level: 0,
+            shift_register: 0,
+            bits_remaining: 8,
+            silence: true,
+            sample_buffer: None,
+            sample_address: 0xC000,
+            sample_length: 1,
+            current_address: 0xC000,
+            bytes_remaining: 0,
+        }
+    }
+
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.irq_enabled = data & IRQ_ENABLE != 0;
+                if !self.irq_enabled {
+                    self.irq = false;
+                }
+                self.looping = data & LOOP != 0;
+                self.rate = RATES[(data & RATE) as usize];
+            }
+            1 => self.output_level = data & 0x7F,
+            2 => self.sample_address = 0xC000 | ((data as u16) << 6),
+            _ => self.sample_length = ((data as u16) << 4) + 1,
+        }
+    }
+
+    // Called with $4015 bit 4
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.irq = false;
+        if !enabled {
+            self.bytes_remaining = 0;
+        } else if self.bytes_remaining == 0 {
+            self.restart();
+        }
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.bytes_remaining > 0
+    }
+
+    fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+
+    // Address of the next sample byte, when buffer needs refilling
+    pub(super) fn dma_request(&self) -> Option<u16> {
+        (self.sample_buffer.is_none() && self.bytes_remaining > 0).then_some(self.current_address)
+    }
+
+    pub(super) fn dma_complete(&mut self, data: u8) {
+        self.sample_buffer = Some(data);
+        // Address wraps around to $8000
+        self.current_address = self.current_address.checked_add(1).unwrap_or(0x8000);
+        self.bytes_remaining -= 1;
+
+        if self.bytes_remaining == 0 {
+            if self.looping {
+                self.restart();
+            } else if self.irq_enabled {
+                self.irq = true;
+            }
+        }
+    }
+
+    // Clocked every CPU cycle
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer > 0 {
+            self.timer -= 1;
+            return;
+        }
+        self.timer = self.rate - 1;
+
+        if !self.silence {
+            if self.shift_register & 1 != 0 {
+                if self.output_level <= 125 {
+                    self.output_level += 2;
+                }
+            } else if self.output_level >= 2 {
+                self.output_level -= 2;
+            }
+        }
+        self.shift_register >>= 1;
+
+        self.bits_remaining -= 1;
+        if self.bits_remaining == 0 {
+            self.bits_remaining = 8;
+            match self.sample_buffer.take() {
+                Some(data) => {
+                    self.silence = false;
+                    self.shift_register = data;
+                }
+                None => self.silence = true,
+            }
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        self.output_level
+    }
+}
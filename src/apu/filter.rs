@@ -0,0 +1,67 @@
+// Filters of the console's audio output stage, described here:
+// https://www.nesdev.org/wiki/APU_Mixer
+//
+// NES output passes through two high-pass filters, which remove DC
+// offset of the DAC, and a low-pass one. All of them are first order.
+use std::f32::consts::PI;
+
+pub struct HighPass {
+    alpha: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl HighPass {
+    pub fn new(cutoff: f32, sample_rate: u32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        HighPass { alpha: rc / (rc + dt), previous_input: 0.0, previous_output: 0.0 }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.previous_output = self.alpha * (self.previous_output + input - self.previous_input);
+        self.previous_input = input;
+        self.previous_output
+    }
+}
+
+pub struct LowPass {
+    alpha: f32,
+    previous_output: f32,
+}
+
+impl LowPass {
+    pub fn new(cutoff: f32, sample_rate: u32) -> Self {
+        let rc = 1.0 / (2.0 * PI * cutoff);
+        let dt = 1.0 / sample_rate as f32;
+        LowPass { alpha: dt / (rc + dt), previous_output: 0.0 }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        self.previous_output += self.alpha * (input - self.previous_output);
+        self.previous_output
+    }
+}
+
+// High-pass at 90Hz and 440Hz followed by low-pass at 14kHz
+pub struct OutputFilter {
+    high_pass_90: HighPass,
+    high_pass_440: HighPass,
+    low_pass_14k: LowPass,
+}
+
+impl OutputFilter {
+    pub fn new(sample_rate: u32) -> Self {
+        OutputFilter {
+            high_pass_90: HighPass::new(90.0, sample_rate),
+            high_pass_440: HighPass::new(440.0, sample_rate),
+            low_pass_14k: LowPass::new(14_000.0, sample_rate),
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.high_pass_90.process(input);
+        let output = self.high_pass_440.process(output);
+        self.low_pass_14k.process(output)
+    }
+}
@@ -0,0 +1,104 @@
+// Frame sequencer at $4017, described here:
+// https://www.nesdev.org/wiki/APU_Frame_Counter
+//
+// Divides CPU clock into quarter and half frames, which clock
+// envelopes, linear counter, length counters and sweeps. In 4-step
+// mode it also raises IRQ at the end of each sequence.
+
+const FIVE_STEP_MODE: u8 = 0b1000_0000;
+const IRQ_INHIBIT: u8 = 0b0100_0000;
+
+// CPU cycles since sequencer reset at which steps happen. Steps fall
+// in the middle of APU cycles, so they are rounded up.
+const QUARTER_1: u32 = 7457;
+const HALF_1: u32 = 14913;
+const QUARTER_2: u32 = 22371;
+// 4-step mode raises IRQ over three cycles around the last step
+const FOUR_STEP_IRQ: u32 = 29828;
+const FOUR_STEP_HALF_2: u32 = 29829;
+const FOUR_STEP_END: u32 = 29830;
+const FIVE_STEP_HALF_2: u32 = 37281;
+const FIVE_STEP_END: u32 = 37282;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum FrameClock {
+    Quarter,
+    // Half frames are also quarter frames
+    Half,
+}
+
+#[derive(Default)]
+pub(super) struct FrameCounter {
+    cycle: u32,
+    five_step: bool,
+    irq_inhibit: bool,
+    pub(super) irq: bool,
+    // Mode written to $4017 and CPU cycles left until sequencer is
+    // reset with it
+    pending_reset: Option<(bool, u8)>,
+}
+
+impl FrameCounter {
+    // Sequencer is reset 3 CPU cycles after write made during APU
+    // cycle, 4 when made between APU cycles. Delay includes the write
+    // cycle itself.
+    pub(super) fn write(&mut self, data: u8, apu_cycle: bool) {
+        self.irq_inhibit = data & IRQ_INHIBIT != 0;
+        if self.irq_inhibit {
+            self.irq = false;
+        }
+
+        let delay = if apu_cycle { 4 } else { 5 };
+        self.pending_reset = Some((data & FIVE_STEP_MODE != 0, delay));
+    }
+
+    // Reading $4015 acknowledges IRQ
+    pub(super) fn acknowledge_irq(&mut self) {
+        self.irq = false;
+    }
+
+    fn set_irq(&mut self) {
+        if !self.irq_inhibit {
+            self.irq = true;
+        }
+    }
+
+    pub(super) fn tick(&mut self) -> Option<FrameClock> {
+        if let Some((five_step, delay)) = self.pending_reset {
+            if delay > 1 {
+                self.pending_reset = Some((five_step, delay - 1));
+            } else {
+                self.pending_reset = None;
+                self.five_step = five_step;
+                self.cycle = 0;
+                // Switching to 5-step mode clocks all units immediately
+                return five_step.then_some(FrameClock::Half);
+            }
+        }
+
+        self.cycle += 1;
+        match (self.five_step, self.cycle) {
+            (_, QUARTER_1 | QUARTER_2) => Some(FrameClock::Quarter),
+            (_, HALF_1) => Some(FrameClock::Half),
+            (false, FOUR_STEP_IRQ) => {
+                self.set_irq();
+                None
+            }
+            (false, FOUR_STEP_HALF_2) => {
+                self.set_irq();
+                Some(FrameClock::Half)
+            }
+            (false, FOUR_STEP_END) => {
+                self.set_irq();
+                self.cycle = 0;
+                None
+            }
+            (true, FIVE_STEP_HALF_2) => Some(FrameClock::Half),
+            (true, FIVE_STEP_END) => {
+                self.cycle = 0;
+                None
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+// Nonlinear DAC of 2A03, described here:
+// https://www.nesdev.org/wiki/APU_Mixer
+//
+// Pulse channels share one resistor ladder, triangle, noise and DMC
+// another, so each group is mixed nonlinearly. Full volume on every
+// channel outputs close to 1.0.
+
+// Output of both pulse channels, which have levels 0-15
+pub fn pulse_out(pulse_1: u8, pulse_2: u8) -> f32 {
+    let sum = (pulse_1 + pulse_2) as f32;
+    if sum == 0.0 {
+        return 0.0;
+    }
+    95.88 / (8128.0 / sum + 100.0)
+}
+
+// Output of triangle and noise with levels 0-15 and DMC with 0-127
+pub fn tnd_out(triangle: u8, noise: u8, dmc: u8) -> f32 {
+    let sum = triangle as f32 / 8227.0 + noise as f32 / 12241.0 + dmc as f32 / 22638.0;
+    if sum == 0.0 {
+        return 0.0;
+    }
+    159.79 / (1.0 / sum + 100.0)
+}
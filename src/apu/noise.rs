@@ -0,0 +1,73 @@
+// Noise channel at $400C-$400F, described here:
+// https://www.nesdev.org/wiki/APU_Noise
+use super::units::{Envelope, LengthCounter};
+
+// NTSC timer periods, in CPU cycles
+const PERIODS: [u16; 16] = [4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068];
+
+const LENGTH_HALT: u8 = 0b0010_0000;
+const SHORT_MODE: u8 = 0b1000_0000;
+const PERIOD: u8 = 0b0000_1111;
+
+pub(super) struct Noise {
+    // 15-bit linear feedback shift register, loaded with 1 at power on
+    shift_register: u16,
+    // Feedback taken from bit 6 instead of bit 1, producing
+    // short repeating sequence
+    short_mode: bool,
+    period: u16,
+    timer: u16,
+    pub(super) envelope: Envelope,
+    pub(super) length: LengthCounter,
+}
+
+impl Noise {
+    pub(super) fn new() -> Self {
+        Self {
+            shift_register: 1,
+            short_mode: false,
+            period: PERIODS[0],
+            timer: 0,
+            envelope: Envelope::default(),
+            length: LengthCounter::default(),
+        }
+    }
+
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.length.set_halt(data & LENGTH_HALT != 0);
+                self.envelope.write(data);
+            }
+            1 => {}
+            2 => {
+                self.short_mode = data & SHORT_MODE != 0;
+                self.period = PERIODS[(data & PERIOD) as usize];
+            }
+            _ => {
+                self.length.load(data);
+                self.envelope.restart();
+            }
+        }
+    }
+
+    // Clocked every CPU cycle
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period - 1;
+            let tap = if self.short_mode { 6 } else { 1 };
+            let feedback = (self.shift_register ^ (self.shift_register >> tap)) & 1;
+            self.shift_register = (self.shift_register >> 1) | (feedback << 14);
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if self.shift_register & 1 != 0 || !self.length.active() {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
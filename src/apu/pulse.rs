@@ -0,0 +1,117 @@
+// Pulse channels at $4000-$4007, described here:
+// https://www.nesdev.org/wiki/APU_Pulse
+use super::units::{Envelope, LengthCounter};
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+const LENGTH_HALT: u8 = 0b0010_0000;
+
+const SWEEP_ENABLE: u8 = 0b1000_0000;
+const SWEEP_PERIOD: u8 = 0b0111_0000;
+const SWEEP_NEGATE: u8 = 0b0000_1000;
+const SWEEP_SHIFT: u8 = 0b0000_0111;
+
+// Periods outside of this range mute the channel
+const MIN_PERIOD: u16 = 8;
+const MAX_PERIOD: u16 = 0x7FF;
+
+#[derive(Default)]
+pub(super) struct Pulse {
+    // Pulse 1 negates sweep change with ones' complement, so it
+    // subtracts one more than pulse 2, which uses two's complement
+    ones_complement: bool,
+
+    duty: u8,
+    step: u8,
+    period: u16,
+    timer: u16,
+    pub(super) envelope: Envelope,
+    pub(super) length: LengthCounter,
+
+    sweep_enabled: bool,
+    sweep_period: u8,
+    sweep_negate: bool,
+    sweep_shift: u8,
+    sweep_divider: u8,
+    sweep_reload: bool,
+}
+
+impl Pulse {
+    pub(super) fn new(ones_complement: bool) -> Self {
+        Self { ones_complement, ..Self::default() }
+    }
+
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.duty = data >> 6;
+                self.length.set_halt(data & LENGTH_HALT != 0);
+                self.envelope.write(data);
+            }
+            1 => {
+                self.sweep_enabled = data & SWEEP_ENABLE != 0;
+                self.sweep_period = (data & SWEEP_PERIOD) >> 4;
+                self.sweep_negate = data & SWEEP_NEGATE != 0;
+                self.sweep_shift = data & SWEEP_SHIFT;
+                self.sweep_reload = true;
+            }
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0b111) << 8);
+                self.length.load(data);
+                self.step = 0;
+                self.envelope.restart();
+            }
+        }
+    }
+
+    // Clocked every other CPU cycle
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    // Sweep unit constantly computes target period, even when disabled
+    fn target_period(&self) -> u16 {
+        let change = self.period >> self.sweep_shift;
+        if self.sweep_negate {
+            self.period.saturating_sub(change + self.ones_complement as u16)
+        } else {
+            self.period + change
+        }
+    }
+
+    fn muted(&self) -> bool {
+        self.period < MIN_PERIOD || self.target_period() > MAX_PERIOD
+    }
+
+    pub(super) fn clock_sweep(&mut self) {
+        if self.sweep_divider == 0 && self.sweep_enabled && self.sweep_shift > 0 && !self.muted() {
+            self.period = self.target_period();
+        }
+
+        if self.sweep_divider == 0 || self.sweep_reload {
+            self.sweep_divider = self.sweep_period;
+            self.sweep_reload = false;
+        } else {
+            self.sweep_divider -= 1;
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if self.muted() || !self.length.active() || DUTY_TABLE[self.duty as usize][self.step as usize] == 0 {
+            0
+        } else {
+            self.envelope.output()
+        }
+    }
+}
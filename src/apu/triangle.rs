@@ -0,0 +1,74 @@
+// Triangle channel at $4008-$400B, described here:
+// https://www.nesdev.org/wiki/APU_Triangle
+use super::units::LengthCounter;
+
+const SEQUENCE: [u8; 32] = [
+    15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0, //
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+// Also halts length counter
+const LINEAR_CONTROL: u8 = 0b1000_0000;
+const LINEAR_RELOAD: u8 = 0b0111_1111;
+
+#[derive(Default)]
+pub(super) struct Triangle {
+    step: u8,
+    period: u16,
+    timer: u16,
+    pub(super) length: LengthCounter,
+    // Second length counter with finer resolution, clocked by
+    // quarter frames
+    linear_control: bool,
+    linear_reload_value: u8,
+    linear_reload: bool,
+    linear_counter: u8,
+}
+
+impl Triangle {
+    pub(super) fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.linear_control = data & LINEAR_CONTROL != 0;
+                self.length.set_halt(self.linear_control);
+                self.linear_reload_value = data & LINEAR_RELOAD;
+            }
+            1 => {}
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            _ => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0b111) << 8);
+                self.length.load(data);
+                self.linear_reload = true;
+            }
+        }
+    }
+
+    // Clocked every CPU cycle. Stopped channel holds its output
+    // level instead of dropping to 0.
+    pub(super) fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            if self.length.active() && self.linear_counter > 0 {
+                self.step = (self.step + 1) % 32;
+            }
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    pub(super) fn clock_linear_counter(&mut self) {
+        if self.linear_reload {
+            self.linear_counter = self.linear_reload_value;
+        } else if self.linear_counter > 0 {
+            self.linear_counter -= 1;
+        }
+
+        if !self.linear_control {
+            self.linear_reload = false;
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        SEQUENCE[self.step as usize]
+    }
+}
@@ -0,0 +1,100 @@
+// Envelope and length counter, shared by pulse and noise channels,
+// described here:
+// https://www.nesdev.org/wiki/APU_Envelope
+// https://www.nesdev.org/wiki/APU_Length_Counter
+
+// Lengths loaded by writes of bits 3-7 to the last channel register
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, //
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const ENVELOPE_LOOP: u8 = 0b0010_0000;
+const ENVELOPE_CONSTANT_VOLUME: u8 = 0b0001_0000;
+const ENVELOPE_VOLUME: u8 = 0b0000_1111;
+
+// Produces decaying volume, or constant one, clocked by quarter frames
+#[derive(Default)]
+pub(super) struct Envelope {
+    start: bool,
+    divider: u8,
+    decay: u8,
+    looping: bool,
+    constant_volume: bool,
+    volume: u8,
+}
+
+impl Envelope {
+    pub(super) fn write(&mut self, data: u8) {
+        self.looping = data & ENVELOPE_LOOP != 0;
+        self.constant_volume = data & ENVELOPE_CONSTANT_VOLUME != 0;
+        self.volume = data & ENVELOPE_VOLUME;
+    }
+
+    pub(super) fn restart(&mut self) {
+        self.start = true;
+    }
+
+    pub(super) fn clock(&mut self) {
+        if self.start {
+            self.start = false;
+            self.decay = 15;
+            self.divider = self.volume;
+        } else if self.divider == 0 {
+            self.divider = self.volume;
+            if self.decay > 0 {
+                self.decay -= 1;
+            } else if self.looping {
+                self.decay = 15;
+            }
+        } else {
+            self.divider -= 1;
+        }
+    }
+
+    pub(super) fn output(&self) -> u8 {
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.decay
+        }
+    }
+}
+
+// Silences channel after given time, clocked by half frames.
+// Disabled channels have their counter forced to 0.
+#[derive(Default)]
+pub(super) struct LengthCounter {
+    enabled: bool,
+    halt: bool,
+    value: u8,
+}
+
+impl LengthCounter {
+    pub(super) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.value = 0;
+        }
+    }
+
+    pub(super) fn set_halt(&mut self, halt: bool) {
+        self.halt = halt;
+    }
+
+    pub(super) fn load(&mut self, data: u8) {
+        if self.enabled {
+            self.value = LENGTH_TABLE[(data >> 3) as usize];
+        }
+    }
+
+    pub(super) fn clock(&mut self) {
+        if !self.halt && self.value > 0 {
+            self.value -= 1;
+        }
+    }
+
+    pub(super) fn active(&self) -> bool {
+        self.value > 0
+    }
+}
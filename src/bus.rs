@@ -1,14 +1,21 @@
-use crate::rom::Rom;
+use crate::apu::{APU, DEFAULT_SAMPLE_RATE};
+use crate::cartridge::{self, Cartridge};
+use crate::controller::{Buttons, Controller};
+use crate::cpu::interrupts::{InterruptLines, IrqSource};
+use crate::ppu::PPU;
+use crate::rom::{Rom, RomError};
 
 pub trait Bus {
-    fn mem_read(&self, addr: u16) -> u8 ;
+    // Reads take mutable reference, as reading some registers
+    // changes state of devices mapped to them
+    fn mem_read(&mut self, addr: u16) -> u8;
     fn mem_write(&mut self, addr: u16, data: u8) -> ();
 
-    fn mem_read_u16(&self, addr: u16) -> u16 {
+    fn mem_read_u16(&mut self, addr: u16) -> u16 {
         (self.mem_read(addr.wrapping_add(1)) as u16) << 8 | (self.mem_read(addr) as u16)
     }
 
-    fn mem_read_u16_zero_page(&self, addr: u16) -> u16 {
+    fn mem_read_u16_zero_page(&mut self, addr: u16) -> u16 {
         (self.mem_read(addr.wrapping_add(1) % 256) as u16) << 8 | (self.mem_read(addr % 256) as u16)
     }
 
@@ -16,6 +23,25 @@ pub trait Bus {
         self.mem_write(addr, (data & 0xff) as u8);
         self.mem_write(addr + 1, (data >> 8) as u8);
     }
+
+    // Called by CPU after every cycle. Devices connected to the bus
+    // advance their state here and drive CPU interrupt lines.
+    fn tick(&mut self, _interrupts: &mut InterruptLines) {}
+
+    // Checked by CPU before every instruction. Returns page requested
+    // for OAM DMA, CPU is halted while it copies the page to PPU.
+    fn poll_dma(&mut self) -> Option<u8> {
+        None
+    }
+
+    // Checked by CPU before every instruction. Returns address of the
+    // sample byte requested by APU DMC, which CPU is halted to read.
+    fn poll_dmc_dma(&mut self) -> Option<u16> {
+        None
+    }
+
+    // Hands the byte read for DMC DMA back to the APU
+    fn complete_dmc_dma(&mut self, _data: u8) {}
 }
 
 pub struct TestBus {
@@ -23,7 +49,7 @@ pub struct TestBus {
 }
 
 impl Bus for TestBus {
-    fn mem_read(&self, addr: u16) -> u8 {
+    fn mem_read(&mut self, addr: u16) -> u8 {
         self.memory[addr as usize]
     }
 
@@ -51,43 +77,193 @@ impl TestBus {
 }
 
 
+const RAM_MIRRORS_END: u16 = 0x1FFF;
+const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
+const IO_REGISTERS_END: u16 = 0x401F;
+const OAM_DMA: u16 = 0x4014;
+const APU_STATUS: u16 = 0x4015;
+const APU_FRAME_COUNTER: u16 = 0x4017;
+const JOYPAD_1: u16 = 0x4016;
+const JOYPAD_2: u16 = 0x4017;
+// Controllers drive only the lowest bits of data bus
+const JOYPAD_OPEN_BUS: u8 = 0b1110_0000;
+
 pub struct NesBus {
     ram: [u8; 2048],
-    rom: Rom
+    cartridge: Cartridge,
+    ppu: PPU,
+    apu: APU,
+    controllers: [Controller; 2],
+    dma_page: Option<u8>,
+    // Called whenever PPU finishes a picture
+    frame_callback: Box<dyn FnMut(&PPU)>,
+    // Last value driven on data bus. Reads from addresses nothing
+    // responds to return it, as bus capacitance holds the value.
+    open_bus: u8,
 }
 
 impl NesBus {
-    pub fn new(rom: Rom) -> Box<Self> {
-        Box::new(Self {
+    pub fn new(rom: Rom) -> Result<Box<Self>, RomError> {
+        Self::with_frame_callback(rom, |_| {})
+    }
+
+    // Fails when cartridge hardware described by ROM isn't emulated
+    pub fn with_frame_callback(rom: Rom, frame_callback: impl FnMut(&PPU) + 'static) -> Result<Box<Self>, RomError> {
+        let cartridge = cartridge::new(rom)?;
+        let ppu = PPU::new(cartridge.clone());
+
+        Ok(Box::new(Self {
             ram: [0u8; 2048],
-            rom
-        })
+            cartridge,
+            ppu,
+            apu: APU::new(DEFAULT_SAMPLE_RATE),
+            controllers: [Controller::new(), Controller::new()],
+            dma_page: None,
+            frame_callback: Box::new(frame_callback),
+            open_bus: 0,
+        }))
+    }
+
+    pub fn ppu(&self) -> &PPU {
+        &self.ppu
+    }
+
+    pub fn apu(&self) -> &APU {
+        &self.apu
+    }
+
+    pub fn apu_mut(&mut self) -> &mut APU {
+        &mut self.apu
+    }
+
+    // Buttons of given player, 0 for the first controller. Handle stays
+    // valid after bus is moved into CPU.
+    pub fn buttons(&self, player: usize) -> Buttons {
+        self.controllers[player].buttons()
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cartridge
+    }
+
+    // Contents of battery backed RAM, None when cartridge has no battery
+    pub fn save_data(&self) -> Option<Vec<u8>> {
+        self.cartridge.borrow().battery_ram().map(<[u8]>::to_vec)
+    }
+
+    // Restores battery backed RAM, normally before reset
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        self.cartridge.borrow_mut().load_battery_ram(data);
+    }
+
+    // PPU exposes 8 registers mirrored every 8 bytes through $3FFF.
+    // It has its own data latch, so these reads never see CPU open bus.
+    fn read_ppu_register(&mut self, addr: u16) -> Option<u8> {
+        Some(self.ppu.read_register(addr))
+    }
+
+    fn write_ppu_register(&mut self, addr: u16, data: u8) {
+        self.ppu.write_register(addr, data);
+        self.cartridge.borrow_mut().ppu_register_write(addr, data);
     }
 
-    pub fn read_prg_rom(&self, mut addr: u16) -> u8 {
-        addr -= 0x8000;
-        if self.rom.prg_rom.len() == 0x4000 && addr >= 0x4000 {
-            addr = addr % 0x4000;
+    // APU and I/O registers, most of them are write only
+    fn read_io_register(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            // Bit 5 isn't driven by APU
+            APU_STATUS => Some(self.apu.read_status() | (self.open_bus & 0b0010_0000)),
+            JOYPAD_1 => Some(self.controllers[0].read() | (self.open_bus & JOYPAD_OPEN_BUS)),
+            JOYPAD_2 => Some(self.controllers[1].read() | (self.open_bus & JOYPAD_OPEN_BUS)),
+            _ => None,
         }
-        self.rom.prg_rom[addr as usize]
     }
-}
 
-impl Bus for NesBus {
-    fn mem_read(&self, addr: u16) -> u8 {
+    fn write_io_register(&mut self, addr: u16, data: u8) {
         match addr {
-            0x0000..=0x1FFF => self.ram[addr as usize],
-            0x8000..=0xFFFF => self.read_prg_rom(addr),
-            _ => todo!()
+            OAM_DMA => self.dma_page = Some(data),
+            JOYPAD_1 => self.controllers.iter_mut().for_each(|controller| controller.write(data)),
+            0x4000..=0x4013 | APU_STATUS | APU_FRAME_COUNTER => self.apu.write_register(addr, data),
+            _ => {}
         }
-        
+    }
+
+    // Cartridge space $4020-$FFFF, decoded by the mapper
+    fn read_cartridge(&mut self, addr: u16) -> Option<u8> {
+        self.cartridge.borrow_mut().cpu_read(addr)
+    }
+
+    // Writes to ROM space are seen by the mapper, which may treat
+    // them as register writes
+    fn write_cartridge(&mut self, addr: u16, data: u8) {
+        self.cartridge.borrow_mut().cpu_write(addr, data);
+    }
+}
+
+impl Bus for NesBus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        let value = match addr {
+            0x0000..=RAM_MIRRORS_END => Some(self.ram[(addr & 0x07FF) as usize]),
+            0x2000..=PPU_REGISTERS_MIRRORS_END => self.read_ppu_register(0x2000 | (addr & 0x0007)),
+            0x4000..=IO_REGISTERS_END => self.read_io_register(addr),
+            _ => self.read_cartridge(addr),
+        };
+
+        let value = value.unwrap_or(self.open_bus);
+        self.open_bus = value;
+        value
     }
 
     fn mem_write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
+
         match addr {
-            0x0000..=0x1FFF => self.ram[addr as usize] = data,
-            0x8000..=0xFFFF => panic!("Attempt to write ROM space"),
-            _ => todo!()
+            0x0000..=RAM_MIRRORS_END => self.ram[(addr & 0x07FF) as usize] = data,
+            0x2000..=PPU_REGISTERS_MIRRORS_END => self.write_ppu_register(0x2000 | (addr & 0x0007), data),
+            0x4000..=IO_REGISTERS_END => self.write_io_register(addr, data),
+            _ => self.write_cartridge(addr, data),
         }
     }
+
+    // PPU runs three dots per CPU cycle, APU one step
+    fn tick(&mut self, interrupts: &mut InterruptLines) {
+        for _ in 0..3 {
+            if self.ppu.tick() {
+                (self.frame_callback)(&self.ppu);
+            }
+        }
+
+        interrupts.set_nmi(self.ppu.nmi_line());
+
+        let mut cartridge = self.cartridge.borrow_mut();
+        cartridge.cpu_tick();
+        if cartridge.irq() {
+            interrupts.assert_irq(IrqSource::Mapper);
+        } else {
+            interrupts.release_irq(IrqSource::Mapper);
+        }
+
+        self.apu.tick(cartridge.audio_output());
+        if self.apu.frame_irq() {
+            interrupts.assert_irq(IrqSource::FrameCounter);
+        } else {
+            interrupts.release_irq(IrqSource::FrameCounter);
+        }
+        if self.apu.dmc_irq() {
+            interrupts.assert_irq(IrqSource::Dmc);
+        } else {
+            interrupts.release_irq(IrqSource::Dmc);
+        }
+    }
+
+    fn poll_dma(&mut self) -> Option<u8> {
+        self.dma_page.take()
+    }
+
+    fn poll_dmc_dma(&mut self) -> Option<u16> {
+        self.apu.dmc_request()
+    }
+
+    fn complete_dmc_dma(&mut self, data: u8) {
+        self.apu.dmc_fill(data);
+    }
 }
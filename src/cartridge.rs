@@ -0,0 +1,184 @@
+// Implemented here:
+// - NROM, mapper 0
+pub mod nrom;
+// - MMC1, mapper 1
+pub mod mmc1;
+// - UxROM, mapper 2
+pub mod uxrom;
+// - CNROM, mapper 3
+pub mod cnrom;
+// - AxROM, mapper 7
+pub mod axrom;
+// - MMC3, mapper 4
+pub mod mmc3;
+// - MMC5, mapper 5
+pub mod mmc5;
+// - VRC2 and VRC4, mappers 21, 22, 23 and 25
+pub mod vrc2_4;
+// - VRC6, mappers 24 and 26
+pub mod vrc6;
+// - VRC7, mapper 85
+pub mod vrc7;
+
+// IRQ counter shared by Konami VRC boards
+mod vrc_irq;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ppu::memory::mirrored_page;
+use crate::rom::{Mirroring, Rom, RomError};
+use axrom::Axrom;
+use cnrom::Cnrom;
+use mmc1::Mmc1;
+use mmc3::Mmc3;
+use mmc5::Mmc5;
+use nrom::Nrom;
+use uxrom::Uxrom;
+use vrc2_4::Vrc2_4;
+use vrc6::Vrc6;
+use vrc7::Vrc7;
+
+// Cartridge is connected to both CPU and PPU buses
+pub type Cartridge = Rc<RefCell<dyn Mapper>>;
+
+// Board hardware sitting between ROM chips and console, described here:
+// https://www.nesdev.org/wiki/Mapper
+pub trait Mapper {
+    // CPU side, $4020-$FFFF. Returns None when nothing on the
+    // cartridge responds, so open bus value is seen.
+    fn cpu_read(&mut self, addr: u16) -> Option<u8>;
+    fn cpu_write(&mut self, addr: u16, data: u8);
+
+    // PPU side, pattern tables at $0000-$1FFF
+    fn ppu_read(&mut self, addr: u16) -> u8;
+    fn ppu_write(&mut self, addr: u16, data: u8);
+
+    // Queried on every nametable access, mappers may switch it any time
+    fn mirroring(&self) -> Mirroring;
+
+    // VRAM page shown in nametable 0-3. Console has two 1KB pages,
+    // four screen boards add another two.
+    fn nametable_page(&self, table: u16) -> u16 {
+        mirrored_page(self.mirroring(), table)
+    }
+
+    // Boards with their own nametable memory answer fetches
+    // of $2000-$3EFF instead of VRAM
+    fn read_nametable(&mut self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    // Returns true when write was taken by the board
+    fn write_nametable(&mut self, _addr: u16, _data: u8) -> bool {
+        false
+    }
+
+    // CPU writes to PPU registers, seen by boards snooping on PPU state
+    fn ppu_register_write(&mut self, _addr: u16, _data: u8) {}
+
+    // Called with every address PPU puts on its bus, $0000-$3EFF,
+    // for boards which watch PPU fetches
+    fn ppu_bus_address(&mut self, _addr: u16) {}
+
+    // Called after every CPU cycle, for boards which count cycles
+    // or need to know timing of register writes
+    fn cpu_tick(&mut self) {}
+
+    // State of cartridge IRQ output
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Expansion audio output, on the same scale as APU mixer output
+    fn audio_output(&self) -> f32 {
+        0.0
+    }
+
+    // Memory kept alive by battery, saved between sessions
+    fn battery_ram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    fn load_battery_ram(&mut self, _data: &[u8]) {}
+
+    // Copies trainer to $7000-$71FF of PRG RAM at power on
+    fn load_trainer(&mut self, _trainer: &[u8]) {}
+}
+
+// Builds board described by ROM header
+pub fn new(mut rom: Rom) -> Result<Cartridge, RomError> {
+    let prg_size = rom.prg_rom.len();
+    if prg_size == 0 || !prg_size.is_multiple_of(prg_bank_size(rom.mapper)) {
+        return Err(RomError::InvalidSize("PRG ROM"));
+    }
+
+    // Trainer implies RAM at $6000-$7FFF, even if header doesn't say so
+    let trainer = rom.trainer.take();
+    if trainer.is_some() && rom.prg_ram_size + rom.prg_nvram_size == 0 {
+        rom.prg_ram_size = PRG_RAM_SIZE;
+    }
+
+    let cartridge: Cartridge = match rom.mapper {
+        0 => Rc::new(RefCell::new(Nrom::new(rom))),
+        1 => Rc::new(RefCell::new(Mmc1::new(rom))),
+        2 => Rc::new(RefCell::new(Uxrom::new(rom))),
+        3 => Rc::new(RefCell::new(Cnrom::new(rom))),
+        4 => Rc::new(RefCell::new(Mmc3::new(rom))),
+        5 => Rc::new(RefCell::new(Mmc5::new(rom))),
+        7 => Rc::new(RefCell::new(Axrom::new(rom))),
+        21 | 22 | 23 | 25 => Rc::new(RefCell::new(Vrc2_4::new(rom))),
+        24 | 26 => Rc::new(RefCell::new(Vrc6::new(rom))),
+        85 => Rc::new(RefCell::new(Vrc7::new(rom))),
+        mapper => return Err(RomError::UnsupportedMapper(mapper)),
+    };
+
+    if let Some(trainer) = trainer {
+        cartridge.borrow_mut().load_trainer(&trainer);
+    }
+    Ok(cartridge)
+}
+
+// Smallest PRG ROM unit each board switches, images have to hold whole banks
+fn prg_bank_size(mapper: u16) -> usize {
+    match mapper {
+        4 | 5 | 21..=26 | 85 => 0x2000,
+        7 => 0x8000,
+        _ => 0x4000,
+    }
+}
+
+// Cartridges without CHR ROM provide RAM, 8KB unless header says otherwise
+pub(crate) fn chr_memory(rom: &Rom) -> (Vec<u8>, bool) {
+    if !rom.chr_rom.is_empty() {
+        return (rom.chr_rom.clone(), false);
+    }
+
+    let size = match rom.chr_ram_size + rom.chr_nvram_size {
+        0 => 8192,
+        size => size,
+    };
+    (vec![0; size], true)
+}
+
+const PRG_RAM_SIZE: usize = 0x2000;
+
+// Trainer lands at $7000, in the second half of the first RAM bank
+const TRAINER_OFFSET: usize = 0x1000;
+
+pub(crate) fn load_trainer(prg_ram: &mut [u8], trainer: &[u8]) {
+    let len = prg_ram.len();
+    for (i, &byte) in trainer.iter().enumerate().take(len) {
+        prg_ram[(TRAINER_OFFSET + i) % len] = byte;
+    }
+}
+
+// Discrete boards latch value from data bus without disabling ROM,
+// both drive the bus and 0 wins. Most boards are wired this way, only
+// NES 2.0 submapper 1 marks ones that avoid it, described here:
+// https://www.nesdev.org/wiki/Bus_conflict
+pub(crate) const NO_BUS_CONFLICTS_SUBMAPPER: u8 = 1;
+
+pub(crate) fn bus_conflict(rom_value: u8, data: u8) -> u8 {
+    rom_value & data
+}
@@ -0,0 +1,78 @@
+// AxROM boards, described here:
+// https://www.nesdev.org/wiki/AxROM
+//
+// $8000-$FFFF 32KB switchable PRG ROM bank
+// Writes to $8000-$FFFF select PRG bank in bits 0-2 and
+// nametable used for single screen mirroring in bit 4
+use crate::cartridge::{bus_conflict, chr_memory, Mapper, NO_BUS_CONFLICTS_SUBMAPPER};
+use crate::rom::{Mirroring, Rom};
+
+const PRG_BANK_SIZE: usize = 0x8000;
+
+const PRG_BANK: u8 = 0b0000_0111;
+const NAMETABLE: u8 = 0b0001_0000;
+
+pub struct Axrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    bus_conflicts: bool,
+    register: u8,
+}
+
+impl Axrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            bus_conflicts: rom.submapper != NO_BUS_CONFLICTS_SUBMAPPER,
+            register: 0,
+        }
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let bank = (self.register & PRG_BANK) as usize;
+        (bank * PRG_BANK_SIZE + (addr - 0x8000) as usize) % self.prg_rom.len()
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, mut data: u8) {
+        if addr < 0x8000 {
+            return;
+        }
+        if self.bus_conflicts {
+            data = bus_conflict(self.prg_rom[self.prg_rom_addr(addr)], data);
+        }
+        self.register = data;
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.register & NAMETABLE != 0 {
+            Mirroring::SingleScreenUpper
+        } else {
+            Mirroring::SingleScreenLower
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// CNROM boards, described here:
+// https://www.nesdev.org/wiki/CNROM
+//
+// $8000-$FFFF 16KB PRG ROM mirrored twice, or 32KB
+// PPU $0000-$1FFF 8KB switchable CHR ROM bank
+// Writes to $8000-$FFFF select CHR bank
+use crate::cartridge::{bus_conflict, chr_memory, Mapper, NO_BUS_CONFLICTS_SUBMAPPER};
+use crate::rom::{Mirroring, Rom};
+
+const CHR_BANK_SIZE: usize = 0x2000;
+
+pub struct Cnrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    chr_bank: usize,
+}
+
+impl Cnrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: rom.screen_mirroring,
+            bus_conflicts: rom.submapper != NO_BUS_CONFLICTS_SUBMAPPER,
+            chr_bank: 0,
+        }
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        (addr - 0x8000) as usize % self.prg_rom.len()
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        (self.chr_bank * CHR_BANK_SIZE + addr as usize) % self.chr.len()
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, mut data: u8) {
+        if addr < 0x8000 {
+            return;
+        }
+        if self.bus_conflicts {
+            data = bus_conflict(self.prg_rom[self.prg_rom_addr(addr)], data);
+        }
+        self.chr_bank = data as usize;
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.chr_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
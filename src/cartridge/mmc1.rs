@@ -0,0 +1,214 @@
+// MMC1 boards (SxROM), described here:
+// https://www.nesdev.org/wiki/MMC1
+//
+// Registers are loaded serially through writes to $8000-$FFFF,
+// bit 0 of five consecutive writes goes into shift register and
+// the fifth write copies it into register selected by its address:
+// $8000-$9FFF control, $A000-$BFFF CHR bank 0, $C000-$DFFF CHR bank 1,
+// $E000-$FFFF PRG bank. Writing value with bit 7 set resets shifting.
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const CHR_BANK_SIZE: usize = 0x1000;
+const PRG_RAM_BANK_SIZE: usize = 0x2000;
+
+const CONTROL_MIRRORING: u8 = 0b0_0011;
+const CONTROL_PRG_MODE: u8 = 0b0_1100;
+const CONTROL_CHR_4K: u8 = 0b1_0000;
+const PRG_BANK: u8 = 0b0_1111;
+const PRG_RAM_DISABLE: u8 = 0b1_0000;
+
+// Boards with 8KB CHR use upper bits of CHR bank registers
+// to extend PRG ROM and PRG RAM:
+// SNROM: bit 4 disables PRG RAM
+// SOROM: bit 3 selects 8KB PRG RAM bank out of 16KB
+// SUROM: bit 4 selects 256KB PRG ROM half out of 512KB
+// SXROM: bits 2-3 select 8KB PRG RAM bank out of 32KB, bit 4 as SUROM
+// In 4KB CHR mode these bits are taken from CHR bank 0, games
+// keep both registers in sync.
+const OUTER_PRG_SIZE: usize = 0x40000;
+
+pub struct Mmc1 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    battery: bool,
+
+    shift: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+
+    // Writes on consecutive cycles, e.g. by read-modify-write
+    // instructions, are ignored except for the first one
+    write_this_cycle: bool,
+    write_previous_cycle: bool,
+}
+
+impl Mmc1 {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            battery: rom.battery,
+            shift: 0,
+            shift_count: 0,
+            // Last bank is fixed at $C000 on power on
+            control: CONTROL_PRG_MODE,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            write_this_cycle: false,
+            write_previous_cycle: false,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        if data & 0x80 != 0 {
+            self.shift = 0;
+            self.shift_count = 0;
+            self.control |= CONTROL_PRG_MODE;
+            return;
+        }
+
+        self.shift |= (data & 1) << self.shift_count;
+        self.shift_count += 1;
+        if self.shift_count < 5 {
+            return;
+        }
+
+        match addr {
+            0x8000..=0x9FFF => self.control = self.shift,
+            0xA000..=0xBFFF => self.chr_bank_0 = self.shift,
+            0xC000..=0xDFFF => self.chr_bank_1 = self.shift,
+            _ => self.prg_bank = self.shift,
+        }
+        self.shift = 0;
+        self.shift_count = 0;
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let outer = if self.prg_rom.len() > OUTER_PRG_SIZE {
+            (self.chr_bank_0 & 0b1_0000) as usize
+        } else {
+            0
+        };
+        let bank = (self.prg_bank & PRG_BANK) as usize;
+        let upper_half = addr >= 0xC000;
+        // Last bank of selected 256KB half, images needn't be a power of two
+        let last_bank = (outer | PRG_BANK as usize).min((self.prg_rom.len() / PRG_BANK_SIZE).saturating_sub(1));
+
+        let bank = match ((self.control & CONTROL_PRG_MODE) >> 2, upper_half) {
+            // 32KB mode ignores low bit of bank number
+            (0 | 1, _) => (bank & !1) | upper_half as usize,
+            // First bank fixed at $8000
+            (2, false) => 0,
+            (2, true) => bank,
+            // Last bank fixed at $C000
+            (_, false) => bank,
+            (_, true) => last_bank,
+        };
+
+        ((outer | bank) * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn prg_ram_addr(&self, addr: u16) -> Option<usize> {
+        let disabled = self.prg_bank & PRG_RAM_DISABLE != 0
+            || (self.chr_is_ram && self.prg_rom.len() <= OUTER_PRG_SIZE && self.chr_bank_0 & 0b1_0000 != 0);
+        if disabled || self.prg_ram.is_empty() {
+            return None;
+        }
+
+        let bank = match self.prg_ram.len() {
+            0x4000 => (self.chr_bank_0 >> 3) & 0b01,
+            0x8000 => (self.chr_bank_0 >> 2) & 0b11,
+            _ => 0,
+        } as usize;
+
+        Some((bank * PRG_RAM_BANK_SIZE + (addr - 0x6000) as usize) % self.prg_ram.len())
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        let bank = if self.control & CONTROL_CHR_4K != 0 {
+            if addr < 0x1000 { self.chr_bank_0 } else { self.chr_bank_1 }
+        } else {
+            // 8KB mode ignores low bit of bank number
+            (self.chr_bank_0 & !1) | (addr >= 0x1000) as u8
+        } as usize;
+
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram_addr(addr).map(|addr| self.prg_ram[addr]),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => {
+                if let Some(addr) = self.prg_ram_addr(addr) {
+                    self.prg_ram[addr] = data;
+                }
+            }
+            0x8000..=0xFFFF => {
+                if !self.write_previous_cycle {
+                    self.write_register(addr, data);
+                }
+                self.write_this_cycle = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.chr_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & CONTROL_MIRRORING {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn cpu_tick(&mut self) {
+        self.write_previous_cycle = self.write_this_cycle;
+        self.write_this_cycle = false;
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
@@ -0,0 +1,223 @@
+// MMC3 boards (TxROM), described here:
+// https://www.nesdev.org/wiki/MMC3
+//
+// Registers, selected by address range and parity:
+// $8000 bank select     $8001 bank data
+// $A000 mirroring       $A001 PRG RAM protect
+// $C000 IRQ latch       $C001 IRQ reload
+// $E000 IRQ disable     $E001 IRQ enable
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const BANK_SELECT_REGISTER: u8 = 0b0000_0111;
+const BANK_SELECT_PRG_MODE: u8 = 0b0100_0000;
+const BANK_SELECT_CHR_INVERSION: u8 = 0b1000_0000;
+
+const PRG_RAM_ENABLE: u8 = 0b1000_0000;
+const PRG_RAM_WRITE_PROTECT: u8 = 0b0100_0000;
+
+// A12 has to stay low for this many CPU cycles before its rise
+// clocks IRQ counter, which filters out toggling within a scanline
+const A12_LOW_CYCLES: u8 = 3;
+
+pub struct Mmc3 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    battery: bool,
+    four_screen: bool,
+
+    bank_select: u8,
+    // R0-R1 2KB CHR banks, R2-R5 1KB CHR banks, R6-R7 8KB PRG banks
+    banks: [u8; 8],
+    mirroring: Mirroring,
+    prg_ram_protect: u8,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12: bool,
+    a12_low_cycles: u8,
+}
+
+impl Mmc3 {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            battery: rom.battery,
+            four_screen: rom.screen_mirroring == Mirroring::FourScreen,
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring: rom.screen_mirroring,
+            // Power on state is undefined, games expecting
+            // working RAM don't always enable it
+            prg_ram_protect: PRG_RAM_ENABLE,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12: false,
+            a12_low_cycles: 0,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        let odd = addr & 1 != 0;
+        match (addr, odd) {
+            (0x8000..=0x9FFF, false) => self.bank_select = data,
+            (0x8000..=0x9FFF, true) => self.banks[(self.bank_select & BANK_SELECT_REGISTER) as usize] = data,
+            (0xA000..=0xBFFF, false) => {
+                if !self.four_screen {
+                    self.mirroring = if data & 1 != 0 { Mirroring::Horizontal } else { Mirroring::Vertical };
+                }
+            }
+            (0xA000..=0xBFFF, true) => self.prg_ram_protect = data,
+            (0xC000..=0xDFFF, false) => self.irq_latch = data,
+            (0xC000..=0xDFFF, true) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (_, false) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (_, true) => self.irq_enabled = true,
+        }
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let bank_count = self.prg_rom.len() / PRG_BANK_SIZE;
+        let second_last = bank_count.saturating_sub(2);
+        let swapped = self.bank_select & BANK_SELECT_PRG_MODE != 0;
+
+        let bank = match (addr, swapped) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => (self.banks[6] & 0x3F) as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => (self.banks[7] & 0x3F) as usize,
+            _ => bank_count.saturating_sub(1),
+        };
+
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        // Inversion swaps 2KB and 1KB halves
+        let addr = if self.bank_select & BANK_SELECT_CHR_INVERSION != 0 { addr ^ 0x1000 } else { addr };
+
+        let bank = match addr {
+            0x0000..=0x07FF => (self.banks[0] & 0xFE) as usize | (addr as usize >> 10 & 1),
+            0x0800..=0x0FFF => (self.banks[1] & 0xFE) as usize | (addr as usize >> 10 & 1),
+            _ => self.banks[2 + ((addr as usize - 0x1000) >> 10)] as usize,
+        };
+
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn prg_ram_addr(&self, addr: u16) -> Option<usize> {
+        if self.prg_ram_protect & PRG_RAM_ENABLE == 0 || self.prg_ram.is_empty() {
+            return None;
+        }
+        Some((addr - 0x6000) as usize % self.prg_ram.len())
+    }
+
+    // Counter is reloaded when it reaches zero or reload was requested,
+    // IRQ is raised whenever it is zero after clocking
+    fn clock_irq_counter(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF => self.prg_ram_addr(addr).map(|addr| self.prg_ram[addr]),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF => {
+                if self.prg_ram_protect & PRG_RAM_WRITE_PROTECT != 0 {
+                    return;
+                }
+                if let Some(addr) = self.prg_ram_addr(addr) {
+                    self.prg_ram[addr] = data;
+                }
+            }
+            0x8000..=0xFFFF => self.write_register(addr, data),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.chr_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn ppu_bus_address(&mut self, addr: u16) {
+        let a12 = addr & 0x1000 != 0;
+        if a12 && !self.a12 && self.a12_low_cycles >= A12_LOW_CYCLES {
+            self.clock_irq_counter();
+        }
+        if !a12 && self.a12 {
+            self.a12_low_cycles = 0;
+        }
+        self.a12 = a12;
+    }
+
+    fn cpu_tick(&mut self) {
+        if !self.a12 {
+            self.a12_low_cycles = self.a12_low_cycles.saturating_add(1);
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
@@ -0,0 +1,501 @@
+// MMC5 boards (ExROM), described here:
+// https://www.nesdev.org/wiki/MMC5
+//
+// Besides banking, MMC5 watches PPU bus to tell scanlines apart
+// and which fetch is in progress. Scanline starts with the third
+// consecutive read of the same nametable address, which happens
+// when two unused fetches at the end of a line are followed by
+// the first fetch of the next one.
+
+// Extra pulse channels and PCM implemented here
+mod audio;
+
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+use audio::Audio;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const EXRAM_SIZE: usize = 0x400;
+const ATTRIBUTE_TABLE: usize = 0x3C0;
+
+// Reads of a line, counted from the one detecting it: 32 tiles
+// with 4 reads each, 8 sprites with 4 reads each, then two tiles
+// for the next line
+const BACKGROUND_READS: u16 = 128;
+const SPRITE_READS: u16 = 32;
+
+const EXRAM_NAMETABLE: u8 = 0;
+const EXRAM_EXTENDED_ATTRIBUTES: u8 = 1;
+const EXRAM_READ_ONLY: u8 = 3;
+
+const SPLIT_ENABLE: u8 = 0b1000_0000;
+const SPLIT_RIGHT: u8 = 0b0100_0000;
+const SPLIT_TILES: u8 = 0b0001_1111;
+
+const ROM_BANK: u8 = 0b1000_0000;
+
+// PPU is considered idle when it doesn't read for this many CPU cycles
+const IDLE_CYCLES: u8 = 3;
+
+pub struct Mmc5 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    battery: bool,
+    exram: [u8; EXRAM_SIZE],
+
+    prg_mode: u8,
+    chr_mode: u8,
+    prg_ram_protect: [u8; 2],
+    exram_mode: u8,
+    // Two bits per nametable: VRAM page 0 or 1, ExRAM, fill mode
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    // $5113-$5117, $6000 window first
+    prg_banks: [u8; 5],
+    // $5120-$5127 sprite set A, $5128-$512B background set B,
+    // with upper bits from $5130 at the time of write
+    chr_banks: [u16; 12],
+    chr_upper: u8,
+    last_chr_set_b: bool,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    multiplicand: u8,
+    multiplier: u8,
+
+    // Snooped from PPUCTRL and PPUMASK writes
+    tall_sprites: bool,
+    rendering_enabled: bool,
+
+    // PPU bus tracking
+    in_frame: bool,
+    scanline: u8,
+    idle_cycles: u8,
+    last_addr: u16,
+    nametable_matches: u8,
+    line_reads: u16,
+    column: u8,
+    // ExRAM byte of tile being fetched in extended attribute mode
+    tile_attribute: u8,
+    // Tile being fetched comes from split region
+    split_tile: bool,
+    split_y: u8,
+
+    audio: Audio,
+}
+
+impl Mmc5 {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            battery: rom.battery,
+            exram: [0; EXRAM_SIZE],
+            // Games start with 8KB banks and the last one at $E000
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: [0; 2],
+            exram_mode: 0,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0xFF, 0xFF, 0xFF, 0xFF],
+            chr_banks: [0; 12],
+            chr_upper: 0,
+            last_chr_set_b: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            tall_sprites: false,
+            rendering_enabled: false,
+            in_frame: false,
+            scanline: 0,
+            idle_cycles: 0,
+            last_addr: 0,
+            nametable_matches: 0,
+            line_reads: 0,
+            column: 0,
+            tile_attribute: 0,
+            split_tile: false,
+            split_y: 0,
+            audio: Audio::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5015 => self.audio.write_register(addr, data),
+            0x5100 => self.prg_mode = data & 0b11,
+            0x5101 => self.chr_mode = data & 0b11,
+            0x5102 => self.prg_ram_protect[0] = data & 0b11,
+            0x5103 => self.prg_ram_protect[1] = data & 0b11,
+            0x5104 => self.exram_mode = data & 0b11,
+            0x5105 => self.nametable_mapping = data,
+            0x5106 => self.fill_tile = data,
+            0x5107 => self.fill_attribute = data & 0b11,
+            0x5113..=0x5117 => self.prg_banks[(addr - 0x5113) as usize] = data,
+            0x5120..=0x512B => {
+                self.chr_banks[(addr - 0x5120) as usize] = data as u16 | ((self.chr_upper as u16 & 0b11) << 8);
+                self.last_chr_set_b = addr >= 0x5128;
+            }
+            0x5130 => self.chr_upper = data & 0b11,
+            0x5200 => self.split_control = data,
+            0x5201 => self.split_scroll = data,
+            0x5202 => self.split_bank = data,
+            0x5203 => self.irq_compare = data,
+            0x5204 => self.irq_enabled = data & 0x80 != 0,
+            0x5205 => self.multiplicand = data,
+            0x5206 => self.multiplier = data,
+            0x5C00..=0x5FFF => {
+                let index = (addr - 0x5C00) as usize;
+                match self.exram_mode {
+                    // Nametable modes accept writes only while PPU renders
+                    EXRAM_NAMETABLE | EXRAM_EXTENDED_ATTRIBUTES => {
+                        self.exram[index] = if self.in_frame { data } else { 0 };
+                    }
+                    EXRAM_READ_ONLY => {}
+                    _ => self.exram[index] = data,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read_register(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x5010 | 0x5015 => Some(self.audio.read_register(addr)),
+            0x5204 => {
+                let status = ((self.irq_pending as u8) << 7) | ((self.in_frame as u8) << 6);
+                self.irq_pending = false;
+                Some(status)
+            }
+            0x5205 => Some((self.multiplicand as u16 * self.multiplier as u16) as u8),
+            0x5206 => Some(((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8),
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Some(self.exram[(addr - 0x5C00) as usize]),
+            _ => None,
+        }
+    }
+
+    // Returns ROM or RAM index for CPU address in $6000-$FFFF
+    fn prg_addr(&self, addr: u16) -> (bool, usize) {
+        let (register, size) = match (self.prg_mode, addr) {
+            (_, 0x6000..=0x7FFF) => (0, 0x2000),
+            (0, _) => (4, 0x8000),
+            (1, 0x8000..=0xBFFF) => (2, 0x4000),
+            (1, _) => (4, 0x4000),
+            (2, 0x8000..=0xBFFF) => (2, 0x4000),
+            (2, 0xC000..=0xDFFF) => (3, 0x2000),
+            (2, _) => (4, 0x2000),
+            (_, _) => (1 + ((addr - 0x8000) / 0x2000) as usize, 0x2000),
+        };
+
+        let bank = self.prg_banks[register];
+        // $5117 always maps ROM, $5113 always RAM
+        let rom = register == 4 || (register != 0 && bank & ROM_BANK != 0);
+        let bank = (bank & !ROM_BANK) as usize & !(size / PRG_BANK_SIZE - 1);
+        let index = bank * PRG_BANK_SIZE + (addr as usize & (size - 1));
+        (rom, index)
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    fn chr_addr(&self, addr: u16, set_b: bool) -> usize {
+        let addr = addr as usize;
+        let (size, register) = match (self.chr_mode, set_b) {
+            (0, false) => (0x2000, 7),
+            (1, false) => (0x1000, 3 + (addr / 0x1000) * 4),
+            (2, false) => (0x0800, 1 + (addr / 0x0800) * 2),
+            (_, false) => (0x0400, addr / 0x0400),
+            // Background set covers 4KB, mirrored in both pattern tables
+            (0, true) => (0x2000, 11),
+            (1, true) => (0x1000, 11),
+            (2, true) => (0x0800, 9 + ((addr & 0x0FFF) / 0x0800) * 2),
+            (_, true) => (0x0400, 8 + (addr & 0x0FFF) / 0x0400),
+        };
+
+        (self.chr_banks[register] as usize * size + (addr & (size - 1))) % self.chr.len()
+    }
+
+    fn sprite_fetch(&self) -> bool {
+        (BACKGROUND_READS..BACKGROUND_READS + SPRITE_READS).contains(&self.line_reads)
+    }
+
+    fn background_fetch(&self) -> bool {
+        self.in_frame && !self.sprite_fetch()
+    }
+
+    fn pattern_addr(&self, addr: u16) -> usize {
+        if self.background_fetch() && self.split_tile {
+            let fine_y = (self.split_y & 0b111) as usize;
+            return (self.split_bank as usize * 0x1000 + (addr as usize & 0x0FF8) + fine_y) % self.chr.len();
+        }
+        if self.background_fetch() && self.exram_mode == EXRAM_EXTENDED_ATTRIBUTES {
+            let bank = (self.tile_attribute & 0b0011_1111) as usize | ((self.chr_upper as usize) << 6);
+            return (bank * 0x1000 + (addr as usize & 0x0FFF)) % self.chr.len();
+        }
+
+        // Background set is used only with 8x16 sprites, otherwise
+        // the last written set is used for everything
+        let set_b = if self.in_frame && self.tall_sprites {
+            !self.sprite_fetch()
+        } else {
+            self.last_chr_set_b
+        };
+        self.chr_addr(addr, set_b)
+    }
+
+    fn detect_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline = self.scanline.wrapping_add(1);
+            if self.scanline == self.irq_compare {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline = 0;
+        }
+        self.line_reads = 0;
+    }
+
+    fn leave_frame(&mut self) {
+        self.in_frame = false;
+        self.nametable_matches = 0;
+    }
+
+    fn in_split(&self, column: u8) -> bool {
+        if self.split_control & SPLIT_ENABLE == 0 || self.exram_mode > EXRAM_EXTENDED_ATTRIBUTES {
+            return false;
+        }
+
+        let tiles = self.split_control & SPLIT_TILES;
+        if self.split_control & SPLIT_RIGHT != 0 {
+            column >= tiles
+        } else {
+            column < tiles
+        }
+    }
+
+    // Called for each background tile fetch. Detecting read fetches
+    // the third tile, first two were fetched on the previous line.
+    fn start_tile(&mut self, offset: usize) {
+        self.column = if self.line_reads < BACKGROUND_READS {
+            2 + self.line_reads / 4
+        } else {
+            self.line_reads.saturating_sub(BACKGROUND_READS + SPRITE_READS) / 4
+        } as u8;
+        self.tile_attribute = self.exram[offset];
+
+        self.split_tile = self.in_frame && self.in_split(self.column);
+        if self.split_tile {
+            let line = self.scanline as u16 + (self.column < 2) as u16;
+            self.split_y = ((self.split_scroll as u16 + line) % 240) as u8;
+        }
+    }
+
+    fn split_nametable(&self, attribute: bool) -> u8 {
+        let row = (self.split_y / 8) as usize;
+        let column = (self.column & 0x1F) as usize;
+
+        if attribute {
+            let byte = self.exram[ATTRIBUTE_TABLE + (row / 4) * 8 + column / 4];
+            let shift = ((row & 2) << 1) | (column & 2);
+            palette_byte((byte >> shift) & 0b11)
+        } else {
+            self.exram[row * 32 + column]
+        }
+    }
+}
+
+// Attribute byte giving the same palette to all four quadrants
+fn palette_byte(palette: u8) -> u8 {
+    palette * 0b0101_0101
+}
+
+impl Mapper for Mmc5 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x5000..=0x5FFF => self.read_register(addr),
+            0x6000..=0xFFFF => {
+                let (rom, index) = self.prg_addr(addr);
+                let data = if rom {
+                    self.prg_rom[index % self.prg_rom.len()]
+                } else if self.prg_ram.is_empty() {
+                    return None;
+                } else {
+                    self.prg_ram[index % self.prg_ram.len()]
+                };
+
+                // Fetching NMI vector means PPU entered vertical blank
+                if let 0xFFFA | 0xFFFB = addr {
+                    self.leave_frame();
+                }
+                if let 0x8000..=0xBFFF = addr {
+                    self.audio.pcm_read(data);
+                }
+                Some(data)
+            }
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5FFF => self.write_register(addr, data),
+            0x6000..=0xDFFF => {
+                let (rom, index) = self.prg_addr(addr);
+                if !rom && self.prg_ram_writable() && !self.prg_ram.is_empty() {
+                    let len = self.prg_ram.len();
+                    self.prg_ram[index % len] = data;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.pattern_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.pattern_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.nametable_mapping {
+            0x00 => Mirroring::SingleScreenLower,
+            0x55 => Mirroring::SingleScreenUpper,
+            0x50 => Mirroring::Horizontal,
+            _ => Mirroring::Vertical,
+        }
+    }
+
+    fn nametable_page(&self, table: u16) -> u16 {
+        ((self.nametable_mapping >> (table * 2)) & 1) as u16
+    }
+
+    fn read_nametable(&mut self, addr: u16) -> Option<u8> {
+        let table = (addr & 0x0FFF) / 0x0400;
+        let offset = (addr & 0x03FF) as usize;
+        let attribute = offset >= ATTRIBUTE_TABLE;
+
+        if self.background_fetch() && self.split_tile {
+            return Some(self.split_nametable(attribute));
+        }
+        if self.background_fetch() && attribute && self.exram_mode == EXRAM_EXTENDED_ATTRIBUTES {
+            return Some(palette_byte(self.tile_attribute >> 6));
+        }
+
+        match (self.nametable_mapping >> (table * 2)) & 0b11 {
+            2 if self.exram_mode <= EXRAM_EXTENDED_ATTRIBUTES => Some(self.exram[offset]),
+            2 => Some(0),
+            3 if attribute => Some(palette_byte(self.fill_attribute)),
+            3 => Some(self.fill_tile),
+            _ => None,
+        }
+    }
+
+    fn write_nametable(&mut self, addr: u16, data: u8) -> bool {
+        let table = (addr & 0x0FFF) / 0x0400;
+        match (self.nametable_mapping >> (table * 2)) & 0b11 {
+            2 => {
+                if self.exram_mode <= EXRAM_EXTENDED_ATTRIBUTES {
+                    self.exram[(addr & 0x03FF) as usize] = data;
+                }
+                true
+            }
+            3 => true,
+            _ => false,
+        }
+    }
+
+    fn ppu_bus_address(&mut self, addr: u16) {
+        self.idle_cycles = 0;
+        self.line_reads = self.line_reads.saturating_add(1);
+
+        let addr = if addr < 0x2000 { addr } else { 0x2000 | (addr & 0x0FFF) };
+        if addr == self.last_addr && addr >= 0x2000 {
+            self.nametable_matches += 1;
+            if self.nametable_matches == 2 && self.rendering_enabled {
+                self.detect_scanline();
+            }
+        } else {
+            self.nametable_matches = 0;
+        }
+        self.last_addr = addr;
+
+        if addr < 0x2000 {
+            return;
+        }
+
+        let offset = (addr & 0x03FF) as usize;
+        if offset < ATTRIBUTE_TABLE && !self.sprite_fetch() {
+            self.start_tile(offset);
+        }
+    }
+
+    fn ppu_register_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x2000 => self.tall_sprites = data & 0b0010_0000 != 0,
+            0x2001 => {
+                self.rendering_enabled = data & 0b0001_1000 != 0;
+                if !self.rendering_enabled {
+                    self.leave_frame();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn cpu_tick(&mut self) {
+        if self.idle_cycles < IDLE_CYCLES {
+            self.idle_cycles += 1;
+            if self.idle_cycles == IDLE_CYCLES {
+                self.leave_frame();
+            }
+        }
+        self.audio.tick();
+    }
+
+    fn irq(&self) -> bool {
+        (self.irq_pending && self.irq_enabled) || self.audio.irq()
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
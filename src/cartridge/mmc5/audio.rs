@@ -0,0 +1,208 @@
+// MMC5 audio, described here:
+// https://www.nesdev.org/wiki/MMC5_audio
+//
+// Two pulse channels like APU ones, without sweep units, and
+// 8-bit PCM channel written directly or fed by reads of $8000-$BFFF.
+
+// Lengths loaded by writes of bits 3-7 to $5003/$5007
+const LENGTH_TABLE: [u8; 32] = [
+    10, 254, 20, 2, 40, 4, 80, 6, 160, 8, 60, 10, 14, 12, 26, 14, //
+    12, 16, 24, 18, 48, 20, 96, 22, 192, 24, 72, 26, 16, 28, 32, 30,
+];
+
+const DUTY_TABLE: [[u8; 8]; 4] = [
+    [0, 1, 0, 0, 0, 0, 0, 0],
+    [0, 1, 1, 0, 0, 0, 0, 0],
+    [0, 1, 1, 1, 1, 0, 0, 0],
+    [1, 0, 0, 1, 1, 1, 1, 1],
+];
+
+// There is no frame counter, envelopes and lengths are clocked
+// at fixed rate of about 240Hz
+const QUARTER_FRAME_CYCLES: u16 = 7457;
+
+const PCM_READ_MODE: u8 = 0b0000_0001;
+const PCM_IRQ_ENABLE: u8 = 0b1000_0000;
+
+#[derive(Default)]
+struct Pulse {
+    enabled: bool,
+    duty: u8,
+    step: u8,
+    period: u16,
+    timer: u16,
+    length: u8,
+    halt: bool,
+    constant_volume: bool,
+    volume: u8,
+    envelope_start: bool,
+    envelope_divider: u8,
+    envelope_decay: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.duty = data >> 6;
+                self.halt = data & 0b0010_0000 != 0;
+                self.constant_volume = data & 0b0001_0000 != 0;
+                self.volume = data & 0b0000_1111;
+            }
+            2 => self.period = (self.period & 0x0700) | data as u16,
+            3 => {
+                self.period = (self.period & 0x00FF) | ((data as u16 & 0b111) << 8);
+                if self.enabled {
+                    self.length = LENGTH_TABLE[(data >> 3) as usize];
+                }
+                self.step = 0;
+                self.envelope_start = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.length = 0;
+        }
+    }
+
+    fn clock_timer(&mut self) {
+        if self.timer == 0 {
+            self.timer = self.period;
+            self.step = (self.step + 1) % 8;
+        } else {
+            self.timer -= 1;
+        }
+    }
+
+    fn clock_quarter_frame(&mut self) {
+        if self.envelope_start {
+            self.envelope_start = false;
+            self.envelope_decay = 15;
+            self.envelope_divider = self.volume;
+        } else if self.envelope_divider == 0 {
+            self.envelope_divider = self.volume;
+            if self.envelope_decay > 0 {
+                self.envelope_decay -= 1;
+            } else if self.halt {
+                self.envelope_decay = 15;
+            }
+        } else {
+            self.envelope_divider -= 1;
+        }
+
+        if !self.halt && self.length > 0 {
+            self.length -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.length == 0 || DUTY_TABLE[self.duty as usize][self.step as usize] == 0 {
+            return 0;
+        }
+        if self.constant_volume {
+            self.volume
+        } else {
+            self.envelope_decay
+        }
+    }
+}
+
+pub(super) struct Audio {
+    pulses: [Pulse; 2],
+    pcm: u8,
+    pcm_control: u8,
+    pcm_irq: bool,
+    cycles: u16,
+}
+
+impl Audio {
+    pub(super) fn new() -> Self {
+        Self {
+            pulses: Default::default(),
+            pcm: 0,
+            pcm_control: 0,
+            pcm_irq: false,
+            cycles: 0,
+        }
+    }
+
+    pub(super) fn write_register(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x5000..=0x5007 => {
+                let pulse = (addr - 0x5000) as usize / 4;
+                self.pulses[pulse].write(addr & 0b11, data);
+            }
+            0x5010 => self.pcm_control = data & (PCM_READ_MODE | PCM_IRQ_ENABLE),
+            // Zero can't be written, it's what raises IRQ in read mode
+            0x5011 if self.pcm_control & PCM_READ_MODE == 0 && data != 0 => self.pcm = data,
+            0x5015 => {
+                self.pulses[0].set_enabled(data & 0b01 != 0);
+                self.pulses[1].set_enabled(data & 0b10 != 0);
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn read_register(&mut self, addr: u16) -> u8 {
+        match addr {
+            0x5010 => {
+                let status = (self.irq() as u8) << 7;
+                self.pcm_irq = false;
+                status
+            }
+            _ => (self.pulses[0].length > 0) as u8 | ((self.pulses[1].length > 0) as u8) << 1,
+        }
+    }
+
+    // Called with every CPU read of $8000-$BFFF
+    pub(super) fn pcm_read(&mut self, data: u8) {
+        if self.pcm_control & PCM_READ_MODE == 0 {
+            return;
+        }
+        if data == 0 {
+            self.pcm_irq = true;
+        } else {
+            self.pcm = data;
+        }
+    }
+
+    pub(super) fn tick(&mut self) {
+        self.cycles += 1;
+        // Timers run at half CPU clock, like APU pulse timers
+        if self.cycles.is_multiple_of(2) {
+            self.pulses.iter_mut().for_each(Pulse::clock_timer);
+        }
+        if self.cycles == QUARTER_FRAME_CYCLES {
+            self.cycles = 0;
+            self.pulses.iter_mut().for_each(Pulse::clock_quarter_frame);
+        }
+    }
+
+    pub(super) fn irq(&self) -> bool {
+        self.pcm_irq && self.pcm_control & PCM_IRQ_ENABLE != 0
+    }
+
+    // Pulses are mixed like APU pulses, PCM about as loud as DMC
+    // with its 7-bit level
+    pub(super) fn output(&self) -> f32 {
+        let pulses = (self.pulses[0].output() + self.pulses[1].output()) as f32;
+        let pulse_out = if pulses == 0.0 {
+            0.0
+        } else {
+            95.88 / (8128.0 / pulses + 100.0)
+        };
+
+        let pcm = (self.pcm / 2) as f32;
+        let pcm_out = if pcm == 0.0 {
+            0.0
+        } else {
+            159.79 / (22638.0 / pcm + 100.0)
+        };
+
+        pulse_out + pcm_out
+    }
+}
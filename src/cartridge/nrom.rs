@@ -0,0 +1,81 @@
+// NROM board, described here:
+// https://www.nesdev.org/wiki/NROM
+//
+// $6000-$7FFF optional PRG RAM, mirrored when smaller than 8KB
+// $8000-$FFFF 16KB PRG ROM mirrored twice, or 32KB
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+
+pub struct Nrom {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    battery: bool,
+}
+
+impl Nrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: rom.screen_mirroring,
+            battery: rom.battery,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                Some(self.prg_ram[(addr - 0x6000) as usize % self.prg_ram.len()])
+            }
+            0x8000..=0xFFFF => Some(self.prg_rom[(addr - 0x8000) as usize % self.prg_rom.len()]),
+            _ => None,
+        }
+    }
+
+    // Writes to ROM have no effect, NROM has no registers
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if let 0x6000..=0x7FFF = addr {
+            if !self.prg_ram.is_empty() {
+                let len = self.prg_ram.len();
+                self.prg_ram[(addr - 0x6000) as usize % len] = data;
+            }
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
@@ -0,0 +1,76 @@
+// UxROM boards, described here:
+// https://www.nesdev.org/wiki/UxROM
+//
+// $8000-$BFFF 16KB switchable PRG ROM bank
+// $C000-$FFFF 16KB PRG ROM bank, fixed to the last one
+// Writes to $8000-$FFFF select the switchable bank
+use crate::cartridge::{bus_conflict, chr_memory, Mapper, NO_BUS_CONFLICTS_SUBMAPPER};
+use crate::rom::{Mirroring, Rom};
+
+const PRG_BANK_SIZE: usize = 0x4000;
+
+pub struct Uxrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    prg_bank: usize,
+}
+
+impl Uxrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: rom.screen_mirroring,
+            bus_conflicts: rom.submapper != NO_BUS_CONFLICTS_SUBMAPPER,
+            prg_bank: 0,
+        }
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xBFFF => self.prg_bank,
+            _ => (self.prg_rom.len() / PRG_BANK_SIZE).saturating_sub(1),
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, mut data: u8) {
+        if addr < 0x8000 {
+            return;
+        }
+        if self.bus_conflicts {
+            data = bus_conflict(self.prg_rom[self.prg_rom_addr(addr)], data);
+        }
+        self.prg_bank = data as usize;
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let len = self.chr.len();
+            self.chr[addr as usize % len] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
@@ -0,0 +1,194 @@
+// Konami VRC2 and VRC4 boards, described here:
+// https://www.nesdev.org/wiki/VRC2_and_VRC4
+//
+// $8000 PRG bank 0      $9000 mirroring, PRG swap mode (VRC4)
+// $A000 PRG bank 1      $B000-$E003 CHR banks, 4 bits per register
+// $F000-$F003 IRQ latch, control and acknowledge (VRC4)
+//
+// Boards connect different CPU address lines to the chip's two
+// register select pins. Mappers 21, 23 and 25 gather several
+// boards, NES 2.0 submapper tells which one it is. Without it,
+// both wirings are decoded together.
+use crate::cartridge::vrc_irq::VrcIrq;
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const PRG_SWAP_MODE: u8 = 0b10;
+
+pub struct Vrc2_4 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    battery: bool,
+
+    vrc2: bool,
+    // CPU address lines of register select pins A0 and A1
+    pins: (u16, u16),
+    // VRC2a ignores the lowest bit of CHR banks
+    chr_shift: u8,
+
+    prg_banks: [u8; 2],
+    prg_swap_mode: bool,
+    chr_banks: [u16; 8],
+    mirroring: Mirroring,
+    // VRC2 boards without RAM have 1-bit latch at $6000
+    microwire_latch: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc2_4 {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+        let pins = match (rom.mapper, rom.submapper) {
+            (21, 1) => (0x02, 0x04),
+            (21, 2) => (0x40, 0x80),
+            (21, _) => (0x42, 0x84),
+            (22, _) => (0x02, 0x01),
+            (23, 1 | 3) => (0x01, 0x02),
+            (23, 2) => (0x04, 0x08),
+            (23, _) => (0x05, 0x0A),
+            (25, 1 | 3) => (0x02, 0x01),
+            (25, 2) => (0x08, 0x04),
+            (_, _) => (0x0A, 0x05),
+        };
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            battery: rom.battery,
+            vrc2: matches!((rom.mapper, rom.submapper), (22, _) | (23 | 25, 3)),
+            pins,
+            chr_shift: (rom.mapper == 22) as u8,
+            prg_banks: [0; 2],
+            prg_swap_mode: false,
+            chr_banks: [0; 8],
+            mirroring: rom.screen_mirroring,
+            microwire_latch: 0,
+            irq: VrcIrq::default(),
+        }
+    }
+
+    // Register 0-3 selected within $x000-$xFFF range
+    fn register(&self, addr: u16) -> u16 {
+        let (a0, a1) = self.pins;
+        (addr & a0 != 0) as u16 | ((addr & a1 != 0) as u16) << 1
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let second_last = (self.prg_rom.len() / PRG_BANK_SIZE).saturating_sub(2);
+        let bank = match (addr, self.prg_swap_mode) {
+            (0x8000..=0x9FFF, false) | (0xC000..=0xDFFF, true) => self.prg_banks[0] as usize,
+            (0x8000..=0x9FFF, true) | (0xC000..=0xDFFF, false) => second_last,
+            (0xA000..=0xBFFF, _) => self.prg_banks[1] as usize,
+            _ => second_last + 1,
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        let bank = (self.chr_banks[addr as usize / CHR_BANK_SIZE] >> self.chr_shift) as usize;
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn write_chr_bank(&mut self, addr: u16, data: u8) {
+        // Each bank is written in halves, $B000/$B001 first bank,
+        // $B002/$B003 second one, $C000 third and so on
+        let register = self.register(addr);
+        let slot = ((addr - 0xB000) / 0x1000 * 2 + register / 2) as usize;
+        let bank = &mut self.chr_banks[slot];
+        if register & 1 == 0 {
+            *bank = (*bank & 0x1F0) | (data & 0x0F) as u16;
+        } else {
+            let high_bits = if self.vrc2 { 0x0F } else { 0x1F };
+            *bank = (*bank & 0x0F) | (((data & high_bits) as u16) << 4);
+        }
+    }
+}
+
+impl Mapper for Vrc2_4 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => Some(self.prg_ram[addr as usize % self.prg_ram.len()]),
+            0x6000..=0x6FFF if self.vrc2 => Some(self.microwire_latch),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            0x6000..=0x7FFF if !self.prg_ram.is_empty() => {
+                let len = self.prg_ram.len();
+                self.prg_ram[addr as usize % len] = data;
+            }
+            0x6000..=0x6FFF if self.vrc2 => self.microwire_latch = data & 1,
+            0x8000..=0x8FFF => self.prg_banks[0] = data & 0x1F,
+            0x9000..=0x9FFF => {
+                let register = self.register(addr);
+                if self.vrc2 {
+                    self.mirroring = if data & 1 == 0 { Mirroring::Vertical } else { Mirroring::Horizontal };
+                } else if register < 2 {
+                    self.mirroring = match data & 0b11 {
+                        0 => Mirroring::Vertical,
+                        1 => Mirroring::Horizontal,
+                        2 => Mirroring::SingleScreenLower,
+                        _ => Mirroring::SingleScreenUpper,
+                    };
+                } else {
+                    self.prg_swap_mode = data & PRG_SWAP_MODE != 0;
+                }
+            }
+            0xA000..=0xAFFF => self.prg_banks[1] = data & 0x1F,
+            0xB000..=0xEFFF => self.write_chr_bank(addr, data),
+            0xF000..=0xFFFF if !self.vrc2 => match self.register(addr) {
+                0 => self.irq.write_latch_low(data),
+                1 => self.irq.write_latch_high(data),
+                2 => self.irq.write_control(data),
+                _ => self.irq.acknowledge(),
+            },
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.chr_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    fn cpu_tick(&mut self) {
+        self.irq.tick();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
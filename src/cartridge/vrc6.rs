@@ -0,0 +1,188 @@
+// Konami VRC6 boards, described here:
+// https://www.nesdev.org/wiki/VRC6
+//
+// $8000 16KB PRG bank   $9000-$B002 audio
+// $B003 PPU banking     $C000 8KB PRG bank
+// $D000-$E003 CHR banks $F000-$F002 IRQ latch, control and acknowledge
+//
+// Mapper 26 swaps register select pins A0 and A1.
+// Nametables taken from CHR ROM ($B003 bit 4) aren't supported.
+
+// Two pulse channels and sawtooth implemented here
+mod audio;
+
+use crate::cartridge::vrc_irq::VrcIrq;
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+use audio::Audio;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const BANKING_CHR_MODE: u8 = 0b0000_0011;
+const BANKING_MIRRORING: u8 = 0b0000_1100;
+const BANKING_CHR_A10: u8 = 0b0010_0000;
+const BANKING_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+pub struct Vrc6 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    battery: bool,
+    swapped_pins: bool,
+
+    prg_16k_bank: u8,
+    prg_8k_bank: u8,
+    chr_banks: [u8; 8],
+    banking: u8,
+    irq: VrcIrq,
+    audio: Audio,
+}
+
+impl Vrc6 {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            battery: rom.battery,
+            swapped_pins: rom.mapper == 26,
+            prg_16k_bank: 0,
+            prg_8k_bank: 0,
+            chr_banks: [0; 8],
+            banking: 0,
+            irq: VrcIrq::default(),
+            audio: Audio::new(),
+        }
+    }
+
+    // Address with register select in the two lowest bits
+    fn register_addr(&self, addr: u16) -> u16 {
+        let addr = addr & 0xF003;
+        if self.swapped_pins {
+            (addr & 0xF000) | ((addr & 1) << 1) | ((addr >> 1) & 1)
+        } else {
+            addr
+        }
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xBFFF => self.prg_16k_bank as usize * 2 + (addr as usize - 0x8000) / PRG_BANK_SIZE,
+            0xC000..=0xDFFF => self.prg_8k_bank as usize,
+            _ => (self.prg_rom.len() / PRG_BANK_SIZE).saturating_sub(1),
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        let slot = addr as usize / CHR_BANK_SIZE;
+        // 2KB banks take their lowest bit from PPU A10, unless told
+        // to behave as mirrored 1KB banks
+        let two_kb_bank = |register: usize| {
+            let bank = self.chr_banks[register];
+            if self.banking & BANKING_CHR_A10 != 0 {
+                (bank & 0xFE) | (slot & 1) as u8
+            } else {
+                bank
+            }
+        };
+
+        let bank = match (self.banking & BANKING_CHR_MODE, slot) {
+            (0, _) => self.chr_banks[slot],
+            (1, _) => two_kb_bank(slot / 2),
+            (_, 0..=3) => self.chr_banks[slot],
+            (_, _) => two_kb_bank(4 + (slot - 4) / 2),
+        };
+        (bank as usize * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.banking & BANKING_PRG_RAM_ENABLE != 0 && !self.prg_ram.is_empty()
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Some(self.prg_ram[addr as usize % self.prg_ram.len()]),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if let 0x6000..=0x7FFF = addr {
+            if self.prg_ram_enabled() {
+                let len = self.prg_ram.len();
+                self.prg_ram[addr as usize % len] = data;
+            }
+            return;
+        }
+
+        match self.register_addr(addr) {
+            0x8000..=0x8003 => self.prg_16k_bank = data & 0x0F,
+            addr @ (0x9000..=0xB002) => self.audio.write_register(addr, data),
+            0xB003 => self.banking = data,
+            0xC000..=0xC003 => self.prg_8k_bank = data & 0x1F,
+            addr @ (0xD000..=0xE003) => {
+                let slot = ((addr - 0xD000) / 0x1000 * 4 + (addr & 3)) as usize;
+                self.chr_banks[slot] = data;
+            }
+            0xF000 => self.irq.write_latch(data),
+            0xF001 => self.irq.write_control(data),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.chr_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking & BANKING_MIRRORING) >> 2 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn cpu_tick(&mut self) {
+        self.irq.tick();
+        self.audio.tick();
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn audio_output(&self) -> f32 {
+        self.audio.output()
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
@@ -0,0 +1,182 @@
+// VRC6 audio, described here:
+// https://www.nesdev.org/wiki/VRC6_audio
+//
+// Two pulse channels with 16-step duty and sawtooth channel,
+// all clocked by CPU and mixed linearly.
+
+const PULSE_MODE: u8 = 0b1000_0000;
+const CHANNEL_ENABLE: u8 = 0b1000_0000;
+
+const FREQUENCY_HALT: u8 = 0b001;
+const FREQUENCY_SHIFT_4: u8 = 0b010;
+const FREQUENCY_SHIFT_8: u8 = 0b100;
+
+// Sawtooth accumulator is reset after this many timer clocks
+const SAWTOOTH_STEPS: u8 = 14;
+
+// Pulse at full volume is about as loud as APU pulse at full
+// volume, which reaches about 0.15 of mixer range
+const LEVEL: f32 = 0.15 / 15.0;
+
+#[derive(Default)]
+struct Timer {
+    period: u16,
+    counter: u16,
+    enabled: bool,
+}
+
+impl Timer {
+    fn write_low(&mut self, data: u8) {
+        self.period = (self.period & 0x0F00) | data as u16;
+    }
+
+    fn write_high(&mut self, data: u8) {
+        self.period = (self.period & 0x00FF) | ((data as u16 & 0x0F) << 8);
+        self.enabled = data & CHANNEL_ENABLE != 0;
+    }
+
+    // Returns true when timer runs out and channel steps forward
+    fn clock(&mut self, shift: u8) -> bool {
+        if self.counter == 0 {
+            self.counter = self.period >> shift;
+            true
+        } else {
+            self.counter -= 1;
+            false
+        }
+    }
+}
+
+#[derive(Default)]
+struct Pulse {
+    timer: Timer,
+    volume: u8,
+    duty: u8,
+    ignore_duty: bool,
+    step: u8,
+}
+
+impl Pulse {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => {
+                self.ignore_duty = data & PULSE_MODE != 0;
+                self.duty = (data >> 4) & 0b111;
+                self.volume = data & 0x0F;
+            }
+            1 => self.timer.write_low(data),
+            _ => {
+                self.timer.write_high(data);
+                if !self.timer.enabled {
+                    self.step = 15;
+                }
+            }
+        }
+    }
+
+    fn clock(&mut self, shift: u8) {
+        if self.timer.enabled && self.timer.clock(shift) {
+            self.step = self.step.checked_sub(1).unwrap_or(15);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.timer.enabled && (self.ignore_duty || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Default)]
+struct Sawtooth {
+    timer: Timer,
+    rate: u8,
+    accumulator: u8,
+    step: u8,
+}
+
+impl Sawtooth {
+    fn write(&mut self, register: u16, data: u8) {
+        match register {
+            0 => self.rate = data & 0b0011_1111,
+            1 => self.timer.write_low(data),
+            _ => {
+                self.timer.write_high(data);
+                if !self.timer.enabled {
+                    self.accumulator = 0;
+                    self.step = 0;
+                }
+            }
+        }
+    }
+
+    // Accumulator grows on every other timer clock
+    fn clock(&mut self, shift: u8) {
+        if !self.timer.enabled || !self.timer.clock(shift) {
+            return;
+        }
+
+        self.step += 1;
+        if self.step == SAWTOOTH_STEPS {
+            self.step = 0;
+            self.accumulator = 0;
+        } else if self.step.is_multiple_of(2) {
+            self.accumulator = self.accumulator.wrapping_add(self.rate);
+        }
+    }
+
+    fn output(&self) -> u8 {
+        self.accumulator >> 3
+    }
+}
+
+pub(super) struct Audio {
+    pulses: [Pulse; 2],
+    sawtooth: Sawtooth,
+    frequency_control: u8,
+}
+
+impl Audio {
+    pub(super) fn new() -> Self {
+        Self {
+            pulses: Default::default(),
+            sawtooth: Sawtooth::default(),
+            frequency_control: 0,
+        }
+    }
+
+    // Takes $9000-$B002 with register select in the lowest bits
+    pub(super) fn write_register(&mut self, addr: u16, data: u8) {
+        let register = addr & 0b11;
+        match addr & 0xF000 {
+            0x9000 if register == 3 => self.frequency_control = data & 0b111,
+            0x9000 => self.pulses[0].write(register, data),
+            0xA000 if register < 3 => self.pulses[1].write(register, data),
+            0xB000 if register < 3 => self.sawtooth.write(register, data),
+            _ => {}
+        }
+    }
+
+    pub(super) fn tick(&mut self) {
+        if self.frequency_control & FREQUENCY_HALT != 0 {
+            return;
+        }
+
+        let shift = if self.frequency_control & FREQUENCY_SHIFT_8 != 0 {
+            8
+        } else if self.frequency_control & FREQUENCY_SHIFT_4 != 0 {
+            4
+        } else {
+            0
+        };
+        self.pulses.iter_mut().for_each(|pulse| pulse.clock(shift));
+        self.sawtooth.clock(shift);
+    }
+
+    pub(super) fn output(&self) -> f32 {
+        let sum = self.pulses[0].output() + self.pulses[1].output() + self.sawtooth.output();
+        sum as f32 * LEVEL
+    }
+}
@@ -0,0 +1,179 @@
+// Konami VRC7 boards, described here:
+// https://www.nesdev.org/wiki/VRC7
+//
+// $8000, $8010 PRG banks at $8000 and $A000
+// $9000 PRG bank at $C000         $9010, $9030 audio register select and data
+// $A000-$D010 CHR banks           $E000 mirroring, audio reset, PRG RAM enable
+// $E010 IRQ latch                 $F000, $F010 IRQ control and acknowledge
+//
+// Boards differ by address line used as the second register select,
+// A4 on VRC7a (submapper 2), A3 on VRC7b (submapper 1).
+
+// FM synthesis implemented here
+mod opll;
+
+use crate::cartridge::vrc_irq::VrcIrq;
+use crate::cartridge::{chr_memory, load_trainer, Mapper};
+use crate::rom::{Mirroring, Rom};
+use opll::Opll;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const CONTROL_MIRRORING: u8 = 0b0000_0011;
+const CONTROL_AUDIO_RESET: u8 = 0b0100_0000;
+const CONTROL_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+pub struct Vrc7 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    battery: bool,
+    // CPU address line of the second register in each range
+    select_line: u16,
+
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+    opll: Opll,
+    opll_register: u8,
+}
+
+impl Vrc7 {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = chr_memory(&rom);
+
+        Self {
+            prg_ram: vec![0; rom.prg_ram_size + rom.prg_nvram_size],
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            battery: rom.battery,
+            select_line: match rom.submapper {
+                1 => 0x08,
+                2 => 0x10,
+                _ => 0x18,
+            },
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::default(),
+            opll: Opll::new(),
+            opll_register: 0,
+        }
+    }
+
+    fn prg_rom_addr(&self, addr: u16) -> usize {
+        let bank = match addr {
+            0x8000..=0xDFFF => self.prg_banks[(addr as usize - 0x8000) / PRG_BANK_SIZE] as usize,
+            _ => (self.prg_rom.len() / PRG_BANK_SIZE).saturating_sub(1),
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_addr(&self, addr: u16) -> usize {
+        let bank = self.chr_banks[addr as usize / CHR_BANK_SIZE] as usize;
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn prg_ram_enabled(&self) -> bool {
+        self.control & CONTROL_PRG_RAM_ENABLE != 0 && !self.prg_ram.is_empty()
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn cpu_read(&mut self, addr: u16) -> Option<u8> {
+        match addr {
+            0x6000..=0x7FFF if self.prg_ram_enabled() => Some(self.prg_ram[addr as usize % self.prg_ram.len()]),
+            0x8000..=0xFFFF => Some(self.prg_rom[self.prg_rom_addr(addr)]),
+            _ => None,
+        }
+    }
+
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if let 0x6000..=0x7FFF = addr {
+            if self.prg_ram_enabled() {
+                let len = self.prg_ram.len();
+                self.prg_ram[addr as usize % len] = data;
+            }
+            return;
+        }
+
+        let second = addr & self.select_line != 0;
+        match (addr & 0xF000, second) {
+            (0x8000, false) => self.prg_banks[0] = data & 0x3F,
+            (0x8000, true) => self.prg_banks[1] = data & 0x3F,
+            // Sound chip decodes A4 and A5 on its own, on all boards
+            (0x9000, _) if addr & 0x30 == 0x30 => self.opll.write(self.opll_register, data),
+            (0x9000, _) if addr & 0x30 == 0x10 => self.opll_register = data,
+            (0x9000, false) => self.prg_banks[2] = data & 0x3F,
+            (0xA000..=0xD000, _) => {
+                let slot = ((addr & 0xF000) - 0xA000) as usize / 0x1000 * 2 + second as usize;
+                self.chr_banks[slot] = data;
+            }
+            (0xE000, false) => {
+                self.control = data;
+                if data & CONTROL_AUDIO_RESET != 0 {
+                    self.opll = Opll::new();
+                }
+            }
+            (0xE000, true) => self.irq.write_latch(data),
+            (0xF000, false) => self.irq.write_control(data),
+            (0xF000, true) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn ppu_read(&mut self, addr: u16) -> u8 {
+        self.chr[self.chr_addr(addr)]
+    }
+
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        if self.chr_is_ram {
+            let addr = self.chr_addr(addr);
+            self.chr[addr] = data;
+        }
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & CONTROL_MIRRORING {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+
+    fn cpu_tick(&mut self) {
+        self.irq.tick();
+        if self.control & CONTROL_AUDIO_RESET == 0 {
+            self.opll.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq.pending()
+    }
+
+    fn audio_output(&self) -> f32 {
+        if self.control & CONTROL_AUDIO_RESET != 0 {
+            return 0.0;
+        }
+        self.opll.output()
+    }
+
+    fn battery_ram(&self) -> Option<&[u8]> {
+        self.battery.then_some(&self.prg_ram[..])
+    }
+
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        let len = data.len().min(self.prg_ram.len());
+        self.prg_ram[..len].copy_from_slice(&data[..len]);
+    }
+
+    fn load_trainer(&mut self, trainer: &[u8]) {
+        load_trainer(&mut self.prg_ram, trainer);
+    }
+}
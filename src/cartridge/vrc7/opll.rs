@@ -0,0 +1,309 @@
+// Subset of Yamaha YM2413 (OPLL) built into VRC7, described here:
+// https://www.nesdev.org/wiki/VRC7_audio
+//
+// Six channels of two operators each: modulator output shifts
+// carrier phase. Channel plays one of 15 built-in instruments or
+// the custom one defined in registers $00-$07. Key scaling and
+// tremolo/vibrato LFOs aren't emulated.
+use std::f32::consts::TAU;
+
+// Chip produces a sample every 36 CPU cycles, about 49.7kHz
+const CYCLES_PER_SAMPLE: u8 = 36;
+const SAMPLE_RATE: f32 = 1_789_773.0 / CYCLES_PER_SAMPLE as f32;
+const CHANNELS: usize = 6;
+
+// Envelope attenuation in dB at which operator goes silent
+const MAX_ATTENUATION: f32 = 48.0;
+
+// Duration of the slowest rate going through the whole envelope
+// range, from datasheet. Each rate step halves it.
+const ATTACK_TIME: f32 = 2.826;
+const DECAY_TIME: f32 = 39.28;
+
+// Release rate of keys released with sustain flag on
+const SUSTAIN_RELEASE_RATE: u8 = 5;
+
+// Phase shift in cycles caused by modulator at full output
+const MODULATION_DEPTH: f32 = 2.0;
+
+// Channel swings about as far as APU pulse at full volume
+const LEVEL: f32 = 0.075;
+
+// Built-in instruments 1-15, as dumped from the chip
+const PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27],
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12],
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12],
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27],
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28],
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4],
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07],
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17],
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01],
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02],
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12],
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16],
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02],
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6],
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06],
+];
+
+const MULTIPLIERS: [f32; 16] = [
+    0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0, 12.0, 12.0, 15.0, 15.0,
+];
+
+const PATCH_SUSTAINED: u8 = 0b0010_0000;
+const PATCH_MULTIPLIER: u8 = 0b0000_1111;
+const PATCH_TOTAL_LEVEL: u8 = 0b0011_1111;
+const PATCH_CARRIER_HALF_SINE: u8 = 0b0001_0000;
+const PATCH_MODULATOR_HALF_SINE: u8 = 0b0000_1000;
+const PATCH_FEEDBACK: u8 = 0b0000_0111;
+
+const CHANNEL_SUSTAIN: u8 = 0b0010_0000;
+const CHANNEL_KEY: u8 = 0b0001_0000;
+
+// Operator settings unpacked from instrument bytes
+struct OperatorPatch {
+    multiplier: f32,
+    sustained: bool,
+    half_sine: bool,
+    attack_rate: u8,
+    decay_rate: u8,
+    sustain_level: f32,
+    release_rate: u8,
+}
+
+impl OperatorPatch {
+    // Even bytes describe modulator, odd bytes carrier
+    fn new(patch: &[u8; 8], carrier: bool) -> Self {
+        let i = carrier as usize;
+        let half_sine = if carrier { PATCH_CARRIER_HALF_SINE } else { PATCH_MODULATOR_HALF_SINE };
+
+        Self {
+            multiplier: MULTIPLIERS[(patch[i] & PATCH_MULTIPLIER) as usize],
+            sustained: patch[i] & PATCH_SUSTAINED != 0,
+            half_sine: patch[3] & half_sine != 0,
+            attack_rate: patch[4 + i] >> 4,
+            decay_rate: patch[4 + i] & 0x0F,
+            sustain_level: (patch[6 + i] >> 4) as f32 * 3.0,
+            release_rate: patch[6 + i] & 0x0F,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Stage {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+    #[default]
+    Off,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Operator {
+    // Position within waveform, in cycles
+    phase: f32,
+    // Envelope output in dB
+    attenuation: f32,
+    stage: Stage,
+}
+
+impl Operator {
+    fn key_on(&mut self) {
+        self.phase = 0.0;
+        self.stage = Stage::Attack;
+    }
+
+    fn key_off(&mut self) {
+        if self.stage != Stage::Off {
+            self.stage = Stage::Release;
+        }
+    }
+
+    fn update_envelope(&mut self, patch: &OperatorPatch, channel_sustain: bool) {
+        match self.stage {
+            Stage::Attack => {
+                self.attenuation -= attack_step(patch.attack_rate);
+                if self.attenuation <= 0.0 {
+                    self.attenuation = 0.0;
+                    self.stage = Stage::Decay;
+                }
+            }
+            Stage::Decay => {
+                self.attenuation += decay_step(patch.decay_rate);
+                if self.attenuation >= patch.sustain_level {
+                    self.attenuation = patch.sustain_level;
+                    self.stage = Stage::Sustain;
+                }
+            }
+            // Percussive instruments keep fading while key is held
+            Stage::Sustain if !patch.sustained => self.attenuation += decay_step(patch.release_rate),
+            Stage::Sustain | Stage::Off => {}
+            Stage::Release => {
+                let rate = if channel_sustain { SUSTAIN_RELEASE_RATE } else { patch.release_rate };
+                self.attenuation += decay_step(rate);
+            }
+        }
+
+        if self.attenuation >= MAX_ATTENUATION {
+            self.attenuation = MAX_ATTENUATION;
+            if self.stage != Stage::Attack {
+                self.stage = Stage::Off;
+            }
+        }
+    }
+
+    // Advances phase and returns output for extra phase shift
+    fn output(&mut self, step: f32, shift: f32, half_sine: bool, level: f32) -> f32 {
+        self.phase = (self.phase + step).fract();
+        let sample = (TAU * (self.phase + shift)).sin();
+        if half_sine && sample < 0.0 {
+            return 0.0;
+        }
+        sample * gain(self.attenuation + level)
+    }
+}
+
+// Envelope changes per sample, in dB
+fn attack_step(rate: u8) -> f32 {
+    match rate {
+        0 => 0.0,
+        15 => MAX_ATTENUATION,
+        _ => MAX_ATTENUATION / (ATTACK_TIME / (1 << (rate - 1)) as f32 * SAMPLE_RATE),
+    }
+}
+
+fn decay_step(rate: u8) -> f32 {
+    match rate {
+        0 => 0.0,
+        _ => MAX_ATTENUATION / (DECAY_TIME / (1 << (rate - 1)) as f32 * SAMPLE_RATE),
+    }
+}
+
+fn gain(attenuation: f32) -> f32 {
+    if attenuation >= MAX_ATTENUATION {
+        0.0
+    } else {
+        10f32.powf(-attenuation / 20.0)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Channel {
+    frequency: u16,
+    block: u8,
+    key: bool,
+    sustain: bool,
+    instrument: u8,
+    volume: u8,
+    modulator: Operator,
+    carrier: Operator,
+    // Last two modulator outputs, fed back into its phase
+    feedback: [f32; 2],
+}
+
+pub(super) struct Opll {
+    custom_patch: [u8; 8],
+    channels: [Channel; CHANNELS],
+    cycles: u8,
+    sample: f32,
+}
+
+impl Opll {
+    pub(super) fn new() -> Self {
+        Self {
+            custom_patch: [0; 8],
+            channels: [Channel::default(); CHANNELS],
+            cycles: 0,
+            sample: 0.0,
+        }
+    }
+
+    pub(super) fn write(&mut self, register: u8, data: u8) {
+        let index = (register & 0x0F) as usize;
+        match register {
+            0x00..=0x07 => self.custom_patch[index] = data,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[index];
+                channel.frequency = (channel.frequency & 0x100) | data as u16;
+            }
+            0x20..=0x25 => {
+                let channel = &mut self.channels[index];
+                channel.frequency = (channel.frequency & 0xFF) | ((data as u16 & 1) << 8);
+                channel.block = (data >> 1) & 0b111;
+                channel.sustain = data & CHANNEL_SUSTAIN != 0;
+
+                let key = data & CHANNEL_KEY != 0;
+                if key && !channel.key {
+                    channel.modulator.key_on();
+                    channel.carrier.key_on();
+                } else if !key && channel.key {
+                    channel.modulator.key_off();
+                    channel.carrier.key_off();
+                }
+                channel.key = key;
+            }
+            0x30..=0x35 => {
+                let channel = &mut self.channels[index];
+                channel.instrument = data >> 4;
+                channel.volume = data & 0x0F;
+            }
+            _ => {}
+        }
+    }
+
+    fn patch(&self, instrument: u8) -> [u8; 8] {
+        match instrument {
+            0 => self.custom_patch,
+            _ => PATCHES[instrument as usize - 1],
+        }
+    }
+
+    pub(super) fn tick(&mut self) {
+        self.cycles += 1;
+        if self.cycles == CYCLES_PER_SAMPLE {
+            self.cycles = 0;
+            self.sample = (0..CHANNELS).map(|channel| self.channel_sample(channel)).sum::<f32>() * LEVEL;
+        }
+    }
+
+    fn channel_sample(&mut self, index: usize) -> f32 {
+        let patch = self.patch(self.channels[index].instrument);
+        let modulator_patch = OperatorPatch::new(&patch, false);
+        let carrier_patch = OperatorPatch::new(&patch, true);
+        let channel = &mut self.channels[index];
+
+        channel.modulator.update_envelope(&modulator_patch, channel.sustain);
+        channel.carrier.update_envelope(&carrier_patch, channel.sustain);
+
+        // Frequency in cycles per sample
+        let step = channel.frequency as f32 * (1 << channel.block) as f32 / (1 << 19) as f32;
+
+        let feedback = match patch[3] & PATCH_FEEDBACK {
+            0 => 0.0,
+            level => (channel.feedback[0] + channel.feedback[1]) / 2.0 / (1 << (7 - level)) as f32,
+        };
+        let modulator_level = (patch[2] & PATCH_TOTAL_LEVEL) as f32 * 0.75;
+        let modulation = channel.modulator.output(
+            step * modulator_patch.multiplier,
+            feedback,
+            modulator_patch.half_sine,
+            modulator_level,
+        );
+        channel.feedback = [channel.feedback[1], modulation];
+
+        let carrier_level = channel.volume as f32 * 3.0;
+        channel.carrier.output(
+            step * carrier_patch.multiplier,
+            modulation * MODULATION_DEPTH,
+            carrier_patch.half_sine,
+            carrier_level,
+        )
+    }
+
+    pub(super) fn output(&self) -> f32 {
+        self.sample
+    }
+}
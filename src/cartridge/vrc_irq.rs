@@ -0,0 +1,85 @@
+// IRQ counter of Konami VRC4, VRC6 and VRC7, described here:
+// https://www.nesdev.org/wiki/VRC_IRQ
+//
+// 8-bit counter counts up and raises IRQ when it overflows,
+// reloading from latch. It's clocked either by every CPU cycle
+// or by a prescaler approximating scanlines.
+
+const CONTROL_ENABLE_AFTER_ACK: u8 = 0b001;
+const CONTROL_ENABLE: u8 = 0b010;
+const CONTROL_CYCLE_MODE: u8 = 0b100;
+
+// Scanline takes 341 PPU dots, 3 dots per CPU cycle
+const PRESCALER_PERIOD: i16 = 341;
+const PRESCALER_STEP: i16 = 3;
+
+#[derive(Default)]
+pub(crate) struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    prescaler: i16,
+    control: u8,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub(crate) fn write_latch(&mut self, data: u8) {
+        self.latch = data;
+    }
+
+    // VRC4 takes latch through two 4-bit registers
+    pub(crate) fn write_latch_low(&mut self, data: u8) {
+        self.latch = (self.latch & 0xF0) | (data & 0x0F);
+    }
+
+    pub(crate) fn write_latch_high(&mut self, data: u8) {
+        self.latch = (self.latch & 0x0F) | (data << 4);
+    }
+
+    pub(crate) fn write_control(&mut self, data: u8) {
+        self.control = data & 0b111;
+        self.pending = false;
+        if self.control & CONTROL_ENABLE != 0 {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_PERIOD;
+        }
+    }
+
+    pub(crate) fn acknowledge(&mut self) {
+        self.pending = false;
+        if self.control & CONTROL_ENABLE_AFTER_ACK != 0 {
+            self.control |= CONTROL_ENABLE;
+        } else {
+            self.control &= !CONTROL_ENABLE;
+        }
+    }
+
+    pub(crate) fn tick(&mut self) {
+        if self.control & CONTROL_ENABLE == 0 {
+            return;
+        }
+
+        if self.control & CONTROL_CYCLE_MODE != 0 {
+            self.clock();
+        } else {
+            self.prescaler -= PRESCALER_STEP;
+            if self.prescaler <= 0 {
+                self.prescaler += PRESCALER_PERIOD;
+                self.clock();
+            }
+        }
+    }
+
+    fn clock(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+
+    pub(crate) fn pending(&self) -> bool {
+        self.pending
+    }
+}
@@ -0,0 +1,82 @@
+// Standard controller, described here:
+// https://www.nesdev.org/wiki/Standard_controller
+//
+// Writing 1 to $4016 makes controllers latch button state, and while
+// strobe stays high the latch keeps reloading. Each read of $4016 or
+// $4017 then shifts out one button of the first or second controller.
+use std::cell::Cell;
+use std::rc::Rc;
+
+// Order in which buttons are read out
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Button {
+    A = 0b0000_0001,
+    B = 0b0000_0010,
+    Select = 0b0000_0100,
+    Start = 0b0000_1000,
+    Up = 0b0001_0000,
+    Down = 0b0010_0000,
+    Left = 0b0100_0000,
+    Right = 0b1000_0000,
+}
+
+// Buttons held by a player, shared so front ends can update them
+// while the bus is owned by CPU
+#[derive(Clone, Default)]
+pub struct Buttons(Rc<Cell<u8>>);
+
+impl Buttons {
+    pub fn set(&self, button: Button, pressed: bool) {
+        let state = self.0.get() & !(button as u8);
+        self.0.set(if pressed { state | button as u8 } else { state });
+    }
+
+    pub fn pressed(&self, button: Button) -> bool {
+        self.0.get() & button as u8 != 0
+    }
+
+    // All buttons at once, bit 0 for A through bit 7 for Right
+    pub fn state(&self) -> u8 {
+        self.0.get()
+    }
+
+    pub fn set_state(&self, state: u8) {
+        self.0.set(state);
+    }
+}
+
+#[derive(Default)]
+pub struct Controller {
+    buttons: Buttons,
+    strobe: bool,
+    shift_register: u8,
+}
+
+impl Controller {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn buttons(&self) -> Buttons {
+        self.buttons.clone()
+    }
+
+    // Bit 0 of $4016 writes drives strobe of both controllers
+    pub fn write(&mut self, data: u8) {
+        self.strobe = data & 1 != 0;
+        if self.strobe {
+            self.shift_register = self.buttons.state();
+        }
+    }
+
+    // Returns button in bit 0. Once all 8 are read, official
+    // controllers return 1.
+    pub fn read(&mut self) -> u8 {
+        if self.strobe {
+            self.shift_register = self.buttons.state();
+        }
+        let value = self.shift_register & 1;
+        self.shift_register = (self.shift_register >> 1) | 0b1000_0000;
+        value
+    }
+}
@@ -2,7 +2,13 @@
 pub mod memory;
 // Instructions and opcodes implemented here
 pub mod instructions;
+// Interrupt lines and their polling implemented here
+pub mod interrupts;
+// OAM DMA transfers implemented here
+pub mod dma;
 use instructions::OPCODES;
+use interrupts::{InterruptLines, InterruptPolling};
+use memory::AddressingMode;
 
 use crate::bus::Bus;
 
@@ -14,13 +20,20 @@ pub struct CPU {
     pub status: u8,
     pub program_counter: u16,
     pub jmp_compat: bool,
+    // When set, reaching BRK stops `run` instead of executing the interrupt
+    pub stop_on_brk: bool,
+    // Set when CPU executed one of JAM opcodes and stopped
+    pub halted: bool,
+    // Number of CPU cycles since power on
+    pub cycles: u64,
+    pub interrupts: InterruptLines,
+    polling: InterruptPolling,
     pub bus: Box<dyn Bus>
 }
 
-#[allow(dead_code)]
 pub struct InstructionResult {
-    end_of_program: bool,
-    cycles: u8,
+    pub end_of_program: bool,
+    pub cycles: u16,
 }
 
 impl CPU {
@@ -33,6 +46,11 @@ impl CPU {
             status: 0,
             program_counter: 0,
             jmp_compat: true,
+            stop_on_brk: false,
+            halted: false,
+            cycles: 0,
+            interrupts: InterruptLines::new(),
+            polling: InterruptPolling::default(),
             bus,
         }
     }
@@ -42,7 +60,11 @@ impl CPU {
         self.register_x = 0;
         self.register_y = 0;
         self.stack_pointer = 0;
+        // Reset enters the interrupt sequence, which masks IRQs
         self.status = 0;
+        self.set_interrupt_disable_flag(1);
+        self.halted = false;
+        self.polling = InterruptPolling::default();
 
         self.program_counter = self.bus.mem_read_u16(0xFFFC);
     }
@@ -54,7 +76,7 @@ impl CPU {
         }
     }
 
-    pub fn run_with_callback(&mut self, mut callback: impl FnMut(&mut CPU, u8) -> ()) {
+    pub fn run_with_callback(&mut self, mut callback: impl FnMut(&mut CPU, u16) -> ()) {
         callback(self, 0);
         loop {
             let result = self.next();
@@ -66,82 +88,68 @@ impl CPU {
     }
 
     pub fn next(&mut self) -> InstructionResult {
+        let start_cycles = self.cycles;
+
+        if self.halted {
+            // Halted CPU keeps the bus busy and ignores interrupts
+            self.read(0xFFFF);
+            return InstructionResult {
+                end_of_program: true,
+                cycles: 1,
+            };
+        }
+
+        if let Some(addr) = self.bus.poll_dmc_dma() {
+            self.dmc_dma(addr);
+            return InstructionResult {
+                end_of_program: false,
+                cycles: (self.cycles - start_cycles) as u16,
+            };
+        }
+
+        if let Some(page) = self.bus.poll_dma() {
+            self.oam_dma(page);
+            return InstructionResult {
+                end_of_program: false,
+                cycles: (self.cycles - start_cycles) as u16,
+            };
+        }
+
+        if self.polling.interrupt_requested() {
+            self.interrupt();
+            return InstructionResult {
+                end_of_program: false,
+                cycles: (self.cycles - start_cycles) as u16,
+            };
+        }
+
         let opcode = self.pop_read();
 
-        let ins = OPCODES
-            .get(&opcode)
-            .unwrap_or_else(|| panic!("opcode {:X} is not implemented", opcode));
-
-        let mut end_of_program = false;
-
-        let additional_cycles = match ins.instruction_name.as_str() {
-            "ADC" => self.adc(&ins.addresing_mode),
-            "AND" => self.and(&ins.addresing_mode),
-            "ASL" => self.asl(&ins.addresing_mode),
-            "BCC" => self.bcc(&ins.addresing_mode),
-            "BCS" => self.bcs(&ins.addresing_mode),
-            "BEQ" => self.beq(&ins.addresing_mode),
-            "BIT" => self.bit(&ins.addresing_mode),
-            "BMI" => self.bmi(&ins.addresing_mode),
-            "BNE" => self.bne(&ins.addresing_mode),
-            "BPL" => self.bpl(&ins.addresing_mode),
-            "BRK" => {
-                end_of_program = true;
-                0
-            } // !TODO: implement BRK
-            "BVC" => self.bvc(&ins.addresing_mode),
-            "BVS" => self.bvs(&ins.addresing_mode),
-            "CLC" => self.clc(),
-            "CLD" => self.cld(),
-            "CLI" => self.cli(),
-            "CLV" => self.clv(),
-            "CMP" => self.cmp(&ins.addresing_mode),
-            "CPX" => self.cpx(&ins.addresing_mode),
-            "CPY" => self.cpy(&ins.addresing_mode),
-            "DEC" => self.dec(&ins.addresing_mode),
-            "DEX" => self.dex(),
-            "DEY" => self.dey(),
-            "EOR" => self.eor(&ins.addresing_mode),
-            "INC" => self.inc(&ins.addresing_mode),
-            "INX" => self.inx(),
-            "INY" => self.iny(),
-            "JMP" => self.jmp(&ins.addresing_mode),
-            "JSR" => self.jsr(&ins.addresing_mode),
-            "LDA" => self.lda(&ins.addresing_mode),
-            "LDX" => self.ldx(&ins.addresing_mode),
-            "LDY" => self.ldy(&ins.addresing_mode),
-            "LSR" => self.lsr(&ins.addresing_mode),
-            "NOP" => 0, // !TODO: think of better handling this
-            "ORA" => self.ora(&ins.addresing_mode),
-            "PHA" => self.pha(),
-            "PHP" => self.php(),
-            "PLA" => self.pla(),
-            "PLP" => self.plp(),
-            "ROL" => self.rol(&ins.addresing_mode),
-            "ROR" => self.ror(&ins.addresing_mode),
-            "RTI" => 0, // !RODO: implement RTI
-            "RTS" => self.rts(),
-            "SBC" => self.sbc(&ins.addresing_mode),
-            "SEC" => self.sec(),
-            "SED" => self.sed(),
-            "SEI" => self.sei(),
-            "STA" => self.sta(&ins.addresing_mode),
-            "STX" => self.stx(&ins.addresing_mode),
-            "STY" => self.sty(&ins.addresing_mode),
-            "TAX" => self.tax(),
-            "TAY" => self.tay(),
-            "TSX" => self.tsx(),
-            "TXA" => self.txa(),
-            "TXS" => self.txs(),
-            "TYA" => self.tya(),
-            _ => panic!("instruction {} is not implemented", ins.instruction_name),
-        };
-
-        let cycles = additional_cycles + ins.cycles;
+        let ins = &OPCODES[opcode as usize];
+
+        if let AddressingMode::NoneAddressing = ins.addresing_mode {
+            // Single byte instructions still read the byte after opcode
+            // on their second cycle and discard it
+            self.read(self.program_counter);
+        }
+
+        if opcode == 0x00 && self.stop_on_brk {
+            // Leave program counter pointing at BRK
+            self.program_counter = self.program_counter.wrapping_sub(1);
+            return InstructionResult {
+                end_of_program: true,
+                cycles: (self.cycles - start_cycles) as u16,
+            };
+        }
+
+        (ins.execute)(self, &ins.addresing_mode);
+
+        // Only JAM halts the CPU
+        let end_of_program = self.halted;
 
         InstructionResult {
             end_of_program,
-            cycles,
+            cycles: (self.cycles - start_cycles) as u16,
         }
     }
 }
@@ -0,0 +1,35 @@
+// DMA units are part of 2A03, described here:
+// https://www.nesdev.org/wiki/DMA
+const OAMDATA: u16 = 0x2004;
+
+impl crate::cpu::CPU {
+    // Copies 256 bytes from given page to OAMDATA, taking 513 cycles,
+    // or 514 when transfer has to wait for read cycle to align
+    pub(super) fn oam_dma(&mut self, page: u8) {
+        // CPU is halted on a read cycle, and repeats that read
+        self.read(self.program_counter);
+        if self.cycles % 2 == 1 {
+            self.read(self.program_counter);
+        }
+
+        let base = (page as u16) << 8;
+        for offset in 0..=0xFF {
+            let value = self.read(base | offset);
+            self.write(OAMDATA, value);
+        }
+    }
+
+    // Fetches one sample byte for APU DMC, taking 3 cycles,
+    // or 4 when the read has to wait for alignment
+    pub(super) fn dmc_dma(&mut self, addr: u16) {
+        // Halt and dummy cycle repeat the interrupted read
+        self.read(self.program_counter);
+        self.read(self.program_counter);
+        if self.cycles % 2 == 1 {
+            self.read(self.program_counter);
+        }
+
+        let value = self.read(addr);
+        self.bus.complete_dmc_dma(value);
+    }
+}
@@ -6,6 +6,8 @@ use opcodes::OPCODES;
 // - zero flag handling
 // - negative flag handling
 mod flags;
+// Unofficial instructions implemented here
+mod unofficial;
 use super::memory::{AddressResult, AddressingMode};
 
 OPCODES! {
@@ -139,7 +141,7 @@ OPCODES! {
     (0x4E, "LSR", 6, AddressingMode::Absolute,       true)
     (0x5E, "LSR", 7, AddressingMode::AbsoluteX,      true)
 
-    (0xEA, "NOP", 1, AddressingMode::NoneAddressing, true)
+    (0xEA, "NOP", 2, AddressingMode::NoneAddressing, true)
 
     (0x09, "ORA", 2, AddressingMode::Immediate,      true)
     (0x05, "ORA", 3, AddressingMode::ZeroPage,       true)
@@ -216,17 +218,139 @@ OPCODES! {
     (0x9A, "TXS", 2, AddressingMode::NoneAddressing, true)
 
     (0x98, "TYA", 2, AddressingMode::NoneAddressing, true)
+
+    // Unofficial opcodes
+
+    (0x4B, "ALR", 2, AddressingMode::Immediate,      true)
+
+    (0x0B, "ANC", 2, AddressingMode::Immediate,      true)
+    (0x2B, "ANC", 2, AddressingMode::Immediate,      true)
+
+    (0x6B, "ARR", 2, AddressingMode::Immediate,      true)
+
+    (0xCB, "AXS", 2, AddressingMode::Immediate,      true)
+
+    (0xC7, "DCP", 5, AddressingMode::ZeroPage,       true)
+    (0xD7, "DCP", 6, AddressingMode::ZeroPageX,      true)
+    (0xCF, "DCP", 6, AddressingMode::Absolute,       true)
+    (0xDF, "DCP", 7, AddressingMode::AbsoluteX,      true)
+    (0xDB, "DCP", 7, AddressingMode::AbsoluteY,      true)
+    (0xC3, "DCP", 8, AddressingMode::IndirectX,      true)
+    (0xD3, "DCP", 8, AddressingMode::IndirectY,      true)
+
+    (0xE7, "ISC", 5, AddressingMode::ZeroPage,       true)
+    (0xF7, "ISC", 6, AddressingMode::ZeroPageX,      true)
+    (0xEF, "ISC", 6, AddressingMode::Absolute,       true)
+    (0xFF, "ISC", 7, AddressingMode::AbsoluteX,      true)
+    (0xFB, "ISC", 7, AddressingMode::AbsoluteY,      true)
+    (0xE3, "ISC", 8, AddressingMode::IndirectX,      true)
+    (0xF3, "ISC", 8, AddressingMode::IndirectY,      true)
+
+    (0x02, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x12, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x22, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x32, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x42, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x52, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x62, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x72, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0x92, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0xB2, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0xD2, "JAM", 3, AddressingMode::NoneAddressing, false)
+    (0xF2, "JAM", 3, AddressingMode::NoneAddressing, false)
+
+    (0xBB, "LAS", 4, AddressingMode::AbsoluteY,      true)
+
+    (0xA7, "LAX", 3, AddressingMode::ZeroPage,       true)
+    (0xB7, "LAX", 4, AddressingMode::ZeroPageY,      true)
+    (0xAF, "LAX", 4, AddressingMode::Absolute,       true)
+    (0xBF, "LAX", 4, AddressingMode::AbsoluteY,      true)
+    (0xA3, "LAX", 6, AddressingMode::IndirectX,      true)
+    (0xB3, "LAX", 5, AddressingMode::IndirectY,      true)
+
+    (0xAB, "LXA", 2, AddressingMode::Immediate,      true)
+
+    (0x1A, "NOP", 2, AddressingMode::NoneAddressing, true)
+    (0x3A, "NOP", 2, AddressingMode::NoneAddressing, true)
+    (0x5A, "NOP", 2, AddressingMode::NoneAddressing, true)
+    (0x7A, "NOP", 2, AddressingMode::NoneAddressing, true)
+    (0xDA, "NOP", 2, AddressingMode::NoneAddressing, true)
+    (0xFA, "NOP", 2, AddressingMode::NoneAddressing, true)
+    (0x80, "NOP", 2, AddressingMode::Immediate,      true)
+    (0x82, "NOP", 2, AddressingMode::Immediate,      true)
+    (0x89, "NOP", 2, AddressingMode::Immediate,      true)
+    (0xC2, "NOP", 2, AddressingMode::Immediate,      true)
+    (0xE2, "NOP", 2, AddressingMode::Immediate,      true)
+    (0x04, "NOP", 3, AddressingMode::ZeroPage,       true)
+    (0x44, "NOP", 3, AddressingMode::ZeroPage,       true)
+    (0x64, "NOP", 3, AddressingMode::ZeroPage,       true)
+    (0x14, "NOP", 4, AddressingMode::ZeroPageX,      true)
+    (0x34, "NOP", 4, AddressingMode::ZeroPageX,      true)
+    (0x54, "NOP", 4, AddressingMode::ZeroPageX,      true)
+    (0x74, "NOP", 4, AddressingMode::ZeroPageX,      true)
+    (0xD4, "NOP", 4, AddressingMode::ZeroPageX,      true)
+    (0xF4, "NOP", 4, AddressingMode::ZeroPageX,      true)
+    (0x0C, "NOP", 4, AddressingMode::Absolute,       true)
+    (0x1C, "NOP", 4, AddressingMode::AbsoluteX,      true)
+    (0x3C, "NOP", 4, AddressingMode::AbsoluteX,      true)
+    (0x5C, "NOP", 4, AddressingMode::AbsoluteX,      true)
+    (0x7C, "NOP", 4, AddressingMode::AbsoluteX,      true)
+    (0xDC, "NOP", 4, AddressingMode::AbsoluteX,      true)
+    (0xFC, "NOP", 4, AddressingMode::AbsoluteX,      true)
+
+    (0x27, "RLA", 5, AddressingMode::ZeroPage,       true)
+    (0x37, "RLA", 6, AddressingMode::ZeroPageX,      true)
+    (0x2F, "RLA", 6, AddressingMode::Absolute,       true)
+    (0x3F, "RLA", 7, AddressingMode::AbsoluteX,      true)
+    (0x3B, "RLA", 7, AddressingMode::AbsoluteY,      true)
+    (0x23, "RLA", 8, AddressingMode::IndirectX,      true)
+    (0x33, "RLA", 8, AddressingMode::IndirectY,      true)
+
+    (0x67, "RRA", 5, AddressingMode::ZeroPage,       true)
+    (0x77, "RRA", 6, AddressingMode::ZeroPageX,      true)
+    (0x6F, "RRA", 6, AddressingMode::Absolute,       true)
+    (0x7F, "RRA", 7, AddressingMode::AbsoluteX,      true)
+    (0x7B, "RRA", 7, AddressingMode::AbsoluteY,      true)
+    (0x63, "RRA", 8, AddressingMode::IndirectX,      true)
+    (0x73, "RRA", 8, AddressingMode::IndirectY,      true)
+
+    (0x87, "SAX", 3, AddressingMode::ZeroPage,       true)
+    (0x97, "SAX", 4, AddressingMode::ZeroPageY,      true)
+    (0x8F, "SAX", 4, AddressingMode::Absolute,       true)
+    (0x83, "SAX", 6, AddressingMode::IndirectX,      true)
+
+    (0xEB, "SBC", 2, AddressingMode::Immediate,      true)
+
+    (0x9F, "SHA", 5, AddressingMode::AbsoluteY,      true)
+    (0x93, "SHA", 6, AddressingMode::IndirectY,      true)
+
+    (0x9E, "SHX", 5, AddressingMode::AbsoluteY,      true)
+
+    (0x9C, "SHY", 5, AddressingMode::AbsoluteX,      true)
+
+    (0x07, "SLO", 5, AddressingMode::ZeroPage,       true)
+    (0x17, "SLO", 6, AddressingMode::ZeroPageX,      true)
+    (0x0F, "SLO", 6, AddressingMode::Absolute,       true)
+    (0x1F, "SLO", 7, AddressingMode::AbsoluteX,      true)
+    (0x1B, "SLO", 7, AddressingMode::AbsoluteY,      true)
+    (0x03, "SLO", 8, AddressingMode::IndirectX,      true)
+    (0x13, "SLO", 8, AddressingMode::IndirectY,      true)
+
+    (0x47, "SRE", 5, AddressingMode::ZeroPage,       true)
+    (0x57, "SRE", 6, AddressingMode::ZeroPageX,      true)
+    (0x4F, "SRE", 6, AddressingMode::Absolute,       true)
+    (0x5F, "SRE", 7, AddressingMode::AbsoluteX,      true)
+    (0x5B, "SRE", 7, AddressingMode::AbsoluteY,      true)
+    (0x43, "SRE", 8, AddressingMode::IndirectX,      true)
+    (0x53, "SRE", 8, AddressingMode::IndirectY,      true)
+
+    (0x9B, "TAS", 5, AddressingMode::AbsoluteY,      true)
+
+    (0x8B, "XAA", 2, AddressingMode::Immediate,      true)
 }
 
 impl crate::cpu::CPU {
-    pub(super) fn adc(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult {
-            address,
-            page_crossed,
-        } = self.get_operand_address(mode);
-
-        let value = self.bus.mem_read(address);
-
+    pub(super) fn add_to_register_a(&mut self, value: u8) {
         let (result, overflow) = self.register_a.overflowing_add(value);
         let (result, overflow_carry) = result.overflowing_add(self.get_carry_flag());
 
@@ -238,41 +362,32 @@ impl crate::cpu::CPU {
         self.register_a = result;
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
+    }
 
-        match mode {
-            AddressingMode::AbsoluteX | AddressingMode::AbsoluteY | AddressingMode::IndirectY => {
-                if page_crossed {
-                    1
-                } else {
-                    0
-                }
-            }
-            _ => 0,
-        }
+    pub(super) fn compare(&mut self, register: u8, value: u8) {
+        self.set_carry_flag((register >= value) as u8);
+        self.calc_zero_flag(register.wrapping_sub(value));
+        self.calc_negative_flag(register.wrapping_sub(value));
+    }
+
+    pub(super) fn adc(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = self.read(address);
+        self.add_to_register_a(value);
     }
 
-    pub(super) fn and(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, page_crossed } = self.get_operand_address(mode);
+    pub(super) fn and(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
 
         self.register_a = self.register_a & value;
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        match mode {
-            AddressingMode::AbsoluteX | AddressingMode::AbsoluteY | AddressingMode::IndirectY => {
-                if page_crossed {
-                    1
-                } else {
-                    0
-                }
-            }
-            _ => 0,
-        }
     }
 
-    pub(super) fn asl(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn asl(&mut self, mode: &AddressingMode) {
         match mode {
             AddressingMode::NoneAddressing => {
                 self.set_carry_flag(self.register_a >> 7);
@@ -281,356 +396,244 @@ impl crate::cpu::CPU {
                 self.calc_negative_flag(self.register_a);
             }
             mode => {
-                let AddressResult { address, .. } = self.get_operand_address(mode);
+                let address = self.get_write_address(mode);
 
-                let value = self.bus.mem_read(address);
+                let value = self.read(address);
+                self.write(address, value);
                 self.set_carry_flag(value >> 7);
 
                 let new_value = value.overflowing_shl(1).0;
-                self.bus.mem_write(address, new_value);
+                self.write(address, new_value);
                 self.calc_zero_flag(new_value);
                 self.calc_negative_flag(new_value);
             }
         }
-
-        return 0;
     }
 
-    pub(super) fn bcc(&mut self, mode: &AddressingMode) -> u8 {
+    fn branch(&mut self, mode: &AddressingMode, condition: bool) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
-        if self.get_carry_flag() == 0 {
-            self.program_counter = address;
+        if !condition {
+            return;
         }
 
-        return 0;
-    }
-
-    pub(super) fn bcs(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_carry_flag() == 1 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
+        // Taken branch spends one cycle reading next opcode and another one
+        // when page is crossed, while program counter high byte is fixed
+        let next_address = self.program_counter;
+        let polling = (self.polling.prev_nmi_pending, self.polling.prev_irq_pending);
+        self.read(next_address);
+        self.program_counter = address;
 
-            self.program_counter = address;
+        if next_address & 0xFF00 == address & 0xFF00 {
+            // Taken branch without page crossing does not poll interrupts
+            // on its last cycle, so they are delayed by one instruction
+            (self.polling.prev_nmi_pending, self.polling.prev_irq_pending) = polling;
         } else {
-            self.program_counter += 1
+            self.read(next_address & 0xFF00 | address & 0x00FF);
         }
-
-        return 0;
     }
 
-    pub(super) fn beq(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_zero_flag() == 1 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
+    pub(super) fn bcc(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_carry_flag() == 0)
+    }
 
-            self.program_counter = address;
-        } else {
-            self.program_counter += 1
-        }
+    pub(super) fn bcs(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_carry_flag() == 1)
+    }
 
-        return 0;
+    pub(super) fn beq(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_zero_flag() == 1)
     }
 
-    pub(super) fn bit(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn bit(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
         self.calc_zero_flag(value & self.register_a);
         self.set_overflow_flag((value & 0b0100_0000) >> 6);
         self.set_negative_flag((value & 0b1000_0000) >> 7);
-
-        return 0;
     }
 
-    pub(super) fn bmi(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_negative_flag() == 1 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
-
-            self.program_counter = address;
-        } else {
-            self.program_counter += 1
-        }
-
-        return 0;
+    pub(super) fn bmi(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_negative_flag() == 1)
     }
 
-    pub(super) fn bne(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_zero_flag() == 0 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
-
-            self.program_counter = address;
-        } else {
-            self.program_counter += 1
-        }
-
-        return 0;
+    pub(super) fn bne(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_zero_flag() == 0)
     }
 
-    pub(super) fn bpl(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_negative_flag() == 0 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
-
-            self.program_counter = address;
-        } else {
-            self.program_counter += 1
-        }
-
-        return 0;
+    pub(super) fn bpl(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_negative_flag() == 0)
     }
 
-    pub(super) fn bvc(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_overflow_flag() == 0 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
+    pub(super) fn brk(&mut self, _mode: &AddressingMode) {
+        // BRK is a two byte instruction, the byte after opcode is padding
+        // which is skipped by return address
+        self.program_counter = self.program_counter.wrapping_add(1);
 
-            self.program_counter = address;
-        } else {
-            self.program_counter += 1
-        }
+        // Status pushed by BRK always has B flag set:
+        // https://www.nesdev.org/wiki/Status_flags#The_B_flag
+        self.enter_interrupt(self.status | 0b0011_0000);
 
-        return 0;
+        // First instruction of handler is always executed before next NMI
+        self.polling.prev_nmi_pending = false;
     }
 
-    pub(super) fn bvs(&mut self, mode: &AddressingMode) -> u8 {
-        if self.get_overflow_flag() == 1 {
-            let AddressResult { address, .. } = self.get_operand_address(mode);
-
-            self.program_counter = address;
-        } else {
-            self.program_counter += 1
-        }
+    pub(super) fn bvc(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_overflow_flag() == 0)
+    }
 
-        return 0;
+    pub(super) fn bvs(&mut self, mode: &AddressingMode) {
+        self.branch(mode, self.get_overflow_flag() == 1)
     }
 
-    pub(super) fn clc(&mut self) -> u8 {
+    pub(super) fn clc(&mut self, _mode: &AddressingMode) {
         self.set_carry_flag(0);
-
-        return 0;
     }
 
-    pub(super) fn cld(&mut self) -> u8 {
+    pub(super) fn cld(&mut self, _mode: &AddressingMode) {
         self.set_decimal_flag(0);
-
-        return 0;
     }
 
-    pub(super) fn cli(&mut self) -> u8 {
+    pub(super) fn cli(&mut self, _mode: &AddressingMode) {
         self.set_interrupt_disable_flag(0);
-
-        return 0;
     }
 
-    pub(crate) fn clv(&mut self) -> u8 {
+    pub(crate) fn clv(&mut self, _mode: &AddressingMode) {
         self.set_overflow_flag(0);
-
-        return 0;
     }
 
-    pub(super) fn cmp(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn cmp(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
-
-        if self.register_a >= value {
-            self.set_carry_flag(1)
-        } else {
-            self.set_carry_flag(0)
-        }
-
-        if self.register_a == value {
-            self.set_zero_flag(1)
-        } else {
-            self.set_zero_flag(0)
-        }
-
-        if self.register_a.overflowing_sub(value).0 >> 7 == 1 {
-            self.set_negative_flag(1)
-        } else {
-            self.set_negative_flag(0)
-        }
-
-        return 0;
+        let value = self.read(address);
+        self.compare(self.register_a, value);
     }
 
-    pub(super) fn cpx(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn cpx(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
-
-        if self.register_x >= value {
-            self.set_carry_flag(1)
-        } else {
-            self.set_carry_flag(0)
-        }
-
-        if self.register_x == value {
-            self.set_zero_flag(1)
-        } else {
-            self.set_zero_flag(0)
-        }
-
-        if self.register_x.overflowing_sub(value).0 >> 7 == 1 {
-            self.set_negative_flag(1)
-        } else {
-            self.set_negative_flag(0)
-        }
-
-        return 0;
+        let value = self.read(address);
+        self.compare(self.register_x, value);
     }
 
-    pub(super) fn cpy(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn cpy(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
-
-        if self.register_y >= value {
-            self.set_carry_flag(1)
-        } else {
-            self.set_carry_flag(0)
-        }
-
-        if self.register_y == value {
-            self.set_zero_flag(1)
-        } else {
-            self.set_zero_flag(0)
-        }
-
-        if self.register_y.overflowing_sub(value).0 >> 7 == 1 {
-            self.set_negative_flag(1)
-        } else {
-            self.set_negative_flag(0)
-        }
-
-        return 0;
+        let value = self.read(address);
+        self.compare(self.register_y, value);
     }
 
-    pub(super) fn dec(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, .. } = self.get_operand_address(mode);
+    pub(super) fn dec(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
 
-        let mut value = self.bus.mem_read(address);
+        let mut value = self.read(address);
+        self.write(address, value);
         value = value.overflowing_sub(1).0;
-        self.bus.mem_write(address, value);
+        self.write(address, value);
 
         self.calc_zero_flag(value);
         self.calc_negative_flag(value);
-
-        return 0;
     }
 
-    pub(super) fn dex(&mut self) -> u8 {
+    pub(super) fn dex(&mut self, _mode: &AddressingMode) {
         self.register_x = self.register_x.overflowing_sub(1).0;
 
         self.calc_zero_flag(self.register_x);
         self.calc_negative_flag(self.register_x);
-
-        return 0;
     }
 
-    pub(super) fn dey(&mut self) -> u8 {
+    pub(super) fn dey(&mut self, _mode: &AddressingMode) {
         self.register_y = self.register_y.overflowing_sub(1).0;
 
         self.calc_zero_flag(self.register_y);
         self.calc_negative_flag(self.register_y);
-
-        return 0;
     }
 
-    pub(super) fn eor(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn eor(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
 
         self.register_a = self.register_a ^ value;
 
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn jmp(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn jmp(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
         self.program_counter = address;
-
-        return 0;
     }
 
-    pub(super) fn jsr(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, .. } = self.get_operand_address(mode);
-
-        self.stack_push_u16(self.program_counter.wrapping_sub(1));
-        self.program_counter = address;
+    pub(super) fn jsr(&mut self, _mode: &AddressingMode) {
+        // Return address is pushed between fetching low and high byte
+        // of target, so it points to the last byte of JSR
+        let lo = self.pop_read() as u16;
+        self.stack_dummy_read();
+        self.stack_push_u16(self.program_counter);
+        let hi = self.read(self.program_counter) as u16;
 
-        return 0;
+        self.program_counter = (hi << 8) | lo;
     }
 
-    pub(super) fn inc(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, .. } = self.get_operand_address(mode);
+    pub(super) fn inc(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
 
-        let mut value = self.bus.mem_read(address);
+        let mut value = self.read(address);
+        self.write(address, value);
         value = value.overflowing_add(1).0;
-        self.bus.mem_write(address, value);
+        self.write(address, value);
 
         self.calc_zero_flag(value);
         self.calc_negative_flag(value);
-
-        return 0;
     }
 
-    pub(super) fn inx(&mut self) -> u8 {
+    pub(super) fn inx(&mut self, _mode: &AddressingMode) {
         self.register_x = self.register_x.overflowing_add(1).0;
 
         self.calc_zero_flag(self.register_x);
         self.calc_negative_flag(self.register_x);
-
-        return 0;
     }
 
-    pub(super) fn iny(&mut self) -> u8 {
+    pub(super) fn iny(&mut self, _mode: &AddressingMode) {
         self.register_y = self.register_y.overflowing_add(1).0;
 
         self.calc_zero_flag(self.register_y);
         self.calc_negative_flag(self.register_y);
-
-        return 0;
     }
 
-    pub(super) fn lda(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn lda(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
 
         self.register_a = value;
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn ldx(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn ldx(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
 
         self.register_x = value;
         self.calc_zero_flag(self.register_x);
         self.calc_negative_flag(self.register_x);
-
-        return 0;
     }
 
-    pub(super) fn ldy(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn ldy(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
 
         self.register_y = value;
         self.calc_zero_flag(self.register_y);
         self.calc_negative_flag(self.register_y);
-
-        return 0;
     }
 
-    pub(super) fn lsr(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn lsr(&mut self, mode: &AddressingMode) {
         match mode {
             AddressingMode::NoneAddressing => {
                 self.set_carry_flag(self.register_a & 0b0000_0001);
@@ -639,66 +642,57 @@ impl crate::cpu::CPU {
                 self.calc_negative_flag(self.register_a);
             }
             mode => {
-                let AddressResult { address, .. } = self.get_operand_address(mode);
+                let address = self.get_write_address(mode);
 
-                let value = self.bus.mem_read(address);
+                let value = self.read(address);
+                self.write(address, value);
                 self.set_carry_flag(value & 0b0000_0001);
 
                 let new_value = value.overflowing_shr(1).0;
-                self.bus.mem_write(address, new_value);
+                self.write(address, new_value);
                 self.calc_zero_flag(new_value);
                 self.calc_negative_flag(new_value);
             }
         }
-
-        return 0;
     }
 
-    pub(super) fn ora(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn ora(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
+        let value = self.read(address);
 
         self.register_a = self.register_a | value;
 
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn pha(&mut self) -> u8 {
+    pub(super) fn pha(&mut self, _mode: &AddressingMode) {
         self.stack_push(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn php(&mut self) -> u8 {
+    pub(super) fn php(&mut self, _mode: &AddressingMode) {
         // When pushing status to stack B value on
         // destination should  be set to 1:
         // http://forum.6502.org/viewtopic.php?t=770
         self.stack_push(self.status | 0b0001_0000);
-
-        return 0;
     }
 
-    pub(super) fn pla(&mut self) -> u8 {
+    pub(super) fn pla(&mut self, _mode: &AddressingMode) {
+        self.stack_dummy_read();
         self.register_a = self.stack_pop();
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn plp(&mut self) -> u8 {
+    pub(super) fn plp(&mut self, _mode: &AddressingMode) {
         // In nes version this inctruction ignores bits 4 and 5
         // https://www.nesdev.org/wiki/Status_flags
+        self.stack_dummy_read();
         self.status = self.status & 0b0011_0000 | (self.stack_pop() & 0b1100_1111);
-
-        return 0;
     }
 
-    pub(super) fn rol(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn rol(&mut self, mode: &AddressingMode) {
         let old_carry = self.get_carry_flag();
         match mode {
             AddressingMode::NoneAddressing => {
@@ -710,24 +704,23 @@ impl crate::cpu::CPU {
                 self.calc_negative_flag(self.register_a);
             }
             mode => {
-                let AddressResult { address, .. } = self.get_operand_address(mode);
+                let address = self.get_write_address(mode);
 
-                let mut value = self.bus.mem_read(address);
+                let mut value = self.read(address);
+                self.write(address, value);
 
                 self.set_carry_flag(value >> 7);
                 value = value.overflowing_shl(1).0;
                 value += old_carry;
 
-                self.bus.mem_write(address, value);
+                self.write(address, value);
                 self.calc_zero_flag(value);
                 self.calc_negative_flag(value);
             }
         }
-
-        return 0;
     }
 
-    pub(super) fn ror(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn ror(&mut self, mode: &AddressingMode) {
         let old_carry = self.get_carry_flag();
         match mode {
             AddressingMode::NoneAddressing => {
@@ -739,135 +732,108 @@ impl crate::cpu::CPU {
                 self.calc_negative_flag(self.register_a);
             }
             mode => {
-                let AddressResult { address, .. } = self.get_operand_address(mode);
+                let address = self.get_write_address(mode);
 
-                let mut value = self.bus.mem_read(address);
+                let mut value = self.read(address);
+                self.write(address, value);
 
                 self.set_carry_flag(value & 1);
                 value = value.overflowing_shr(1).0;
                 value += old_carry << 7;
 
-                self.bus.mem_write(address, value);
+                self.write(address, value);
                 self.calc_zero_flag(value);
                 self.calc_negative_flag(value);
             }
         }
+    }
 
-        return 0;
+    pub(super) fn rti(&mut self, _mode: &AddressingMode) {
+        // Bits 4 and 5 are ignored the same way as in PLP
+        self.stack_dummy_read();
+        self.status = self.status & 0b0011_0000 | (self.stack_pop() & 0b1100_1111);
+        self.program_counter = self.stack_pop_u16();
     }
 
-    pub(super) fn rts(&mut self) -> u8 {
-        self.program_counter = self.stack_pop_u16() + 1;
+    pub(super) fn rts(&mut self, _mode: &AddressingMode) {
+        self.stack_dummy_read();
+        let address = self.stack_pop_u16();
 
-        return 0;
+        // Extra cycle is spent on incrementing the return address
+        self.read(address);
+        self.program_counter = address.wrapping_add(1);
     }
 
-    pub(super) fn tax(&mut self) -> u8 {
+    pub(super) fn tax(&mut self, _mode: &AddressingMode) {
         self.register_x = self.register_a;
         self.calc_zero_flag(self.register_x);
         self.calc_negative_flag(self.register_x);
-
-        return 0;
     }
 
-    pub(super) fn tay(&mut self) -> u8 {
+    pub(super) fn tay(&mut self, _mode: &AddressingMode) {
         self.register_y = self.register_a;
         self.calc_zero_flag(self.register_y);
         self.calc_negative_flag(self.register_y);
-
-        return 0;
     }
 
-    pub(super) fn tsx(&mut self) -> u8 {
+    pub(super) fn tsx(&mut self, _mode: &AddressingMode) {
         self.register_x = self.stack_pointer;
         self.calc_zero_flag(self.register_x);
         self.calc_negative_flag(self.register_x);
-
-        return 0;
     }
 
-    pub(super) fn txa(&mut self) -> u8 {
+    pub(super) fn txa(&mut self, _mode: &AddressingMode) {
         self.register_a = self.register_x;
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn txs(&mut self) -> u8 {
+    pub(super) fn txs(&mut self, _mode: &AddressingMode) {
         self.stack_pointer = self.register_x;
-
-        return 0;
     }
 
-    pub(super) fn tya(&mut self) -> u8 {
+    pub(super) fn tya(&mut self, _mode: &AddressingMode) {
         self.register_a = self.register_y;
         self.calc_zero_flag(self.register_a);
         self.calc_negative_flag(self.register_a);
-
-        return 0;
     }
 
-    pub(super) fn sbc(&mut self, mode: &AddressingMode) -> u8 {
+    pub(super) fn sbc(&mut self, mode: &AddressingMode) {
         let AddressResult { address, .. } = self.get_operand_address(mode);
 
-        let value = self.bus.mem_read(address);
-
-        let (result, overflow) = self.register_a.overflowing_sub(value);
-        let (result, overflow_carry) = result.overflowing_sub(self.get_carry_flag() ^ 1);
+        let value = self.read(address);
 
-        self.set_carry_flag(!(overflow || overflow_carry) as u8);
-
-        self.set_overflow_flag(
-            ((result >> 7 != self.register_a >> 7) && (self.register_a >> 7 != value >> 7)) as u8,
-        );
-
-        self.register_a = result;
-        self.calc_zero_flag(self.register_a);
-        self.calc_negative_flag(self.register_a);
-
-        return 0;
+        // A - M - (1 - C) is the same as A + !M + C
+        self.add_to_register_a(value ^ 0xFF);
     }
 
-    pub(super) fn sec(&mut self) -> u8 {
+    pub(super) fn sec(&mut self, _mode: &AddressingMode) {
         self.set_carry_flag(1);
-
-        return 0;
     }
 
-    pub(super) fn sed(&mut self) -> u8 {
+    pub(super) fn sed(&mut self, _mode: &AddressingMode) {
         self.set_decimal_flag(1);
-
-        return 0;
     }
 
-    pub(super) fn sei(&mut self) -> u8 {
+    pub(super) fn sei(&mut self, _mode: &AddressingMode) {
         self.set_interrupt_disable_flag(1);
-
-        return 0;
     }
 
-    pub(super) fn sta(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, .. } = self.get_operand_address(mode);
-
-        self.bus.mem_write(address, self.register_a);
+    pub(super) fn sta(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
 
-        return 0;
+        self.write(address, self.register_a);
     }
 
-    pub(super) fn stx(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, .. } = self.get_operand_address(mode);
+    pub(super) fn stx(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
 
-        self.bus.mem_write(address, self.register_x);
-
-        return 0;
+        self.write(address, self.register_x);
     }
 
-    pub(super) fn sty(&mut self, mode: &AddressingMode) -> u8 {
-        let AddressResult { address, .. } = self.get_operand_address(mode);
-
-        self.bus.mem_write(address, self.register_y);
+    pub(super) fn sty(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
 
-        return 0;
+        self.write(address, self.register_y);
     }
 }
@@ -4,12 +4,12 @@ macro_rules! flag {
     ($name:ident, $mask:expr) => {
         paste! {
             #[allow(dead_code)]
-            pub(super) fn [< get_ $name _flag >] (&self) -> u8 {
+            pub(in crate::cpu) fn [< get_ $name _flag >] (&self) -> u8 {
                 self.get_flag($mask)
             }
 
             #[allow(dead_code)]
-            pub(super) fn [< set_ $name _flag >] (&mut self, value: u8) {
+            pub(in crate::cpu) fn [< set_ $name _flag >] (&mut self, value: u8) {
                 self.set_flag($mask, value)
             }
         }
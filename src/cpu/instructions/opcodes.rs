@@ -1,36 +1,54 @@
 macro_rules! OPCODES {
     ($(
-        ($code:expr, $instruction_name:expr, $cycles:expr, $addresing_mode:expr, $increment:expr)
+        ($code:literal, $instruction_name:literal, $cycles:expr, $addresing_mode:expr, $increment:expr)
     )*) => {
-        use lazy_static::lazy_static;
-        use std::collections::HashMap;
+        use crate::cpu::CPU;
+        use paste::paste;
 
+        #[derive(Clone, Copy)]
         pub struct OpCode {
-            // pub code: u8,
-            pub instruction_name: String,
+            pub code: u8,
+            pub instruction_name: &'static str,
             pub cycles: u8,
             pub addresing_mode: AddressingMode,
-            pub increment: bool
+            pub increment: bool,
+            pub execute: fn(&mut CPU, &AddressingMode),
         }
 
-        lazy_static! {
-            pub static ref OPCODES: HashMap<u8, OpCode> = {
-                let mut map = HashMap::new();
-                $(
-                    map.insert(
-                        $code,
-                        OpCode {
-                            // code: $code,
-                            instruction_name: String::from($instruction_name),
-                            cycles: $cycles,
-                            addresing_mode: $addresing_mode,
-                            increment: $increment,
-                        },
-                    );
-                )*
-                map
-            };
-        }
+        // Indexed directly by opcode byte, so decoding an instruction
+        // is a single array access followed by indirect call
+        pub static OPCODES: [OpCode; 256] = {
+            let mut table = [OpCode {
+                code: 0,
+                instruction_name: "",
+                cycles: 0,
+                addresing_mode: AddressingMode::NoneAddressing,
+                increment: false,
+                execute: CPU::jam,
+            }; 256];
+            let mut defined = [false; 256];
+
+            $(
+                assert!(!defined[$code], concat!("opcode ", stringify!($code), " defined twice"));
+                defined[$code] = true;
+                table[$code] = OpCode {
+                    code: $code,
+                    instruction_name: $instruction_name,
+                    cycles: $cycles,
+                    addresing_mode: $addresing_mode,
+                    increment: $increment,
+                    execute: paste! { CPU::[<$instruction_name:lower>] },
+                };
+            )*
+
+            let mut code = 0;
+            while code < 256 {
+                assert!(defined[code], "every opcode must be defined");
+                code += 1;
+            }
+
+            table
+        };
     };
 }
 
@@ -0,0 +1,234 @@
+// Unofficial opcodes, behaviour described here:
+// https://www.nesdev.org/wiki/Programming_with_unofficial_opcodes
+// https://www.nesdev.org/6502_cpu.txt
+use crate::cpu::memory::{AddressResult, AddressingMode};
+
+// Unstable instructions (XAA, LXA) OR accumulator with a constant
+// that depends on chip, this is value most commonly observed
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
+impl crate::cpu::CPU {
+    pub(in crate::cpu) fn alr(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = self.read(address) & self.register_a;
+        self.set_carry_flag(value & 0b0000_0001);
+        self.register_a = value >> 1;
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+    }
+
+    pub(in crate::cpu) fn anc(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        self.register_a &= self.read(address);
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+        self.set_carry_flag(self.register_a >> 7);
+    }
+
+    pub(in crate::cpu) fn arr(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = self.read(address) & self.register_a;
+        self.register_a = (value >> 1) | (self.get_carry_flag() << 7);
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+
+        // Carry and overflow come from the adder used by this instruction
+        let bit_6 = (self.register_a >> 6) & 1;
+        let bit_5 = (self.register_a >> 5) & 1;
+        self.set_carry_flag(bit_6);
+        self.set_overflow_flag(bit_6 ^ bit_5);
+    }
+
+    pub(in crate::cpu) fn axs(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = self.read(address);
+        let register = self.register_a & self.register_x;
+        self.compare(register, value);
+        self.register_x = register.wrapping_sub(value);
+    }
+
+    pub(in crate::cpu) fn dcp(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        let value = self.read(address);
+        self.write(address, value);
+
+        let value = value.wrapping_sub(1);
+        self.write(address, value);
+        self.compare(self.register_a, value);
+    }
+
+    pub(in crate::cpu) fn isc(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        let value = self.read(address);
+        self.write(address, value);
+
+        let value = value.wrapping_add(1);
+        self.write(address, value);
+        self.add_to_register_a(value ^ 0xFF);
+    }
+
+    pub(in crate::cpu) fn jam(&mut self, _mode: &AddressingMode) {
+        // CPU locks up with program counter stuck on the opcode,
+        // only reset brings it back to life
+        self.read(self.program_counter);
+        self.program_counter = self.program_counter.wrapping_sub(1);
+        self.halted = true;
+    }
+
+    pub(in crate::cpu) fn las(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = self.read(address) & self.stack_pointer;
+        self.register_a = value;
+        self.register_x = value;
+        self.stack_pointer = value;
+        self.calc_zero_flag(value);
+        self.calc_negative_flag(value);
+    }
+
+    pub(in crate::cpu) fn lax(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = self.read(address);
+        self.register_a = value;
+        self.register_x = value;
+        self.calc_zero_flag(value);
+        self.calc_negative_flag(value);
+    }
+
+    pub(in crate::cpu) fn lxa(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        let value = (self.register_a | UNSTABLE_MAGIC) & self.read(address);
+        self.register_a = value;
+        self.register_x = value;
+        self.calc_zero_flag(value);
+        self.calc_negative_flag(value);
+    }
+
+    pub(in crate::cpu) fn nop(&mut self, mode: &AddressingMode) {
+        if let AddressingMode::NoneAddressing = mode {
+            return;
+        }
+
+        // Operand is read and discarded
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+        self.read(address);
+    }
+
+    pub(in crate::cpu) fn rla(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        let value = self.read(address);
+        self.write(address, value);
+
+        let result = (value << 1) | self.get_carry_flag();
+        self.set_carry_flag(value >> 7);
+        self.write(address, result);
+
+        self.register_a &= result;
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+    }
+
+    pub(in crate::cpu) fn rra(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        let value = self.read(address);
+        self.write(address, value);
+
+        let result = (value >> 1) | (self.get_carry_flag() << 7);
+        self.set_carry_flag(value & 0b0000_0001);
+        self.write(address, result);
+
+        self.add_to_register_a(result);
+    }
+
+    pub(in crate::cpu) fn sax(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        self.write(address, self.register_a & self.register_x);
+    }
+
+    pub(in crate::cpu) fn sha(&mut self, mode: &AddressingMode) {
+        self.store_and_high_byte(mode, self.register_y, self.register_a & self.register_x);
+    }
+
+    pub(in crate::cpu) fn shx(&mut self, mode: &AddressingMode) {
+        self.store_and_high_byte(mode, self.register_y, self.register_x);
+    }
+
+    pub(in crate::cpu) fn shy(&mut self, mode: &AddressingMode) {
+        self.store_and_high_byte(mode, self.register_x, self.register_y);
+    }
+
+    pub(in crate::cpu) fn slo(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        let value = self.read(address);
+        self.write(address, value);
+
+        let result = value << 1;
+        self.set_carry_flag(value >> 7);
+        self.write(address, result);
+
+        self.register_a |= result;
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+    }
+
+    pub(in crate::cpu) fn sre(&mut self, mode: &AddressingMode) {
+        let address = self.get_write_address(mode);
+
+        let value = self.read(address);
+        self.write(address, value);
+
+        let result = value >> 1;
+        self.set_carry_flag(value & 0b0000_0001);
+        self.write(address, result);
+
+        self.register_a ^= result;
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+    }
+
+    pub(in crate::cpu) fn tas(&mut self, mode: &AddressingMode) {
+        self.stack_pointer = self.register_a & self.register_x;
+        self.store_and_high_byte(mode, self.register_y, self.stack_pointer);
+    }
+
+    pub(in crate::cpu) fn xaa(&mut self, mode: &AddressingMode) {
+        let AddressResult { address, .. } = self.get_operand_address(mode);
+
+        self.register_a = (self.register_a | UNSTABLE_MAGIC) & self.register_x & self.read(address);
+        self.calc_zero_flag(self.register_a);
+        self.calc_negative_flag(self.register_a);
+    }
+
+    // SHA, SHX, SHY and TAS store value ANDed with high byte of base
+    // address plus one. When indexing crosses page, the stored value
+    // also replaces high byte of the target address.
+    fn store_and_high_byte(&mut self, mode: &AddressingMode, index: u8, value: u8) {
+        let AddressResult { address, page_crossed } = self.get_operand_address(mode);
+        if !page_crossed {
+            // Stores spend a cycle on fixing address even if page was not crossed
+            self.read(address);
+        }
+
+        let high_byte = (address.wrapping_sub(index as u16) >> 8) as u8;
+        let value = value & high_byte.wrapping_add(1);
+        let address = if page_crossed {
+            ((value as u16) << 8) | (address & 0x00FF)
+        } else {
+            address
+        };
+
+        self.write(address, value);
+    }
+}
@@ -0,0 +1,109 @@
+pub const NMI_VECTOR: u16 = 0xFFFA;
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+
+// Devices which can hold IRQ line low. Line is asserted as long
+// as at least one of them requests an interrupt.
+#[derive(Debug, Clone, Copy)]
+pub enum IrqSource {
+    External = 0b0000_0001,
+    FrameCounter = 0b0000_0010,
+    Dmc = 0b0000_0100,
+    Mapper = 0b0000_1000,
+}
+
+#[derive(Default)]
+pub struct InterruptLines {
+    nmi: bool,
+    irq: u8,
+}
+
+impl InterruptLines {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // NMI is edge triggered, CPU reacts only to transition
+    // from deasserted to asserted state
+    pub fn set_nmi(&mut self, asserted: bool) {
+        self.nmi = asserted;
+    }
+
+    pub fn nmi(&self) -> bool {
+        self.nmi
+    }
+
+    // IRQ is level triggered, it is serviced for as long as
+    // any source holds it and interrupt disable flag is clear
+    pub fn assert_irq(&mut self, source: IrqSource) {
+        self.irq |= source as u8;
+    }
+
+    pub fn release_irq(&mut self, source: IrqSource) {
+        self.irq &= !(source as u8);
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq != 0
+    }
+}
+
+// State of interrupt detection. Lines are polled at the end of every
+// cycle, but CPU decides whether to handle interrupt based on state
+// polled on penultimate cycle of instruction, hence `prev_*` fields:
+// https://www.nesdev.org/wiki/CPU_interrupts
+#[derive(Default)]
+pub(crate) struct InterruptPolling {
+    nmi_line: bool,
+    pub(crate) nmi_pending: bool,
+    pub(crate) prev_nmi_pending: bool,
+    irq_pending: bool,
+    pub(crate) prev_irq_pending: bool,
+}
+
+impl InterruptPolling {
+    pub(crate) fn interrupt_requested(&self) -> bool {
+        self.prev_nmi_pending || self.prev_irq_pending
+    }
+}
+
+impl crate::cpu::CPU {
+    pub(super) fn poll_interrupts(&mut self) {
+        let irq_enabled = self.get_interrupt_disable_flag() == 0;
+        let polling = &mut self.polling;
+
+        polling.prev_nmi_pending = polling.nmi_pending;
+        if !polling.nmi_line && self.interrupts.nmi() {
+            polling.nmi_pending = true;
+        }
+        polling.nmi_line = self.interrupts.nmi();
+
+        polling.prev_irq_pending = polling.irq_pending;
+        polling.irq_pending = self.interrupts.irq() && irq_enabled;
+    }
+
+    // Hardware interrupt sequence takes 7 cycles, same as BRK,
+    // but opcode and operand fetches are discarded
+    pub(super) fn interrupt(&mut self) {
+        self.read(self.program_counter);
+        self.read(self.program_counter);
+        self.enter_interrupt(self.status & 0b1110_1111 | 0b0010_0000);
+    }
+
+    pub(super) fn enter_interrupt(&mut self, pushed_status: u8) {
+        self.stack_push_u16(self.program_counter);
+
+        // NMI detected before status is pushed hijacks the sequence,
+        // both for IRQ and BRK, and takes its vector instead
+        let vector = if self.polling.nmi_pending {
+            self.polling.nmi_pending = false;
+            NMI_VECTOR
+        } else {
+            IRQ_VECTOR
+        };
+
+        self.stack_push(pushed_status);
+        self.set_interrupt_disable_flag(1);
+
+        self.program_counter = self.read_u16(vector);
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum AddressingMode {
     Immediate,
     Indirect,
@@ -20,29 +20,66 @@ pub struct AddressResult {
 }
 
 impl crate::cpu::CPU {
+    // Every bus access takes one CPU cycle, devices on the bus and
+    // interrupt lines are updated after it
+    pub fn read(&mut self, addr: u16) -> u8 {
+        let value = self.bus.mem_read(addr);
+        self.end_cycle();
+        value
+    }
+
+    pub fn write(&mut self, addr: u16, data: u8) {
+        self.bus.mem_write(addr, data);
+        self.end_cycle();
+    }
+
+    pub fn read_u16(&mut self, addr: u16) -> u16 {
+        let lo = self.read(addr) as u16;
+        let hi = self.read(addr.wrapping_add(1)) as u16;
+        (hi << 8) | lo
+    }
+
+    pub fn read_u16_zero_page(&mut self, addr: u16) -> u16 {
+        let lo = self.read(addr % 256) as u16;
+        let hi = self.read(addr.wrapping_add(1) % 256) as u16;
+        (hi << 8) | lo
+    }
+
+    fn end_cycle(&mut self) {
+        self.cycles += 1;
+        self.bus.tick(&mut self.interrupts);
+        self.poll_interrupts();
+    }
+
     pub fn stack_push(&mut self, value: u8) {
-        self.bus.mem_write((0x01 << 8) + self.stack_pointer as u16, value);
+        self.write((0x01 << 8) + self.stack_pointer as u16, value);
         self.stack_pointer = self.stack_pointer.overflowing_sub(1).0;
     }
 
     pub fn stack_push_u16(&mut self, value: u16) {
-        self.bus.mem_write((0x01 << 8) + self.stack_pointer as u16, (value >> 8) as u8);
-        self.bus.mem_write(
+        self.write((0x01 << 8) + self.stack_pointer as u16, (value >> 8) as u8);
+        self.write(
             (0x01 << 8) + self.stack_pointer.overflowing_sub(1).0 as u16,
             (value & 0xff) as u8,
         );
         self.stack_pointer = self.stack_pointer.overflowing_sub(2).0;
     }
 
+    // Pulling instructions spend one cycle reading top of the stack
+    // before stack pointer is incremented
+    pub fn stack_dummy_read(&mut self) {
+        self.read((0x01 << 8) + self.stack_pointer as u16);
+    }
+
     pub fn stack_pop(&mut self) -> u8 {
-        let value = self.bus.mem_read((0x01 << 8) + self.stack_pointer.overflowing_add(1).0 as u16);
+        let value = self.read((0x01 << 8) + self.stack_pointer.overflowing_add(1).0 as u16);
         self.stack_pointer = self.stack_pointer.overflowing_add(1).0;
         value
     }
 
     pub fn stack_pop_u16(&mut self) -> u16 {
-        let lo = self.bus.mem_read((0x01 << 8) + self.stack_pointer.overflowing_add(1).0 as u16);
-        let hi = self.bus.mem_read((0x01 << 8) + self.stack_pointer.overflowing_add(2).0 as u16);
+        let lo = self.read((0x01 << 8) + self.stack_pointer.overflowing_add(1).0 as u16);
+        let hi = self.read((0x01 << 8) + self.stack_pointer.overflowing_add(2).0 as u16);
         self.stack_pointer = self.stack_pointer.overflowing_add(2).0;
         ((hi as u16) << 8) + lo as u16
     }
@@ -55,7 +92,7 @@ impl crate::cpu::CPU {
 
     pub fn pop_read(&mut self) -> u8 {
         let addr = self.pop_next();
-        self.bus.mem_read(addr)
+        self.read(addr)
     }
 
     pub fn pop_read_u16(&mut self) -> u16 {
@@ -70,15 +107,15 @@ impl crate::cpu::CPU {
             },
             AddressingMode::Indirect => AddressResult {
                 address: {
-                    let address = self.bus.mem_read_u16(self.program_counter);
+                    let address = self.read_u16(self.program_counter);
 
                     // This is to replicate bug that occurs in 6502 JMP indirect addressing
                     // https://www.nesdev.org/obelisk-6502-guide/reference.html#JMP
                     if address & 0xFF == 0xFF && self.jmp_compat {
-                        ((self.bus.mem_read(address & 0xFF00) as u16) << 8)
-                            + self.bus.mem_read(address) as u16
+                        let lo = self.read(address) as u16;
+                        ((self.read(address & 0xFF00) as u16) << 8) + lo
                     } else {
-                        self.bus.mem_read_u16(address)
+                        self.read_u16(address)
                     }
                 },
                 page_crossed: false,
@@ -105,31 +142,35 @@ impl crate::cpu::CPU {
             },
 
             AddressingMode::ZeroPageX => AddressResult {
-                address: self.pop_read().wrapping_add(self.register_x) as u16,
+                address: {
+                    let immediate_address_part = self.pop_read();
+                    // Unindexed address is read while index is added
+                    self.read(immediate_address_part as u16);
+                    immediate_address_part.wrapping_add(self.register_x) as u16
+                },
                 page_crossed: false
             },
             AddressingMode::ZeroPageY => AddressResult {
-                address: self.pop_read().wrapping_add(self.register_y) as u16,
+                address: {
+                    let immediate_address_part = self.pop_read();
+                    self.read(immediate_address_part as u16);
+                    immediate_address_part.wrapping_add(self.register_y) as u16
+                },
                 page_crossed: false
             },
             AddressingMode::AbsoluteX => {
                 let immediate_address_part = self.pop_read_u16();
-                AddressResult {
-                    address: immediate_address_part.wrapping_add(self.register_x as u16),
-                    page_crossed: (immediate_address_part & 0xFF) + (self.register_x as u16) > 255
-                }
+                self.indexed_address(immediate_address_part, self.register_x)
             },
             AddressingMode::AbsoluteY => {
                 let immediate_address_part = self.pop_read_u16();
-                AddressResult {
-                    address: immediate_address_part.wrapping_add(self.register_y as u16),
-                    page_crossed: (immediate_address_part & 0xFF) + (self.register_y as u16) > 255
-                }
+                self.indexed_address(immediate_address_part, self.register_y)
             },
             AddressingMode::IndirectX => AddressResult {
                 address: {
                     let immediate_address_part = self.pop_read();
-                    self.bus.mem_read_u16_zero_page(
+                    self.read(immediate_address_part as u16);
+                    self.read_u16_zero_page(
                         immediate_address_part.wrapping_add(self.register_x) as u16
                     )
                 },
@@ -137,14 +178,39 @@ impl crate::cpu::CPU {
             },
             AddressingMode::IndirectY => {
                 let immediate_address_part = self.pop_read();
-                let indirect_address_no_index = self.bus.mem_read_u16_zero_page(immediate_address_part as u16);
-
-                AddressResult {
-                    address: indirect_address_no_index.wrapping_add(self.register_y as u16),
-                    page_crossed: (indirect_address_no_index & 0xFF) + (self.register_y as u16) > 255
-                }
+                let indirect_address_no_index = self.read_u16_zero_page(immediate_address_part as u16);
+                self.indexed_address(indirect_address_no_index, self.register_y)
             }
             AddressingMode::NoneAddressing => panic!("Mode {:?} is not supported", mode),
         }
     }
+
+    // Stores and read-modify-write instructions can't take back a write
+    // to wrong address, so they always spend a cycle on fixing the address
+    pub fn get_write_address(&mut self, mode: &AddressingMode) -> u16 {
+        let AddressResult { address, page_crossed } = self.get_operand_address(mode);
+
+        match mode {
+            AddressingMode::AbsoluteX | AddressingMode::AbsoluteY | AddressingMode::IndirectY
+                if !page_crossed => {
+                self.read(address);
+            }
+            _ => {}
+        }
+
+        address
+    }
+
+    // Index is added to low byte first. When it overflows, CPU reads
+    // from address with unfixed high byte before reading the right one.
+    fn indexed_address(&mut self, base: u16, index: u8) -> AddressResult {
+        let address = base.wrapping_add(index as u16);
+        let page_crossed = (base & 0xFF) + (index as u16) > 255;
+
+        if page_crossed {
+            self.read(base & 0xFF00 | address & 0x00FF);
+        }
+
+        AddressResult { address, page_crossed }
+    }
 }
@@ -1,6 +1,12 @@
 pub mod cpu;
 pub mod bus;
+pub mod ppu;
+pub mod apu;
 pub mod rom;
+pub mod cartridge;
+pub mod controller;
+pub mod save;
+pub mod wav;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,159 @@
+// Implemented here:
+// - CPU facing registers $2000-$2007
+pub mod registers;
+// - VRAM, palette RAM and nametable mirroring
+pub mod memory;
+// Background fetches and pixel priority
+mod render;
+// Scroll position kept in loopy registers
+mod scroll;
+// Sprite evaluation, sprite 0 hit and overflow
+mod sprites;
+pub mod frame;
+pub mod palette;
+
+use crate::cartridge::Cartridge;
+use frame::Frame;
+use registers::*;
+use render::BackgroundPipeline;
+use sprites::{LineSprite, MAX_SPRITES_PER_LINE};
+
+pub const DOTS_PER_SCANLINE: u16 = 341;
+pub const SCANLINES_PER_FRAME: u16 = 262;
+pub const VBLANK_SCANLINE: u16 = 241;
+pub const PRE_RENDER_SCANLINE: u16 = 261;
+
+pub struct PPU {
+    // Pattern tables and nametable mirroring are up to the cartridge
+    cartridge: Cartridge,
+    // Console has 2KB for two nametables, four screen cartridges
+    // bring another 2KB
+    vram: [u8; 4096],
+    palette: [u8; 32],
+    pub oam: [u8; 256],
+    line_sprites: Vec<LineSprite>,
+
+    ctrl: u8,
+    mask: u8,
+    status: u8,
+    oam_addr: u8,
+    // Internal registers, named after their description by loopy:
+    // v - current VRAM address, also used for rendering
+    // t - temporary address, copied into v on specific dots
+    // x - fine X scroll
+    // w - shared by PPUSCROLL and PPUADDR to tell first write from second
+    v: u16,
+    t: u16,
+    fine_x: u8,
+    write_latch: bool,
+    background: BackgroundPipeline,
+    // PPUDATA reads return content of this buffer and refill it
+    read_buffer: u8,
+    // Value last written to any register, returned when reading
+    // write only registers
+    io_latch: u8,
+
+    pub scanline: u16,
+    pub dot: u16,
+    pub frames: u64,
+    odd_frame: bool,
+    frame: Frame,
+}
+
+impl PPU {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge,
+            vram: [0; 4096],
+            palette: [0; 32],
+            oam: [0; 256],
+            line_sprites: Vec::with_capacity(MAX_SPRITES_PER_LINE),
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            oam_addr: 0,
+            v: 0,
+            t: 0,
+            fine_x: 0,
+            write_latch: false,
+            background: BackgroundPipeline::default(),
+            read_buffer: 0,
+            io_latch: 0,
+            scanline: 0,
+            dot: 0,
+            frames: 0,
+            odd_frame: false,
+            frame: Frame::new(),
+        }
+    }
+
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    // NMI output is held for the whole vertical blank when enabled,
+    // CPU reacts to its edge
+    pub fn nmi_line(&self) -> bool {
+        self.status & STATUS_VBLANK != 0 && self.ctrl & CTRL_NMI_ENABLE != 0
+    }
+
+    pub fn rendering_enabled(&self) -> bool {
+        self.mask & (MASK_BACKGROUND | MASK_SPRITES) != 0
+    }
+
+    // Lines on which PPU fetches data and updates v when rendering
+    fn rendering_scanline(&self) -> bool {
+        self.scanline < 240 || self.scanline == PRE_RENDER_SCANLINE
+    }
+
+    // Advances PPU by one dot, returns true when picture is complete
+    // and vertical blank starts
+    pub fn tick(&mut self) -> bool {
+        let mut frame_complete = false;
+
+        if self.rendering_scanline() && self.rendering_enabled() {
+            self.fetch_background();
+            self.fetch_sprites();
+        }
+
+        match (self.scanline, self.dot) {
+            (0..=239, 1..=256) => self.render_pixel(),
+            (0..=239 | PRE_RENDER_SCANLINE, 257) if !self.rendering_enabled() => {
+                self.line_sprites.clear();
+            }
+            (VBLANK_SCANLINE, 1) => {
+                self.status |= STATUS_VBLANK;
+                self.frames += 1;
+                frame_complete = true;
+            }
+            (PRE_RENDER_SCANLINE, 1) => {
+                self.status &= !(STATUS_VBLANK | STATUS_SPRITE_ZERO_HIT | STATUS_SPRITE_OVERFLOW);
+            }
+            _ => {}
+        }
+
+        self.dot += 1;
+
+        // With rendering enabled, pre-render line of odd frames
+        // is one dot shorter
+        if self.scanline == PRE_RENDER_SCANLINE
+            && self.dot == DOTS_PER_SCANLINE - 1
+            && self.odd_frame
+            && self.rendering_enabled()
+        {
+            self.dot = DOTS_PER_SCANLINE;
+        }
+
+        if self.dot == DOTS_PER_SCANLINE {
+            self.dot = 0;
+            self.scanline += 1;
+
+            if self.scanline == SCANLINES_PER_FRAME {
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+
+        frame_complete
+    }
+}
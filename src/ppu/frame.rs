@@ -0,0 +1,33 @@
+pub const WIDTH: usize = 256;
+pub const HEIGHT: usize = 240;
+
+// RGB24 picture, ready to be copied into a texture
+pub struct Frame {
+    pub data: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0; WIDTH * HEIGHT * 3],
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
+        let base = (y * WIDTH + x) * 3;
+        self.data[base] = rgb.0;
+        self.data[base + 1] = rgb.1;
+        self.data[base + 2] = rgb.2;
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let base = (y * WIDTH + x) * 3;
+        (self.data[base], self.data[base + 1], self.data[base + 2])
+    }
+}
+
+impl Default for Frame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
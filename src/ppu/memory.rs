@@ -0,0 +1,77 @@
+// PPU address space:
+// $0000-$1FFF pattern tables, handled by cartridge
+// $2000-$2FFF nametables, mirrored through $3EFF
+// $3F00-$3F1F palette RAM, mirrored through $3FFF
+use crate::rom::Mirroring;
+
+impl crate::ppu::PPU {
+    pub fn read_vram(&self, addr: u16) -> u8 {
+        let addr = addr & 0x3FFF;
+        self.drive_address_bus(addr);
+        match addr {
+            0x0000..=0x1FFF => self.cartridge.borrow_mut().ppu_read(addr),
+            0x2000..=0x3EFF => {
+                let cartridge_data = self.cartridge.borrow_mut().read_nametable(addr);
+                cartridge_data.unwrap_or_else(|| self.vram[self.mirror_nametable_addr(addr)])
+            }
+            _ => self.palette[palette_index(addr)],
+        }
+    }
+
+    pub fn write_vram(&mut self, addr: u16, data: u8) {
+        let addr = addr & 0x3FFF;
+        self.drive_address_bus(addr);
+        match addr {
+            0x0000..=0x1FFF => self.cartridge.borrow_mut().ppu_write(addr, data),
+            0x2000..=0x3EFF => {
+                if !self.cartridge.borrow_mut().write_nametable(addr, data) {
+                    self.vram[self.mirror_nametable_addr(addr)] = data;
+                }
+            }
+            _ => self.palette[palette_index(addr)] = data,
+        }
+    }
+
+    // Palette RAM is inside PPU, other addresses appear on the bus
+    // shared with cartridge
+    pub(super) fn drive_address_bus(&self, addr: u16) {
+        if addr < 0x3F00 {
+            self.cartridge.borrow_mut().ppu_bus_address(addr);
+        }
+    }
+
+    // Maps four logical nametables onto 1KB pages of physical VRAM,
+    // cartridge decides which page backs which nametable
+    pub fn mirror_nametable_addr(&self, addr: u16) -> usize {
+        let addr = (addr - 0x2000) & 0x0FFF;
+        let table = addr / 0x0400;
+        let offset = addr % 0x0400;
+
+        let page = self.cartridge.borrow().nametable_page(table);
+        (page * 0x0400 + offset) as usize
+    }
+}
+
+// Standard layouts:
+// Horizontal: [ A ] [ a ]    Vertical: [ A ] [ B ]    Single screen: [ A ] [ a ]
+//             [ B ] [ b ]              [ a ] [ b ]                   [ a ] [ a ]
+pub fn mirrored_page(mirroring: Mirroring, table: u16) -> u16 {
+    match mirroring {
+        Mirroring::Horizontal => table >> 1,
+        Mirroring::Vertical => table & 1,
+        Mirroring::FourScreen => table,
+        Mirroring::SingleScreenLower => 0,
+        Mirroring::SingleScreenUpper => 1,
+    }
+}
+
+// Backdrop entries of sprite palettes ($3F10/$3F14/$3F18/$3F1C)
+// are mirrors of background ones
+fn palette_index(addr: u16) -> usize {
+    let index = addr & 0x1F;
+    if index & 0x13 == 0x10 {
+        (index & 0x0F) as usize
+    } else {
+        index as usize
+    }
+}
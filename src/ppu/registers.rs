@@ -0,0 +1,116 @@
+// Register bits, described here:
+// https://www.nesdev.org/wiki/PPU_registers
+pub const CTRL_NAMETABLE: u8 = 0b0000_0011;
+pub const CTRL_INCREMENT: u8 = 0b0000_0100;
+pub const CTRL_SPRITE_TABLE: u8 = 0b0000_1000;
+pub const CTRL_BACKGROUND_TABLE: u8 = 0b0001_0000;
+pub const CTRL_SPRITE_SIZE: u8 = 0b0010_0000;
+pub const CTRL_NMI_ENABLE: u8 = 0b1000_0000;
+
+pub const MASK_GREYSCALE: u8 = 0b0000_0001;
+pub const MASK_BACKGROUND_LEFT: u8 = 0b0000_0010;
+pub const MASK_SPRITES_LEFT: u8 = 0b0000_0100;
+pub const MASK_BACKGROUND: u8 = 0b0000_1000;
+pub const MASK_SPRITES: u8 = 0b0001_0000;
+
+pub const STATUS_SPRITE_OVERFLOW: u8 = 0b0010_0000;
+pub const STATUS_SPRITE_ZERO_HIT: u8 = 0b0100_0000;
+pub const STATUS_VBLANK: u8 = 0b1000_0000;
+
+pub const PPUCTRL: u16 = 0x2000;
+pub const PPUMASK: u16 = 0x2001;
+pub const PPUSTATUS: u16 = 0x2002;
+pub const OAMADDR: u16 = 0x2003;
+pub const OAMDATA: u16 = 0x2004;
+pub const PPUSCROLL: u16 = 0x2005;
+pub const PPUADDR: u16 = 0x2006;
+pub const PPUDATA: u16 = 0x2007;
+
+impl crate::ppu::PPU {
+    // Address is expected to be already folded into $2000-$2007
+    pub fn read_register(&mut self, addr: u16) -> u8 {
+        match addr {
+            PPUSTATUS => {
+                // Only top 3 bits are driven, rest is stale bus content
+                self.io_latch = (self.status & 0b1110_0000) | (self.io_latch & 0b0001_1111);
+                self.status &= !STATUS_VBLANK;
+                self.write_latch = false;
+            }
+            OAMDATA => self.io_latch = self.oam[self.oam_addr as usize],
+            PPUDATA => {
+                let addr = self.v & 0x3FFF;
+                self.io_latch = if addr >= 0x3F00 {
+                    // Palette is returned immediately, buffer is filled
+                    // with nametable data "underneath" it
+                    self.read_buffer = self.read_vram(addr - 0x1000);
+                    (self.read_vram(addr) & 0b0011_1111) | (self.io_latch & 0b1100_0000)
+                } else {
+                    let value = self.read_buffer;
+                    self.read_buffer = self.read_vram(addr);
+                    value
+                };
+                self.increment_addr();
+            }
+            // Write only registers
+            _ => {}
+        }
+
+        self.io_latch
+    }
+
+    pub fn write_register(&mut self, addr: u16, data: u8) {
+        self.io_latch = data;
+
+        match addr {
+            PPUCTRL => {
+                self.ctrl = data;
+                self.write_nametable_select(data);
+            }
+            PPUMASK => self.mask = data,
+            OAMADDR => self.oam_addr = data,
+            OAMDATA => {
+                self.oam[self.oam_addr as usize] = data;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
+            PPUSCROLL => {
+                if self.write_latch {
+                    self.write_scroll_y(data);
+                } else {
+                    self.write_scroll_x(data);
+                }
+                self.write_latch = !self.write_latch;
+            }
+            PPUADDR => {
+                // High byte goes first, address takes effect only
+                // after the second write
+                if self.write_latch {
+                    self.t = (self.t & 0xFF00) | data as u16;
+                    self.v = self.t;
+                    // Outside rendering v is what PPU outputs on its bus
+                    self.drive_address_bus(self.v & 0x3FFF);
+                } else {
+                    self.t = (self.t & 0x00FF) | ((data as u16 & 0x3F) << 8);
+                }
+                self.write_latch = !self.write_latch;
+            }
+            PPUDATA => {
+                self.write_vram(self.v, data);
+                self.increment_addr();
+            }
+            // PPUSTATUS is read only
+            _ => {}
+        }
+    }
+
+    fn increment_addr(&mut self) {
+        // During rendering v is busy, access glitches both increments
+        if self.rendering_scanline() && self.rendering_enabled() {
+            self.increment_x();
+            self.increment_y();
+            return;
+        }
+
+        let step = if self.ctrl & CTRL_INCREMENT != 0 { 32 } else { 1 };
+        self.v = self.v.wrapping_add(step) & 0x7FFF;
+    }
+}
@@ -0,0 +1,164 @@
+// Background rendering pipeline, described here:
+// https://www.nesdev.org/wiki/PPU_rendering
+use super::palette::SYSTEM_PALETTE;
+use super::registers::*;
+
+// Tile data is fetched 8 dots ahead and fed into shift registers,
+// one bit of each is shifted out per dot. Upper byte holds tile
+// being drawn, lower byte the next one.
+#[derive(Default)]
+pub(super) struct BackgroundPipeline {
+    tile: u8,
+    attribute: u8,
+    pattern_low: u8,
+    pattern_high: u8,
+
+    shift_pattern_low: u16,
+    shift_pattern_high: u16,
+    shift_attribute_low: u16,
+    shift_attribute_high: u16,
+}
+
+impl crate::ppu::PPU {
+    // Called on every dot of rendering scanlines when rendering is enabled
+    pub(super) fn fetch_background(&mut self) {
+        if let 2..=257 | 322..=337 = self.dot {
+            self.shift_background();
+        }
+
+        // Each fetch takes two dots, a tile every 8 dots. Fetched tile
+        // enters shift registers at the start of the next one.
+        if let 1..=256 | 321..=336 = self.dot {
+            match (self.dot - 1) % 8 {
+                0 => {
+                    if self.dot != 1 && self.dot != 321 {
+                        self.load_background_shifters();
+                    }
+                    self.background.tile = self.read_vram(self.tile_addr());
+                }
+                2 => {
+                    let v = self.v;
+                    let addr = 0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+                    // Each attribute byte covers 4x4 tiles, 2 bits per 2x2 quadrant
+                    let shift = ((v >> 4) & 0b100) | (v & 0b10);
+                    self.background.attribute = (self.read_vram(addr) >> shift) & 0b11;
+                }
+                4 => self.background.pattern_low = self.read_vram(self.pattern_addr()),
+                6 => self.background.pattern_high = self.read_vram(self.pattern_addr() + 8),
+                7 => self.increment_x(),
+                _ => {}
+            }
+        }
+
+        match (self.scanline, self.dot) {
+            (_, 256) => self.increment_y(),
+            (_, 257) => {
+                self.load_background_shifters();
+                self.copy_horizontal();
+            }
+            // Two unused fetches of the next tile, MMC5 detects
+            // scanlines by them
+            (_, 337) => {
+                self.load_background_shifters();
+                self.read_vram(self.tile_addr());
+            }
+            (_, 339) => {
+                self.read_vram(self.tile_addr());
+            }
+            (super::PRE_RENDER_SCANLINE, 280..=304) => self.copy_vertical(),
+            _ => {}
+        }
+    }
+
+    pub(super) fn tile_addr(&self) -> u16 {
+        0x2000 | (self.v & 0x0FFF)
+    }
+
+    fn pattern_addr(&self) -> u16 {
+        let pattern_table: u16 = if self.ctrl & CTRL_BACKGROUND_TABLE != 0 { 0x1000 } else { 0 };
+        pattern_table + self.background.tile as u16 * 16 + self.fine_y()
+    }
+
+    fn shift_background(&mut self) {
+        let background = &mut self.background;
+        background.shift_pattern_low <<= 1;
+        background.shift_pattern_high <<= 1;
+        background.shift_attribute_low <<= 1;
+        background.shift_attribute_high <<= 1;
+    }
+
+    fn load_background_shifters(&mut self) {
+        let background = &mut self.background;
+        background.shift_pattern_low = (background.shift_pattern_low & 0xFF00) | background.pattern_low as u16;
+        background.shift_pattern_high = (background.shift_pattern_high & 0xFF00) | background.pattern_high as u16;
+
+        // Attribute applies to whole tile, extend its bits to 8 pixels
+        let low = if background.attribute & 0b01 != 0 { 0xFF } else { 0x00 };
+        let high = if background.attribute & 0b10 != 0 { 0xFF } else { 0x00 };
+        background.shift_attribute_low = (background.shift_attribute_low & 0xFF00) | low;
+        background.shift_attribute_high = (background.shift_attribute_high & 0xFF00) | high;
+    }
+
+    // Outputs pixel for current dot of visible scanline
+    pub(super) fn render_pixel(&mut self) {
+        let x = (self.dot - 1) as usize;
+        let y = self.scanline as usize;
+
+        let background = if self.mask & MASK_BACKGROUND != 0
+            && (x >= 8 || self.mask & MASK_BACKGROUND_LEFT != 0)
+        {
+            self.background_pixel()
+        } else {
+            0
+        };
+
+        let sprite = if self.mask & MASK_SPRITES != 0
+            && (x >= 8 || self.mask & MASK_SPRITES_LEFT != 0)
+        {
+            self.sprite_pixel(x)
+        } else {
+            None
+        };
+
+        let background_opaque = background & 0b11 != 0;
+        let palette_addr = match sprite {
+            Some(sprite) => {
+                // Hit is never detected on the last column
+                if sprite.sprite_zero && background_opaque && x != 255 {
+                    self.status |= STATUS_SPRITE_ZERO_HIT;
+                }
+
+                if background_opaque && sprite.behind_background {
+                    background
+                } else {
+                    sprite.value
+                }
+            }
+            None if background_opaque => background,
+            // With rendering disabled and v pointing into palette,
+            // that color is shown instead of backdrop
+            None if !self.rendering_enabled() && self.v & 0x3F00 == 0x3F00 => (self.v & 0x1F) as u8,
+            // Transparent pixels show universal background color
+            None => 0,
+        };
+
+        let mut color = self.read_vram(0x3F00 | palette_addr as u16) & 0b0011_1111;
+        if self.mask & MASK_GREYSCALE != 0 {
+            color &= 0b0011_0000;
+        }
+
+        self.frame.set_pixel(x, y, SYSTEM_PALETTE[color as usize]);
+    }
+
+    // Returns palette number in bits 2-3 and color within palette
+    // in bits 0-1, color 0 is transparent
+    fn background_pixel(&self) -> u8 {
+        let background = &self.background;
+        let bit = 15 - self.fine_x;
+
+        let color = (((background.shift_pattern_high >> bit) & 1) << 1) | ((background.shift_pattern_low >> bit) & 1);
+        let palette = (((background.shift_attribute_high >> bit) & 1) << 1) | ((background.shift_attribute_low >> bit) & 1);
+
+        ((palette << 2) | color) as u8
+    }
+}
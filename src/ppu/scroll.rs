@@ -0,0 +1,75 @@
+// Scrolling through v and t registers, described here:
+// https://www.nesdev.org/wiki/PPU_scrolling
+//
+// yyy NN YYYYY XXXXX
+// ||| || ||||| +++++-- coarse X scroll
+// ||| || +++++-------- coarse Y scroll
+// ||| ++-------------- nametable select
+// +++----------------- fine Y scroll
+const COARSE_X: u16 = 0x001F;
+const COARSE_Y: u16 = 0x03E0;
+const NAMETABLE_X: u16 = 0x0400;
+const NAMETABLE_Y: u16 = 0x0800;
+const FINE_Y: u16 = 0x7000;
+
+const HORIZONTAL_BITS: u16 = NAMETABLE_X | COARSE_X;
+const VERTICAL_BITS: u16 = FINE_Y | NAMETABLE_Y | COARSE_Y;
+
+impl crate::ppu::PPU {
+    pub(super) fn fine_y(&self) -> u16 {
+        (self.v & FINE_Y) >> 12
+    }
+
+    // Moves to the next tile, wrapping into horizontally
+    // neighbouring nametable
+    pub(super) fn increment_x(&mut self) {
+        if self.v & COARSE_X == 31 {
+            self.v &= !COARSE_X;
+            self.v ^= NAMETABLE_X;
+        } else {
+            self.v += 1;
+        }
+    }
+
+    // Moves to the next pixel row. Only 30 rows of tiles are visible,
+    // rows 30 and 31 hold attributes and wrap without switching nametable.
+    pub(super) fn increment_y(&mut self) {
+        if self.v & FINE_Y != FINE_Y {
+            self.v += 0x1000;
+            return;
+        }
+
+        self.v &= !FINE_Y;
+        let mut coarse_y = (self.v & COARSE_Y) >> 5;
+        if coarse_y == 29 {
+            coarse_y = 0;
+            self.v ^= NAMETABLE_Y;
+        } else if coarse_y == 31 {
+            coarse_y = 0;
+        } else {
+            coarse_y += 1;
+        }
+        self.v = (self.v & !COARSE_Y) | (coarse_y << 5);
+    }
+
+    pub(super) fn copy_horizontal(&mut self) {
+        self.v = (self.v & !HORIZONTAL_BITS) | (self.t & HORIZONTAL_BITS);
+    }
+
+    pub(super) fn copy_vertical(&mut self) {
+        self.v = (self.v & !VERTICAL_BITS) | (self.t & VERTICAL_BITS);
+    }
+
+    pub(super) fn write_scroll_x(&mut self, data: u8) {
+        self.t = (self.t & !COARSE_X) | (data >> 3) as u16;
+        self.fine_x = data & 0b111;
+    }
+
+    pub(super) fn write_scroll_y(&mut self, data: u8) {
+        self.t = (self.t & !(FINE_Y | COARSE_Y)) | ((data as u16 & 0b111) << 12) | ((data as u16 >> 3) << 5);
+    }
+
+    pub(super) fn write_nametable_select(&mut self, data: u8) {
+        self.t = (self.t & !(NAMETABLE_X | NAMETABLE_Y)) | ((data as u16 & 0b11) << 10);
+    }
+}
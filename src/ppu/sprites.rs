@@ -0,0 +1,180 @@
+// Sprite evaluation and output, described here:
+// https://www.nesdev.org/wiki/PPU_sprite_evaluation
+use super::registers::*;
+
+pub const MAX_SPRITES_PER_LINE: usize = 8;
+
+const ATTRIBUTE_PALETTE: u8 = 0b0000_0011;
+const ATTRIBUTE_BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const ATTRIBUTE_FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const ATTRIBUTE_FLIP_VERTICAL: u8 = 0b1000_0000;
+
+// Sprite selected for the next scanline. Its pattern row is fetched
+// later and flipped horizontally if needed.
+#[derive(Clone, Copy)]
+pub(super) struct LineSprite {
+    x: u8,
+    attributes: u8,
+    addr: u16,
+    low: u8,
+    high: u8,
+    sprite_zero: bool,
+}
+
+pub(super) struct SpritePixel {
+    // Palette RAM address, sprite palettes start at $3F10
+    pub(super) value: u8,
+    pub(super) behind_background: bool,
+    pub(super) sprite_zero: bool,
+}
+
+fn flip(attributes: u8, pattern: u8) -> u8 {
+    if attributes & ATTRIBUTE_FLIP_HORIZONTAL != 0 {
+        pattern.reverse_bits()
+    } else {
+        pattern
+    }
+}
+
+impl crate::ppu::PPU {
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl & CTRL_SPRITE_SIZE != 0 { 16 } else { 8 }
+    }
+
+    fn sprite_in_range(&self, y: u8, height: u16) -> bool {
+        let row = self.scanline;
+        row >= y as u16 && row - (y as u16) < height
+    }
+
+    // Sprites are evaluated one line ahead, so those found here are
+    // drawn on the next scanline. This is why sprite Y is off by one.
+    fn evaluate_sprites(&mut self) {
+        self.line_sprites.clear();
+        if self.scanline == super::PRE_RENDER_SCANLINE {
+            return;
+        }
+
+        let height = self.sprite_height();
+        let mut n = 0;
+        while n < 64 && self.line_sprites.len() < MAX_SPRITES_PER_LINE {
+            if self.sprite_in_range(self.oam[n * 4], height) {
+                let sprite = self.select_sprite(n, height);
+                self.line_sprites.push(sprite);
+            }
+            n += 1;
+        }
+
+        // Once eight sprites are found, hardware keeps looking for the
+        // ninth one, but mistakenly increments byte offset within entry
+        // together with sprite index, comparing tile, attributes or X
+        // as if they were Y coordinate
+        let mut m = 0;
+        while n < 64 {
+            if self.sprite_in_range(self.oam[n * 4 + m], height) {
+                self.status |= STATUS_SPRITE_OVERFLOW;
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 3;
+        }
+
+    }
+
+    // Patterns are fetched on dots 257-320, 8 dots per slot: two unused
+    // nametable reads followed by pattern rows. Mappers watching PPU
+    // address bus (MMC3, MMC5) count on these fetches.
+    pub(super) fn fetch_sprites(&mut self) {
+        if self.dot == 257 {
+            // OAMADDR is used by sprite fetches and left zeroed
+            self.oam_addr = 0;
+            self.evaluate_sprites();
+        }
+
+        if let 257..=320 = self.dot {
+            let slot = (self.dot - 257) as usize / 8;
+            match (self.dot - 257) % 8 {
+                0 | 2 => {
+                    self.read_vram(self.tile_addr());
+                }
+                4 => {
+                    let data = self.read_vram(self.sprite_pattern_addr(slot));
+                    if let Some(sprite) = self.line_sprites.get_mut(slot) {
+                        sprite.low = flip(sprite.attributes, data);
+                    }
+                }
+                6 => {
+                    let data = self.read_vram(self.sprite_pattern_addr(slot) + 8);
+                    if let Some(sprite) = self.line_sprites.get_mut(slot) {
+                        sprite.high = flip(sprite.attributes, data);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Slots not filled by evaluation still fetch patterns of tile $FF
+    fn sprite_pattern_addr(&self, slot: usize) -> u16 {
+        match self.line_sprites.get(slot) {
+            Some(sprite) => sprite.addr,
+            None if self.sprite_height() == 16 || self.ctrl & CTRL_SPRITE_TABLE != 0 => 0x1FF0,
+            None => 0x0FF0,
+        }
+    }
+
+    fn select_sprite(&self, n: usize, height: u16) -> LineSprite {
+        let y = self.oam[n * 4];
+        let tile = self.oam[n * 4 + 1] as u16;
+        let attributes = self.oam[n * 4 + 2];
+        let x = self.oam[n * 4 + 3];
+
+        let mut row = self.scanline - y as u16;
+        if attributes & ATTRIBUTE_FLIP_VERTICAL != 0 {
+            row = height - 1 - row;
+        }
+
+        // 8x16 sprites take pattern table from bit 0 of tile number,
+        // and are made of two consecutive tiles
+        let addr = if height == 16 {
+            let pattern_table = (tile & 1) * 0x1000;
+            let tile = (tile & 0xFE) + row / 8;
+            pattern_table + tile * 16 + row % 8
+        } else {
+            let pattern_table = if self.ctrl & CTRL_SPRITE_TABLE != 0 { 0x1000 } else { 0 };
+            pattern_table + tile * 16 + row
+        };
+
+        LineSprite {
+            x,
+            attributes,
+            addr,
+            low: 0,
+            high: 0,
+            sprite_zero: n == 0,
+        }
+    }
+
+    // First opaque pixel in OAM order wins, regardless of priority bit
+    pub(super) fn sprite_pixel(&self, x: usize) -> Option<SpritePixel> {
+        for sprite in &self.line_sprites {
+            let offset = x.wrapping_sub(sprite.x as usize);
+            if offset >= 8 {
+                continue;
+            }
+
+            let bit = 7 - offset;
+            let color = (((sprite.high >> bit) & 1) << 1) | ((sprite.low >> bit) & 1);
+            if color == 0 {
+                continue;
+            }
+
+            return Some(SpritePixel {
+                value: 0x10 | ((sprite.attributes & ATTRIBUTE_PALETTE) << 2) | color,
+                behind_background: sprite.attributes & ATTRIBUTE_BEHIND_BACKGROUND != 0,
+                sprite_zero: sprite.sprite_zero,
+            });
+        }
+
+        None
+    }
+}
@@ -1,23 +1,54 @@
+// iNES and NES 2.0 header parsing implemented here
+mod header;
+// Reasons for rejecting ROM file
+mod error;
+use header::{Header, HEADER_SIZE};
+pub use header::{ConsoleType, HeaderFormat, Timing};
+pub use error::RomError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum Mirroring {
     Vertical,
+    #[default]
     Horizontal,
-    FourScreen
+    FourScreen,
+    // All nametables show the same 1KB of VRAM, selectable
+    // by mappers at runtime
+    SingleScreenLower,
+    SingleScreenUpper,
 }
 
-const NES_TAG: &[u8] = b"NES";
+const NES_TAG: &[u8] = b"NES\x1A";
 
-const PRG_BANK_SIZE: usize = 16384;
-const VROM_BANK_SIZE: usize = 8192;
+const TRAINER_SIZE: usize = 512;
 
+#[derive(Default)]
 pub struct Rom {
     pub prg_rom: Vec<u8>,
     pub chr_rom: Vec<u8>,
-    pub mapper: u8,
+    // Data following CHR ROM, e.g. PlayChoice-10 hint screens
+    pub misc_rom: Vec<u8>,
+    // 512 bytes copied to $7000-$71FF before the game starts,
+    // used by hacks and copier dumps
+    pub trainer: Option<Vec<u8>>,
+    pub format: HeaderFormat,
+    pub mapper: u16,
+    pub submapper: u8,
     pub screen_mirroring: Mirroring,
+    // Cartridge keeps PRG NVRAM or other memory powered by battery
+    pub battery: bool,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub timing: Timing,
+    pub console_type: ConsoleType,
+    pub misc_rom_count: u8,
+    pub expansion_device: u8,
 }
 
 impl TryFrom <Vec<u8>> for Rom {
-    type Error = String;
+    type Error = RomError;
 
     fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
         (&bytes).try_into()
@@ -25,42 +56,66 @@ impl TryFrom <Vec<u8>> for Rom {
 }
 
 impl TryFrom <&Vec<u8>> for Rom {
-    type Error = String;
+    type Error = RomError;
     fn try_from(bytes: &Vec<u8>) -> Result<Self, Self::Error> {
-        if &bytes[0..4] != NES_TAG {
-            return Err("File is not in iNES format".to_string())
-        };
-
-        let mapper = (bytes[7] & 0b1111_0000) | (bytes[6] >> 4);
-
-        let ines_version = (bytes[7] >> 2) & 0b11;
+        // Malformed files are expected here, every slice is checked
+        let header_bytes: &[u8; HEADER_SIZE] = bytes.get(0..HEADER_SIZE)
+            .and_then(|header| header.try_into().ok())
+            .ok_or(RomError::TruncatedHeader { length: bytes.len() })?;
 
-        if ines_version != 0 {
-            return Err("iNES 2.0 is not supported".to_string());
+        if &header_bytes[0..4] != NES_TAG {
+            return Err(RomError::BadMagic)
         };
 
+        let header = Header::parse(header_bytes)?;
+
         let screen_mirroring = match (
             bytes[6] & 0b1000 != 0, // Four screen layout
-            bytes[6] & 0b1000 != 0  // Vertical mirroring
+            bytes[6] & 0b0001 != 0  // Vertical mirroring
         ) {
             (true, _) => Mirroring::FourScreen,
             (false, true) => Mirroring::Vertical,
             (false, false) => Mirroring::Horizontal
         };
 
-        let prg_rom_size = bytes[4] as usize * PRG_BANK_SIZE;
-        let chr_rom_size = bytes[5] as usize * VROM_BANK_SIZE;
+        let prg_rom_start = if header.trainer { HEADER_SIZE + TRAINER_SIZE } else { HEADER_SIZE };
+        if bytes.len() < prg_rom_start {
+            return Err(RomError::TrainerMissing);
+        }
 
-        let skip_trainer = bytes[6] & 0b100 != 0;
+        let trainer = header.trainer.then(|| bytes[HEADER_SIZE..prg_rom_start].to_vec());
 
-        let prg_rom_start = if skip_trainer { 528 } else { 16 };
-        let chr_rom_start = prg_rom_start+prg_rom_size;
+        let prg_rom = slice_rom(bytes, prg_rom_start, header.prg_rom_size)
+            .map_err(|found| RomError::TruncatedPrgRom { expected: header.prg_rom_size, found })?;
+        let chr_rom_start = prg_rom_start + prg_rom.len();
+        let chr_rom = slice_rom(bytes, chr_rom_start, header.chr_rom_size)
+            .map_err(|found| RomError::TruncatedChrRom { expected: header.chr_rom_size, found })?;
+        let misc_rom_start = chr_rom_start + chr_rom.len();
 
         Ok(Self {
-            prg_rom: bytes[prg_rom_start..prg_rom_start+prg_rom_size].into(),
-            chr_rom: bytes[chr_rom_start..chr_rom_start+chr_rom_size].into(),
-            mapper,
-            screen_mirroring
+            prg_rom: prg_rom.into(),
+            chr_rom: chr_rom.into(),
+            misc_rom: bytes[misc_rom_start..].into(),
+            trainer,
+            format: header.format,
+            mapper: header.mapper,
+            submapper: header.submapper,
+            screen_mirroring,
+            battery: header.battery,
+            prg_ram_size: header.prg_ram_size,
+            prg_nvram_size: header.prg_nvram_size,
+            chr_ram_size: header.chr_ram_size,
+            chr_nvram_size: header.chr_nvram_size,
+            timing: header.timing,
+            console_type: header.console_type,
+            misc_rom_count: header.misc_rom_count,
+            expansion_device: header.expansion_device,
         })
     }
 }
+
+// Returns number of bytes actually available when file is too short
+fn slice_rom(bytes: &[u8], start: usize, size: usize) -> Result<&[u8], usize> {
+    let available = &bytes[start..];
+    available.get(..size).ok_or(available.len())
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RomError {
+    // File doesn't start with "NES\x1A"
+    BadMagic,
+    TruncatedHeader { length: usize },
+    // Header announces trainer, but file ends before it
+    TrainerMissing,
+    TruncatedPrgRom { expected: usize, found: usize },
+    TruncatedChrRom { expected: usize, found: usize },
+    UnsupportedMapper(u16),
+    // Size fields describe ROM that can't exist
+    InvalidSize(&'static str),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::BadMagic => write!(f, "file is not in iNES format"),
+            RomError::TruncatedHeader { length } => write!(f, "header truncated, file has only {length} bytes"),
+            RomError::TrainerMissing => write!(f, "header announces trainer, but file ends before it"),
+            RomError::TruncatedPrgRom { expected, found } => {
+                write!(f, "PRG ROM truncated, expected {expected} bytes, found {found}")
+            }
+            RomError::TruncatedChrRom { expected, found } => {
+                write!(f, "CHR ROM truncated, expected {expected} bytes, found {found}")
+            }
+            RomError::UnsupportedMapper(mapper) => write!(f, "mapper {mapper} is not supported"),
+            RomError::InvalidSize(field) => write!(f, "invalid {field} size in header"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
@@ -0,0 +1,183 @@
+// iNES and NES 2.0 header fields, described here:
+// https://www.nesdev.org/wiki/INES
+// https://www.nesdev.org/wiki/NES_2.0
+use super::RomError;
+
+pub const HEADER_SIZE: usize = 16;
+
+const PRG_BANK_SIZE: usize = 16384;
+const CHR_BANK_SIZE: usize = 8192;
+const INES_PRG_RAM_BANK_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HeaderFormat {
+    #[default]
+    INes,
+    Nes20,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Timing {
+    #[default]
+    Ntsc,
+    Pal,
+    // Works on both NTSC and PAL consoles
+    MultiRegion,
+    Dendy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConsoleType {
+    #[default]
+    Nes,
+    // PPU and hardware types are stored as found in header byte 13
+    VsSystem { ppu: u8, hardware: u8 },
+    Playchoice10,
+    // Clones and derivatives, type number is stored as found in header
+    Extended(u8),
+}
+
+pub(super) struct Header {
+    pub(super) format: HeaderFormat,
+    pub(super) mapper: u16,
+    pub(super) submapper: u8,
+    pub(super) prg_rom_size: usize,
+    pub(super) chr_rom_size: usize,
+    pub(super) prg_ram_size: usize,
+    pub(super) prg_nvram_size: usize,
+    pub(super) chr_ram_size: usize,
+    pub(super) chr_nvram_size: usize,
+    pub(super) battery: bool,
+    pub(super) trainer: bool,
+    pub(super) timing: Timing,
+    pub(super) console_type: ConsoleType,
+    pub(super) misc_rom_count: u8,
+    pub(super) expansion_device: u8,
+}
+
+impl Header {
+    pub(super) fn parse(bytes: &[u8; HEADER_SIZE]) -> Result<Self, RomError> {
+        let format = if bytes[7] & 0b0000_1100 == 0b0000_1000 {
+            HeaderFormat::Nes20
+        } else {
+            HeaderFormat::INes
+        };
+
+        let flags_6 = bytes[6];
+        let mut header = Self {
+            format,
+            mapper: (flags_6 >> 4) as u16,
+            submapper: 0,
+            prg_rom_size: bytes[4] as usize * PRG_BANK_SIZE,
+            chr_rom_size: bytes[5] as usize * CHR_BANK_SIZE,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            battery: flags_6 & 0b0010 != 0,
+            trainer: flags_6 & 0b0100 != 0,
+            timing: Timing::Ntsc,
+            console_type: ConsoleType::Nes,
+            misc_rom_count: 0,
+            expansion_device: 0,
+        };
+
+        match format {
+            HeaderFormat::Nes20 => header.parse_nes_20(bytes)?,
+            HeaderFormat::INes => header.parse_ines(bytes),
+        }
+
+        if header.prg_rom_size == 0 {
+            return Err(RomError::InvalidSize("PRG ROM"));
+        }
+
+        Ok(header)
+    }
+
+    fn parse_ines(&mut self, bytes: &[u8; HEADER_SIZE]) {
+        // Old dumping tools left their signature in bytes 7-15,
+        // upper mapper nibble and RAM size can't be trusted then
+        let garbage = bytes[12..16].iter().any(|&byte| byte != 0);
+        if !garbage {
+            self.mapper |= (bytes[7] & 0b1111_0000) as u16;
+
+            self.console_type = match bytes[7] & 0b11 {
+                0b01 => ConsoleType::VsSystem { ppu: 0, hardware: 0 },
+                0b10 => ConsoleType::Playchoice10,
+                _ => ConsoleType::Nes,
+            };
+        }
+
+        // Value 0 means 8KB for compatibility
+        let prg_ram_banks = if garbage { 1 } else { bytes[8].max(1) };
+        let prg_ram_size = prg_ram_banks as usize * INES_PRG_RAM_BANK_SIZE;
+        if self.battery {
+            self.prg_nvram_size = prg_ram_size;
+        } else {
+            self.prg_ram_size = prg_ram_size;
+        }
+
+        if self.chr_rom_size == 0 {
+            self.chr_ram_size = CHR_BANK_SIZE;
+        }
+    }
+
+    fn parse_nes_20(&mut self, bytes: &[u8; HEADER_SIZE]) -> Result<(), RomError> {
+        self.mapper |= (bytes[7] & 0b1111_0000) as u16 | ((bytes[8] & 0b0000_1111) as u16) << 8;
+        self.submapper = bytes[8] >> 4;
+
+        self.prg_rom_size = rom_size(bytes[4], bytes[9] & 0b0000_1111, PRG_BANK_SIZE)
+            .ok_or(RomError::InvalidSize("PRG ROM"))?;
+        self.chr_rom_size = rom_size(bytes[5], bytes[9] >> 4, CHR_BANK_SIZE)
+            .ok_or(RomError::InvalidSize("CHR ROM"))?;
+
+        self.prg_ram_size = ram_size(bytes[10] & 0b0000_1111);
+        self.prg_nvram_size = ram_size(bytes[10] >> 4);
+        self.chr_ram_size = ram_size(bytes[11] & 0b0000_1111);
+        self.chr_nvram_size = ram_size(bytes[11] >> 4);
+
+        self.timing = match bytes[12] & 0b11 {
+            0 => Timing::Ntsc,
+            1 => Timing::Pal,
+            2 => Timing::MultiRegion,
+            _ => Timing::Dendy,
+        };
+
+        self.console_type = match bytes[7] & 0b11 {
+            0 => ConsoleType::Nes,
+            1 => ConsoleType::VsSystem {
+                ppu: bytes[13] & 0b0000_1111,
+                hardware: bytes[13] >> 4,
+            },
+            2 => ConsoleType::Playchoice10,
+            _ => ConsoleType::Extended(bytes[13] & 0b0000_1111),
+        };
+
+        self.misc_rom_count = bytes[14] & 0b11;
+        self.expansion_device = bytes[15] & 0b0011_1111;
+
+        Ok(())
+    }
+}
+
+// Size is either number of banks split between LSB byte and MSB nibble,
+// or, when MSB nibble is $F, 2^E * (MM * 2 + 1) bytes encoded as EEEEEEMM.
+// Exponent goes up to 63, such sizes don't fit into memory.
+fn rom_size(lsb: u8, msb: u8, bank_size: usize) -> Option<usize> {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 0b11) as usize * 2 + 1;
+        2usize.checked_pow(exponent)?.checked_mul(multiplier)
+    } else {
+        Some((((msb as usize) << 8) | lsb as usize) * bank_size)
+    }
+}
+
+// RAM sizes are stored as shift count, 64 << count bytes
+fn ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
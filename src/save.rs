@@ -0,0 +1,54 @@
+// Battery backed cartridge RAM kept between sessions in a .sav
+// file next to the ROM, named like other emulators name it
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::cartridge::Cartridge;
+
+pub fn save_path(rom_path: impl AsRef<Path>) -> PathBuf {
+    rom_path.as_ref().with_extension("sav")
+}
+
+// Holds its own reference to the cartridge, so saves can be flushed
+// while the bus is owned by CPU
+pub struct SaveFile {
+    path: PathBuf,
+    cartridge: Cartridge,
+}
+
+impl SaveFile {
+    // Loads existing save into cartridge RAM. Missing file is not
+    // an error, the game starts with empty RAM.
+    pub fn open(rom_path: impl AsRef<Path>, cartridge: Cartridge) -> io::Result<Self> {
+        let path = save_path(rom_path);
+        if cartridge.borrow().battery_ram().is_some() {
+            match fs::read(&path) {
+                Ok(data) => cartridge.borrow_mut().load_battery_ram(&data),
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(Self { path, cartridge })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Writes cartridge RAM out, does nothing for cartridges without battery
+    pub fn flush(&self) -> io::Result<()> {
+        match self.cartridge.borrow().battery_ram() {
+            Some(data) => fs::write(&self.path, data),
+            None => Ok(()),
+        }
+    }
+}
+
+// Saves on exit, errors can't be reported at this point
+impl Drop for SaveFile {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
@@ -116,9 +116,7 @@ fn test_opcode(opcode: u8) {
 
 fn main() {
     for opcode_entry in OPCODES.iter() {
-        if *opcode_entry.0 != 0x00 && *opcode_entry.0 != 0x40 {
-            test_opcode(*opcode_entry.0);
-        }
+        test_opcode(opcode_entry.code);
     }
 }
 
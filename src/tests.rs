@@ -1,12 +1,62 @@
 #![allow(dead_code)]
 
 use super::cpu::CPU;
-use super::bus::TestBus;
+use super::bus::{Bus, TestBus};
+use super::rom::{Mirroring, Rom};
 use serde::Deserialize;
 use paste::paste;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+// Handcrafted tests for behaviour not covered by single step tests
+mod apu;
+mod apu_test;
+mod bus;
+mod cartridge;
+mod controller;
+mod interrupts;
+mod ppu;
+mod rom;
+mod save;
+mod scrolling;
+mod unofficial;
+mod wav;
 
 const TESTS_PATH: &str = "src/tests/v1";
 
+// CPU running program from $8000 on TestBus, with IRQ vector at $9000
+// and interrupts enabled
+fn cpu_with_program(program: Vec<u8>) -> CPU {
+    let mut bus = TestBus::new();
+    bus.load(program);
+    bus.mem_write_u16(0xFFFE, 0x9000);
+
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    cpu.stack_pointer = 0xFD;
+    cpu.status = 0b0010_0000;
+    cpu
+}
+
+// NROM image running program from $C000, rest of PRG ROM is NOPs.
+// IRQ vector is left pointing at $EAEA unless given.
+fn nrom_with_program(program: &[u8], irq_vector: Option<u16>) -> Rom {
+    let mut prg_rom = vec![0xEA; 0x4000];
+    prg_rom[..program.len()].copy_from_slice(program);
+    prg_rom[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0xC0]);
+    if let Some(addr) = irq_vector {
+        prg_rom[0x3FFE..0x4000].copy_from_slice(&addr.to_le_bytes());
+    }
+
+    Rom {
+        prg_rom,
+        chr_rom: vec![0u8; 0x2000],
+        mapper: 0,
+        screen_mirroring: Mirroring::Horizontal,
+        ..Rom::default()
+    }
+}
+
 #[derive(Deserialize, Debug)]
 struct RamEntry {
     addr: u16,
@@ -30,6 +80,20 @@ struct State {
     ram: Vec<RamEntry>,
 }
 
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum AccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct BusCycle {
+    addr: u16,
+    value: u8,
+    kind: AccessKind,
+}
+
 #[derive(Deserialize, Debug)]
 struct TestCase {
     name: String,
@@ -37,12 +101,38 @@ struct TestCase {
     init: State,
     #[serde(rename = "final")]
     result: State,
+    cycles: Vec<BusCycle>,
+}
+
+// Bus recording every access made by CPU,
+// log is shared as CPU takes ownership of the bus
+struct RecordingBus {
+    bus: Box<TestBus>,
+    log: Rc<RefCell<Vec<BusCycle>>>,
+}
+
+impl Bus for RecordingBus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        let value = self.bus.mem_read(addr);
+        self.log.borrow_mut().push(BusCycle { addr, value, kind: AccessKind::Read });
+        value
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.bus.mem_write(addr, data);
+        self.log.borrow_mut().push(BusCycle { addr, value: data, kind: AccessKind::Write });
+    }
 }
 
 impl TestCase {
     fn run(&self) -> bool {
-        let bus = TestBus::new();
-        let mut cpu = CPU::new(bus);
+        let mut bus = TestBus::new();
+        for ram_entry in self.init.ram.iter() {
+            bus.mem_write(ram_entry.addr, ram_entry.value);
+        }
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut cpu = CPU::new(Box::new(RecordingBus { bus, log: log.clone() }));
         cpu.program_counter = self.init.program_counter;
         cpu.stack_pointer = self.init.stack_pointer;
         cpu.register_a = self.init.register_a;
@@ -50,14 +140,20 @@ impl TestCase {
         cpu.register_y = self.init.register_y;
         cpu.status = self.init.status;
 
-        for ram_entry in self.init.ram.iter() {
-            cpu.bus.mem_write(ram_entry.addr, ram_entry.value);
-        }
-
-        cpu.next();
+        let result = cpu.next();
+        let cycles = log.take();
 
         let mut passed = true;
 
+        if cycles != self.cycles {
+            print!("Bus cycles invalid: {:?} should be {:?}; ", cycles, self.cycles);
+            passed = false;
+        };
+        if result.cycles as usize != self.cycles.len() {
+            print!("Cycle count invalid: {} should be {}; ", result.cycles, self.cycles.len());
+            passed = false;
+        };
+
         if cpu.program_counter != self.result.program_counter {
             print!(
                 "Program counter invalid: {:04x} should be {:04x}; ",
@@ -115,10 +211,9 @@ macro_rules! test_opcode {
                     Ok(json) => {
                         let test_cases: Vec<TestCase> = serde_json::from_str(&json).unwrap();
 
-                        for test_case in test_cases.iter() {
-                            if test_case.run() {
-                                return;
-                            }
+                        let failed = test_cases.iter().filter(|test_case| !test_case.run()).count();
+                        if failed > 0 {
+                            panic!("{} of {} cases failed. Check test stdout", failed, test_cases.len());
                         }
                     },
                     Err(_) => panic!("Test failed. Check test stdout")
@@ -334,3 +429,76 @@ test_opcode!(0x8A, "TXA");
 test_opcode!(0x9A, "TXS");
 
 test_opcode!(0x98, "TYA");
+
+// Unofficial opcodes
+
+test_opcode!(0x4B, "ALR");
+
+test_opcode!(0x0B, "ANC");
+test_opcode!(0x2B, "ANC");
+
+// ARR test data applies decimal correction when D flag is set,
+// which 2A03 does not do, so it is not checked here
+
+test_opcode!(0xCB, "AXS");
+
+test_opcode!(0xC7, "DCP");
+
+test_opcode!(0xE7, "ISC");
+
+test_opcode!(0x02, "JAM");
+test_opcode!(0x12, "JAM");
+test_opcode!(0x22, "JAM");
+test_opcode!(0x32, "JAM");
+test_opcode!(0x42, "JAM");
+test_opcode!(0x52, "JAM");
+test_opcode!(0x62, "JAM");
+test_opcode!(0x72, "JAM");
+test_opcode!(0x92, "JAM");
+test_opcode!(0xB2, "JAM");
+test_opcode!(0xD2, "JAM");
+test_opcode!(0xF2, "JAM");
+
+test_opcode!(0xA7, "LAX");
+test_opcode!(0xB7, "LAX");
+
+test_opcode!(0xAB, "LXA");
+
+test_opcode!(0x1A, "NOP");
+test_opcode!(0x3A, "NOP");
+test_opcode!(0x5A, "NOP");
+test_opcode!(0x7A, "NOP");
+test_opcode!(0xDA, "NOP");
+test_opcode!(0xFA, "NOP");
+test_opcode!(0x80, "NOP");
+test_opcode!(0x82, "NOP");
+test_opcode!(0x89, "NOP");
+test_opcode!(0xC2, "NOP");
+test_opcode!(0xE2, "NOP");
+test_opcode!(0x04, "NOP");
+test_opcode!(0x44, "NOP");
+test_opcode!(0x64, "NOP");
+test_opcode!(0x14, "NOP");
+test_opcode!(0x34, "NOP");
+test_opcode!(0x54, "NOP");
+test_opcode!(0x74, "NOP");
+test_opcode!(0xD4, "NOP");
+test_opcode!(0xF4, "NOP");
+// 0x0C (NOP absolute) test data misses the operand read,
+// which hardware performs in its fourth cycle
+
+test_opcode!(0x27, "RLA");
+
+test_opcode!(0x67, "RRA");
+
+test_opcode!(0x87, "SAX");
+test_opcode!(0x97, "SAX");
+test_opcode!(0x8F, "SAX");
+
+test_opcode!(0xEB, "SBC");
+
+test_opcode!(0x07, "SLO");
+
+test_opcode!(0x47, "SRE");
+
+test_opcode!(0x8B, "XAA");
@@ -0,0 +1,528 @@
+use crate::apu::blip::BlipBuffer;
+use crate::apu::filter::OutputFilter;
+use crate::apu::mixer::{pulse_out, tnd_out};
+use crate::apu::{APU, CPU_CLOCK, DEFAULT_SAMPLE_RATE};
+use crate::bus::NesBus;
+use crate::cpu::CPU;
+use crate::tests::nrom_with_program;
+
+fn new_apu() -> APU {
+    APU::new(DEFAULT_SAMPLE_RATE)
+}
+
+// Runs APU, returns mixer output of each CPU cycle
+fn run(apu: &mut APU, cycles: usize) -> Vec<f32> {
+    let outputs = (0..cycles)
+        .map(|_| {
+            apu.tick(0.0);
+            apu.output()
+        })
+        .collect();
+    apu.samples().take();
+    outputs
+}
+
+// Output levels of a single channel, given mixer output of its every
+// level. Triangle stays at step 0 while silent, which outputs 15.
+fn levels(outputs: &[f32], channel_out: impl Fn(u8) -> f32) -> Vec<u8> {
+    let table: Vec<f32> = (0..=127).map(channel_out).collect();
+    outputs
+        .iter()
+        .map(|output| (0..=127).min_by(|&a, &b| (table[a as usize] - output).abs().total_cmp(&(table[b as usize] - output).abs())).unwrap())
+        .collect()
+}
+
+fn pulse_1_levels(outputs: &[f32]) -> Vec<u8> {
+    levels(outputs, |level| pulse_out(level.min(15), 0) + tnd_out(15, 0, 0))
+}
+
+fn noise_levels(outputs: &[f32]) -> Vec<u8> {
+    levels(outputs, |level| tnd_out(15, level.min(15), 0))
+}
+
+fn dmc_levels(outputs: &[f32]) -> Vec<u8> {
+    levels(outputs, |level| tnd_out(15, 0, level))
+}
+
+// Distances between consecutive rising edges of the output
+fn edge_periods(levels: &[u8]) -> Vec<usize> {
+    let edges: Vec<usize> = levels.windows(2).enumerate().filter(|(_, pair)| pair[0] == 0 && pair[1] > 0).map(|(i, _)| i).collect();
+    edges.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
+
+#[test]
+fn test_length_counter_status() {
+    let mut apu = new_apu();
+
+    // Length isn't loaded while channel is disabled
+    apu.write_register(0x4003, 0x08);
+    assert_eq!(apu.read_status() & 0x0F, 0);
+
+    apu.write_register(0x4015, 0x0F);
+    apu.write_register(0x4003, 0x08);
+    apu.write_register(0x400B, 0x08);
+    apu.write_register(0x400F, 0x08);
+    assert_eq!(apu.read_status() & 0x0F, 0b1101);
+
+    apu.write_register(0x4015, 0x00);
+    assert_eq!(apu.read_status() & 0x0F, 0);
+}
+
+#[test]
+fn test_length_counter_expires() {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x01);
+    // Length index 3 loads 2 half frames
+    apu.write_register(0x4003, 0x18);
+
+    run(&mut apu, 14913);
+    assert_eq!(apu.read_status() & 0x01, 0x01);
+    run(&mut apu, 29829 - 14913);
+    assert_eq!(apu.read_status() & 0x01, 0x00);
+}
+
+#[test]
+fn test_pulse_period() {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x01);
+    // 50% duty, constant volume 15
+    apu.write_register(0x4000, 0b1011_1111);
+    apu.write_register(0x4002, 0xFF);
+    apu.write_register(0x4003, 0x08);
+
+    let levels = pulse_1_levels(&run(&mut apu, 10000));
+    assert!(levels.iter().all(|&level| level == 0 || level == 15));
+    let periods = edge_periods(&levels);
+    assert!(periods.len() > 1);
+    assert!(periods.iter().all(|&period| period == 16 * 0x100));
+}
+
+// Runs a pulse channel, which sweeps period 0x100 down by half each
+// half frame, returns periods measured after the first sweep
+fn negated_sweep_periods(channel: u16) -> Vec<usize> {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x03);
+    apu.write_register(channel, 0b1011_1111);
+    apu.write_register(channel + 1, 0b1000_1001);
+    apu.write_register(channel + 2, 0x00);
+    apu.write_register(channel + 3, 0x09);
+
+    let levels = pulse_1_levels(&run(&mut apu, 29000));
+    edge_periods(&levels[16000..])
+}
+
+#[test]
+fn test_sweep_negate() {
+    // Pulse 1 subtracts one more
+    let periods = negated_sweep_periods(0x4000);
+    assert!(!periods.is_empty());
+    assert!(periods.iter().all(|&period| period == 16 * (0x7F + 1)));
+
+    let periods = negated_sweep_periods(0x4004);
+    assert!(!periods.is_empty());
+    assert!(periods.iter().all(|&period| period == 16 * (0x80 + 1)));
+}
+
+#[test]
+fn test_sweep_overflow_mutes() {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x01);
+    apu.write_register(0x4000, 0b1011_1111);
+    // Sweep is disabled, target period 0x600 + 0x300 still mutes
+    apu.write_register(0x4001, 0b0000_0001);
+    apu.write_register(0x4002, 0x00);
+    apu.write_register(0x4003, 0x0E);
+
+    assert!(pulse_1_levels(&run(&mut apu, 0x8000)).iter().all(|&level| level == 0));
+}
+
+#[test]
+fn test_triangle_linear_counter() {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x04);
+    apu.write_register(0x4008, 0x81);
+    apu.write_register(0x400A, 0x10);
+    apu.write_register(0x400B, 0x08);
+
+    // Sequencer doesn't move until linear counter is reloaded
+    // by the first quarter frame
+    let samples = run(&mut apu, 7457);
+    assert!(samples.iter().all(|&sample| sample == samples[0]));
+
+    let samples = run(&mut apu, 32 * 0x11);
+    let mut levels = samples.clone();
+    levels.dedup();
+    // Both ends of the sequence repeat a level
+    assert_eq!(levels.len(), 31);
+}
+
+// Sequence of noise output levels, one per timer period
+fn noise_sequence(mode: u8) -> Vec<bool> {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x08);
+    apu.write_register(0x400C, 0b0011_1111);
+    apu.write_register(0x400E, mode);
+    apu.write_register(0x400F, 0x08);
+
+    noise_levels(&run(&mut apu, 4 * 2000)).iter().step_by(4).map(|&level| level > 0).collect()
+}
+
+fn repeat_period(sequence: &[bool]) -> Option<usize> {
+    (1..sequence.len() / 2).find(|&period| sequence[period..].iter().zip(sequence).all(|(a, b)| a == b))
+}
+
+#[test]
+fn test_noise_modes() {
+    assert_eq!(repeat_period(&noise_sequence(0x80)), Some(93));
+    assert_eq!(repeat_period(&noise_sequence(0x00)), None);
+}
+
+// Runs APU, serving DMC sample fetches with given byte the way
+// bus does, returns fetched addresses and drops samples
+fn run_dmc(apu: &mut APU, cycles: usize, data: u8) -> Vec<u16> {
+    let mut fetched = Vec::new();
+    for _ in 0..cycles {
+        if let Some(addr) = apu.dmc_request() {
+            fetched.push(addr);
+            apu.dmc_fill(data);
+        }
+        apu.tick(0.0);
+    }
+    apu.samples().take();
+    fetched
+}
+
+#[test]
+fn test_dmc_fetch_and_irq() {
+    let mut apu = new_apu();
+    // IRQ enabled, sample at $C040, 17 bytes long
+    apu.write_register(0x4010, 0x8F);
+    apu.write_register(0x4012, 0x01);
+    apu.write_register(0x4013, 0x01);
+    assert_eq!(apu.dmc_request(), None);
+
+    apu.write_register(0x4015, 0x10);
+    assert_eq!(apu.read_status() & 0x10, 0x10);
+    let fetched = run_dmc(&mut apu, 20 * 8 * 54 + 428, 0xFF);
+    assert_eq!(fetched, (0xC040..0xC051).collect::<Vec<u16>>());
+
+    assert!(apu.dmc_irq());
+    assert_eq!(apu.read_status() & 0x90, 0x80);
+
+    // Writing $4015 acknowledges IRQ
+    apu.write_register(0x4015, 0x00);
+    assert!(!apu.dmc_irq());
+}
+
+#[test]
+fn test_dmc_loop() {
+    let mut apu = new_apu();
+    apu.write_register(0x4010, 0xCF);
+    apu.write_register(0x4013, 0x00);
+    apu.write_register(0x4015, 0x10);
+
+    let fetched = run_dmc(&mut apu, 4 * 8 * 54 + 428, 0x00);
+    assert!(fetched.len() > 2);
+    assert!(fetched.iter().all(|&addr| addr == 0xC000));
+    assert!(!apu.dmc_irq());
+    assert_eq!(apu.read_status() & 0x10, 0x10);
+}
+
+#[test]
+fn test_dmc_output() {
+    let mut apu = new_apu();
+    apu.write_register(0x4010, 0x0F);
+    apu.write_register(0x4011, 0x40);
+    apu.write_register(0x4015, 0x10);
+
+    // Each set bit raises output level by 2, clear one lowers it
+    run_dmc(&mut apu, 2000, 0xFF);
+    let samples = run(&mut apu, 1);
+    assert_eq!(dmc_levels(&samples), vec![0x40 + 8 * 2]);
+
+    // Sample is over, output level is held
+    let samples = run(&mut apu, 2000);
+    assert!(dmc_levels(&samples).iter().all(|&level| level == 0x40 + 8 * 2));
+}
+
+#[test]
+fn test_dmc_address_wraps() {
+    let mut apu = new_apu();
+    apu.write_register(0x4010, 0x0F);
+    apu.write_register(0x4012, 0xFF);
+    apu.write_register(0x4013, 0x04);
+    apu.write_register(0x4015, 0x10);
+
+    let fetched = run_dmc(&mut apu, 0x42 * 8 * 54 + 428, 0x00);
+    assert_eq!(fetched.len(), 0x41);
+    assert_eq!(fetched[0x3F], 0xFFFF);
+    assert_eq!(fetched[0x40], 0x8000);
+}
+
+#[test]
+fn test_undrained_samples_are_capped() {
+    let mut apu = APU::new(44_100);
+    for _ in 0..3 * CPU_CLOCK {
+        apu.tick(0.0);
+    }
+    assert!(apu.samples().take().len() <= 2 * 44_100);
+}
+
+#[test]
+fn test_sample_rate() {
+    let samples_per_second = |apu: &mut APU| {
+        for _ in 0..CPU_CLOCK {
+            apu.tick(0.0);
+        }
+        apu.samples().take().len()
+    };
+
+    let mut apu = APU::new(44_100);
+    assert_eq!(samples_per_second(&mut apu), 44_100);
+
+    apu.set_sample_rate(48_000);
+    assert_eq!(samples_per_second(&mut apu), 48_000);
+}
+
+// Amplitude of given frequency in Hann windowed signal
+fn amplitude(samples: &[f32], sample_rate: u32, frequency: f64) -> f64 {
+    let n = samples.len() as f64;
+    let (mut re, mut im) = (0.0, 0.0);
+    for (i, &sample) in samples.iter().enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / n).cos();
+        let angle = 2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64;
+        re += sample as f64 * window * angle.cos();
+        im -= sample as f64 * window * angle.sin();
+    }
+    4.0 * (re * re + im * im).sqrt() / n
+}
+
+// One second of square wave toggling every given number of CPU
+// cycles, resampled by BlipBuffer or sampled at sample start
+fn square_wave(half_period: u32, band_limited: bool) -> Vec<f32> {
+    let mut blip = BlipBuffer::new(CPU_CLOCK, DEFAULT_SAMPLE_RATE);
+    let mut samples = Vec::new();
+    let mut level = 0.5;
+    blip.add_delta(0, level);
+    for cycle in 1..=CPU_CLOCK {
+        if cycle % half_period == 0 {
+            level = -level;
+            blip.add_delta(0, 2.0 * level);
+        }
+        blip.end_frame(1);
+        if band_limited {
+            blip.read_samples(&mut samples);
+        } else if cycle as u64 * DEFAULT_SAMPLE_RATE as u64 / CPU_CLOCK as u64 > samples.len() as u64 {
+            samples.push(level);
+        }
+    }
+    samples
+}
+
+// Frequencies harmonics above Nyquist frequency fold back to, skipping
+// ones landing close to odd harmonics below it
+fn alias_frequencies(fundamental: f64) -> Vec<f64> {
+    let sample_rate = DEFAULT_SAMPLE_RATE as f64;
+    let harmonics: Vec<f64> = (1..).step_by(2).map(|h| h as f64 * fundamental).take_while(|&f| f < sample_rate / 2.0).collect();
+    (1..60)
+        .step_by(2)
+        .map(|h| h as f64 * fundamental)
+        .filter(|&f| f > sample_rate / 2.0)
+        .map(|f| {
+            let folded = f % sample_rate;
+            if folded > sample_rate / 2.0 { sample_rate - folded } else { folded }
+        })
+        .filter(|alias| harmonics.iter().all(|harmonic| (harmonic - alias).abs() > 50.0))
+        .collect()
+}
+
+#[test]
+fn test_band_limited_square_wave_spectrum() {
+    let half_period = 300;
+    let fundamental = CPU_CLOCK as f64 / (2 * half_period) as f64;
+    let aliases = alias_frequencies(fundamental);
+    assert!(aliases.len() > 10);
+
+    let samples = square_wave(half_period, true);
+    assert_eq!(samples.len(), DEFAULT_SAMPLE_RATE as usize);
+    let peak = amplitude(&samples, DEFAULT_SAMPLE_RATE, fundamental);
+    // Square wave of amplitude 0.5 has harmonics of 2 / (PI * n)
+    assert!((peak - 2.0 / std::f64::consts::PI).abs() < 0.01);
+    for n in [3.0, 5.0] {
+        let harmonic = amplitude(&samples, DEFAULT_SAMPLE_RATE, n * fundamental);
+        assert!((harmonic * n / peak - 1.0).abs() < 0.02, "harmonic {n}");
+    }
+    for &alias in &aliases {
+        let level = amplitude(&samples, DEFAULT_SAMPLE_RATE, alias) / peak;
+        assert!(level < 0.001, "alias at {alias:.0}Hz is {level}");
+    }
+
+    // Point sampling has aliases an order of magnitude above that
+    let samples = square_wave(half_period, false);
+    let peak = amplitude(&samples, DEFAULT_SAMPLE_RATE, fundamental);
+    let worst = aliases.iter().map(|&alias| amplitude(&samples, DEFAULT_SAMPLE_RATE, alias) / peak).fold(0.0, f64::max);
+    assert!(worst > 0.01);
+}
+
+#[test]
+fn test_output_filter() {
+    let sample_rate = DEFAULT_SAMPLE_RATE;
+    let tone = |frequency: f64| -> f64 {
+        let mut filter = OutputFilter::new(sample_rate);
+        let samples: Vec<f32> = (0..sample_rate)
+            .map(|i| filter.process((2.0 * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64).sin() as f32))
+            .collect();
+        amplitude(&samples[sample_rate as usize / 2..], sample_rate, frequency)
+    };
+
+    // High-pass filters remove DC offset
+    let mut filter = OutputFilter::new(sample_rate);
+    let settled = (0..sample_rate / 10).map(|_| filter.process(1.0)).last().unwrap();
+    assert!(settled.abs() < 0.001);
+
+    assert!(tone(1000.0) > 0.85);
+    assert!(tone(100.0) < 0.3);
+    assert!(tone(14000.0) < 0.75);
+}
+
+#[test]
+fn test_nonlinear_mixer() {
+    assert_eq!(pulse_out(0, 0), 0.0);
+    assert_eq!(tnd_out(0, 0, 0), 0.0);
+    assert!((pulse_out(15, 15) + tnd_out(15, 15, 127) - 1.0).abs() < 0.01);
+    // Second channel adds less than the first one
+    assert!(pulse_out(15, 15) < 2.0 * pulse_out(15, 0));
+}
+
+#[test]
+fn test_apu_samples_have_no_dc_offset() {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x01);
+    apu.write_register(0x4000, 0b1011_1111);
+    apu.write_register(0x4002, 0xFF);
+    apu.write_register(0x4003, 0x08);
+    apu.write_register(0x4017, 0x80);
+
+    for _ in 0..CPU_CLOCK / 4 {
+        apu.tick(0.0);
+    }
+    let samples = apu.samples().take();
+    let tail = &samples[samples.len() / 2..];
+    let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+    assert!(mean.abs() < 0.005);
+    assert!(tail.iter().any(|&sample| sample > 0.05));
+}
+
+#[test]
+fn test_dmc_dma_steals_cycles() {
+    // LDA #$0F; STA $4010; LDA #$10; STA $4015; NOP...
+    let program = [0xA9, 0x0F, 0x8D, 0x10, 0x40, 0xA9, 0x10, 0x8D, 0x15, 0x40];
+    let bus = NesBus::new(nrom_with_program(&program, None)).unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+
+    for _ in 0..4 {
+        cpu.next();
+    }
+    // Sample byte is fetched before the next instruction
+    let result = cpu.next();
+    assert!(result.cycles == 3 || result.cycles == 4);
+    assert_eq!(cpu.program_counter, 0xC00A);
+
+    // Single byte sample is done
+    assert_eq!(cpu.bus.mem_read(0x4015) & 0x10, 0);
+}
+
+// Ticks until frame IRQ is raised, returns number of ticks taken
+fn ticks_until_frame_irq(apu: &mut APU, limit: usize) -> Option<usize> {
+    (1..=limit).find(|_| {
+        apu.tick(0.0);
+        apu.frame_irq()
+    })
+}
+
+#[test]
+fn test_frame_irq_timing() {
+    // Write made between APU cycles resets sequencer 4 CPU cycles
+    // after the write cycle
+    let mut apu = new_apu();
+    apu.write_register(0x4017, 0x00);
+    assert_eq!(ticks_until_frame_irq(&mut apu, 40000), Some(1 + 4 + 29828));
+
+    // and 3 cycles after when made during APU cycle
+    let mut apu = new_apu();
+    apu.tick(0.0);
+    apu.write_register(0x4017, 0x00);
+    assert_eq!(ticks_until_frame_irq(&mut apu, 40000), Some(1 + 3 + 29828));
+
+    // Next sequence is 29830 cycles later, flag is raised over
+    // three cycles
+    apu.read_status();
+    assert_eq!(ticks_until_frame_irq(&mut apu, 40000), Some(1));
+    apu.read_status();
+    assert_eq!(ticks_until_frame_irq(&mut apu, 40000), Some(1));
+    apu.read_status();
+    assert_eq!(ticks_until_frame_irq(&mut apu, 40000), Some(29830 - 2));
+}
+
+#[test]
+fn test_frame_irq_acknowledge() {
+    let mut apu = new_apu();
+    run(&mut apu, 29830);
+    assert!(apu.frame_irq());
+
+    assert_eq!(apu.read_status() & 0x40, 0x40);
+    assert!(!apu.frame_irq());
+    assert_eq!(apu.read_status() & 0x40, 0x00);
+}
+
+#[test]
+fn test_frame_irq_inhibit() {
+    let mut apu = new_apu();
+    run(&mut apu, 29830);
+    assert!(apu.frame_irq());
+
+    // Setting inhibit flag clears pending IRQ immediately
+    apu.write_register(0x4017, 0x40);
+    assert!(!apu.frame_irq());
+    assert_eq!(ticks_until_frame_irq(&mut apu, 100000), None);
+
+    // 5-step mode never raises IRQ
+    apu.write_register(0x4017, 0x80);
+    assert_eq!(ticks_until_frame_irq(&mut apu, 100000), None);
+}
+
+#[test]
+fn test_five_step_mode() {
+    let mut apu = new_apu();
+    apu.write_register(0x4015, 0x01);
+    // Length index 5 loads 4 half frames
+    apu.write_register(0x4003, 0x28);
+
+    // Write clocks half frame once it takes effect
+    apu.write_register(0x4017, 0x80);
+    run(&mut apu, 1 + 4);
+    assert_eq!(apu.read_status() & 0x01, 0x01);
+
+    // Half frames at 14913 and 37281, then again 37282 cycles later
+    run(&mut apu, 37282 + 14913 - 1);
+    assert_eq!(apu.read_status() & 0x01, 0x01);
+    run(&mut apu, 1);
+    assert_eq!(apu.read_status() & 0x01, 0x00);
+}
+
+#[test]
+fn test_frame_irq_reaches_cpu() {
+    // CLI; JMP $C001 with IRQ handler INX; LDA $4015; RTI at $C010
+    let mut program = [0xEA; 0x15];
+    program[0..4].copy_from_slice(&[0x58, 0x4C, 0x01, 0xC0]);
+    program[0x10..0x15].copy_from_slice(&[0xE8, 0xAD, 0x15, 0x40, 0x40]);
+    let bus = NesBus::new(nrom_with_program(&program, Some(0xC010))).unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+
+    while cpu.cycles < 3 * 29830 - 100 {
+        cpu.next();
+    }
+    assert_eq!(cpu.register_x, 2);
+}
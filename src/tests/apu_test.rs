@@ -0,0 +1,115 @@
+// Headless harness for blargg's apu_test ROMs:
+// https://github.com/christopherpow/nes-test-roms/tree/master/apu_test
+//
+// ROMs aren't distributed with this repo, put them into APU_TEST_PATH
+// and run ignored tests.
+use crate::bus::NesBus;
+use crate::cpu::CPU;
+use crate::rom::Rom;
+use crate::tests::nrom_with_program;
+
+const APU_TEST_PATH: &str = "src/tests/apu_test/rom_singles";
+
+// Test ROMs report progress in PRG RAM: $6000 holds status, $6001-$6003
+// a signature telling status is valid, and $6004 text output
+const STATUS: u16 = 0x6000;
+const SIGNATURE: u16 = 0x6001;
+const OUTPUT: u16 = 0x6004;
+const VALID_SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const RUNNING: u8 = 0x80;
+const NEEDS_RESET: u8 = 0x81;
+
+// Emulated time after which test is considered hung
+const MAX_CYCLES: u64 = 20 * 1_789_773;
+// Checking status after every instruction would be needlessly slow
+const POLL_INTERVAL: usize = 1000;
+
+fn read_output(cpu: &mut CPU) -> String {
+    let mut text = Vec::new();
+    let mut addr = OUTPUT;
+    loop {
+        let byte = cpu.bus.mem_read(addr);
+        if byte == 0 || addr == 0x7FFF {
+            break;
+        }
+        text.push(byte);
+        addr += 1;
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+// Runs ROM until it reports result, returns result code and text output
+fn run_test_rom(rom: Rom) -> Result<(u8, String), String> {
+    let bus = NesBus::new(rom).map_err(|error| format!("{error:?}"))?;
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+
+    let mut reset_at = None;
+    while cpu.cycles < MAX_CYCLES {
+        for _ in 0..POLL_INTERVAL {
+            cpu.next();
+        }
+
+        let signature = [0, 1, 2].map(|offset| cpu.bus.mem_read(SIGNATURE + offset));
+        if signature != VALID_SIGNATURE {
+            continue;
+        }
+
+        match cpu.bus.mem_read(STATUS) {
+            RUNNING => {}
+            // Reset button has to be pressed at least 100ms later
+            NEEDS_RESET => match reset_at {
+                None => reset_at = Some(cpu.cycles + 1_789_773 / 10),
+                Some(cycles) if cpu.cycles >= cycles => {
+                    reset_at = None;
+                    cpu.reset();
+                }
+                Some(_) => {}
+            },
+            result => return Ok((result, read_output(&mut cpu))),
+        }
+    }
+
+    Err(format!("Timed out, output: {}", read_output(&mut cpu)))
+}
+
+macro_rules! apu_test {
+    ($name:ident, $file:expr) => {
+        #[test]
+        #[ignore = "requires blargg apu_test ROMs"]
+        fn $name() {
+            let path = format!("{}/{}", APU_TEST_PATH, $file);
+            let bytes = std::fs::read(&path).unwrap_or_else(|error| panic!("Can't read {path}: {error}"));
+
+            let rom = Rom::try_from(bytes).unwrap();
+            match run_test_rom(rom) {
+                Ok((0, _)) => {}
+                Ok((result, output)) => panic!("Failed with code {result}: {output}"),
+                Err(error) => panic!("{error}"),
+            }
+        }
+    };
+}
+
+apu_test!(test_apu_len_ctr, "1-len_ctr.nes");
+apu_test!(test_apu_len_table, "2-len_table.nes");
+apu_test!(test_apu_irq_flag, "3-irq_flag.nes");
+apu_test!(test_apu_jitter, "4-jitter.nes");
+apu_test!(test_apu_len_timing, "5-len_timing.nes");
+apu_test!(test_apu_irq_flag_timing, "6-irq_flag_timing.nes");
+apu_test!(test_apu_dmc_basics, "7-dmc_basics.nes");
+apu_test!(test_apu_dmc_rates, "8-dmc_rates.nes");
+
+#[test]
+fn test_harness_reads_result() {
+    // Reports result 3 with text "OK" the way test ROMs do
+    let mut program = Vec::new();
+    for (addr, value) in [(0x6001u16, 0xDE), (0x6002, 0xB0), (0x6003, 0x61), (0x6004, b'O'), (0x6005, b'K'), (0x6006, 0), (0x6000, 3)] {
+        program.extend([0xA9, value, 0x8D, addr as u8, (addr >> 8) as u8]);
+    }
+    let end = 0xC000 + program.len() as u16;
+    program.extend([0x4C, end as u8, (end >> 8) as u8]);
+
+    let rom = Rom { prg_ram_size: 0x2000, ..nrom_with_program(&program, None) };
+    assert_eq!(run_test_rom(rom), Ok((3, "OK".to_string())));
+}
@@ -0,0 +1,76 @@
+use crate::bus::{Bus, NesBus};
+use crate::rom::{Mirroring, Rom};
+
+fn nes_bus(prg_banks: usize) -> Box<NesBus> {
+    let mut prg_rom = vec![0u8; prg_banks * 0x4000];
+    for (i, byte) in prg_rom.iter_mut().enumerate() {
+        *byte = (i / 0x100) as u8;
+    }
+
+    NesBus::new(Rom {
+        prg_rom,
+        chr_rom: vec![0u8; 0x2000],
+        mapper: 0,
+        screen_mirroring: Mirroring::Horizontal,
+        prg_ram_size: 0x2000,
+        ..Rom::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_ram_mirroring() {
+    let mut bus = nes_bus(1);
+
+    bus.mem_write(0x0012, 0x34);
+    assert_eq!(bus.mem_read(0x0812), 0x34);
+    assert_eq!(bus.mem_read(0x1012), 0x34);
+    assert_eq!(bus.mem_read(0x1812), 0x34);
+
+    bus.mem_write(0x1FFF, 0x56);
+    assert_eq!(bus.mem_read(0x07FF), 0x56);
+}
+
+#[test]
+fn test_prg_rom_mirroring() {
+    let mut bus = nes_bus(1);
+    assert_eq!(bus.mem_read(0x8100), 0x01);
+    assert_eq!(bus.mem_read(0xC100), 0x01);
+
+    let mut bus = nes_bus(2);
+    assert_eq!(bus.mem_read(0xC100), 0x41);
+}
+
+#[test]
+fn test_rom_writes_are_ignored() {
+    let mut bus = nes_bus(1);
+
+    bus.mem_write(0x8100, 0xFF);
+    assert_eq!(bus.mem_read(0x8100), 0x01);
+}
+
+#[test]
+fn test_prg_ram() {
+    let mut bus = nes_bus(1);
+
+    bus.mem_write(0x6000, 0x12);
+    bus.mem_write(0x7FFF, 0x34);
+    assert_eq!(bus.mem_read(0x6000), 0x12);
+    assert_eq!(bus.mem_read(0x7FFF), 0x34);
+}
+
+#[test]
+fn test_open_bus() {
+    let mut bus = nes_bus(1);
+
+    // Unmapped expansion area returns last value seen on the bus
+    bus.mem_read(0x8300);
+    assert_eq!(bus.mem_read(0x5000), 0x03);
+
+    bus.mem_write(0x0000, 0xAB);
+    assert_eq!(bus.mem_read(0x4020), 0xAB);
+
+    // Write only APU register
+    bus.mem_read(0x0000);
+    assert_eq!(bus.mem_read(0x4000), 0xAB);
+}
@@ -0,0 +1,1310 @@
+use crate::bus::NesBus;
+use crate::cartridge::mmc3::Mmc3;
+use crate::cartridge::uxrom::Uxrom;
+use crate::cartridge::{self, Cartridge, Mapper};
+use crate::cpu::CPU;
+use crate::ppu::frame::Frame;
+use crate::ppu::palette::SYSTEM_PALETTE;
+use crate::rom::{Mirroring, Rom};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn nrom_cartridge(rom: Rom) -> Cartridge {
+    cartridge::new(Rom {
+        prg_rom: (0..0x4000).map(|i| (i / 0x100) as u8).collect(),
+        ..rom
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_nrom_without_prg_ram() {
+    let cartridge = nrom_cartridge(Rom::default());
+    let mut nrom = cartridge.borrow_mut();
+
+    nrom.cpu_write(0x6000, 0x12);
+    assert_eq!(nrom.cpu_read(0x6000), None);
+    assert_eq!(nrom.cpu_read(0x5000), None);
+    assert_eq!(nrom.cpu_read(0xC100), Some(0x01));
+}
+
+#[test]
+fn test_nrom_small_prg_ram_is_mirrored() {
+    let cartridge = nrom_cartridge(Rom { prg_ram_size: 0x800, ..Rom::default() });
+    let mut nrom = cartridge.borrow_mut();
+
+    nrom.cpu_write(0x6001, 0x12);
+    assert_eq!(nrom.cpu_read(0x6801), Some(0x12));
+    assert_eq!(nrom.cpu_read(0x7801), Some(0x12));
+}
+
+#[test]
+fn test_nrom_chr_ram() {
+    let cartridge = nrom_cartridge(Rom::default());
+    let mut nrom = cartridge.borrow_mut();
+
+    nrom.ppu_write(0x1FFF, 0x42);
+    assert_eq!(nrom.ppu_read(0x1FFF), 0x42);
+
+    // Header may ask for less than 8KB
+    let cartridge = nrom_cartridge(Rom { chr_ram_size: 0x1000, ..Rom::default() });
+    let mut nrom = cartridge.borrow_mut();
+
+    nrom.ppu_write(0x0010, 0x42);
+    assert_eq!(nrom.ppu_read(0x1010), 0x42);
+}
+
+#[test]
+fn test_nrom_battery_ram() {
+    let cartridge = nrom_cartridge(Rom::default());
+    assert!(cartridge.borrow().battery_ram().is_none());
+
+    let cartridge = nrom_cartridge(Rom { prg_nvram_size: 0x2000, battery: true, ..Rom::default() });
+    let mut nrom = cartridge.borrow_mut();
+
+    nrom.load_battery_ram(&[0x12, 0x34]);
+    assert_eq!(nrom.cpu_read(0x6001), Some(0x34));
+
+    nrom.cpu_write(0x7FFF, 0x56);
+    let saved = nrom.battery_ram().unwrap();
+    assert_eq!(saved.len(), 0x2000);
+    assert_eq!(saved[0x1FFF], 0x56);
+}
+
+#[test]
+fn test_unsupported_mapper() {
+    let rom = Rom { prg_rom: vec![0; 0x4000], mapper: 0x421, ..Rom::default() };
+    let error = cartridge::new(rom).err().unwrap();
+    assert_eq!(error.to_string(), "mapper 1057 is not supported");
+}
+
+// Every byte holds number of bank it belongs to
+fn banked(size: usize, bank_size: usize) -> Vec<u8> {
+    (0..size).map(|i| (i / bank_size) as u8).collect()
+}
+
+fn mmc1_cartridge(rom: Rom) -> Cartridge {
+    cartridge::new(Rom { mapper: 1, ..rom }).unwrap()
+}
+
+// Loads register serially, leaving gaps between writes
+fn write_mmc1(mapper: &mut dyn Mapper, addr: u16, value: u8) {
+    for bit in 0..5 {
+        mapper.cpu_write(addr, (value >> bit) & 1);
+        mapper.cpu_tick();
+        mapper.cpu_tick();
+    }
+}
+
+#[test]
+fn test_mmc1_power_on_fixes_last_bank() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x20000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    assert_eq!(mmc1.cpu_read(0x8000), Some(0));
+    assert_eq!(mmc1.cpu_read(0xFFFF), Some(7));
+
+    write_mmc1(&mut *mmc1, 0xE000, 5);
+    assert_eq!(mmc1.cpu_read(0x8000), Some(5));
+    assert_eq!(mmc1.cpu_read(0xC000), Some(7));
+}
+
+#[test]
+fn test_mmc1_last_bank_of_non_power_of_two_prg() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0xC000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    assert_eq!(mmc1.cpu_read(0xC000), Some(2));
+    assert_eq!(mmc1.cpu_read(0xFFFC), Some(2));
+
+    write_mmc1(&mut *mmc1, 0xE000, 1);
+    assert_eq!(mmc1.cpu_read(0x8000), Some(1));
+    assert_eq!(mmc1.cpu_read(0xC000), Some(2));
+
+    // 384KB, second 256KB half holds only 8 banks
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x60000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+    assert_eq!(mmc1.cpu_read(0xC000), Some(15));
+    write_mmc1(&mut *mmc1, 0xA000, 0b1_0000);
+    assert_eq!(mmc1.cpu_read(0xC000), Some(23));
+}
+
+#[test]
+fn test_mmc1_prg_modes() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x20000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+    write_mmc1(&mut *mmc1, 0xE000, 5);
+
+    // First bank fixed at $8000
+    write_mmc1(&mut *mmc1, 0x8000, 0b0_1000);
+    assert_eq!(mmc1.cpu_read(0x8000), Some(0));
+    assert_eq!(mmc1.cpu_read(0xC000), Some(5));
+
+    // 32KB switching ignores low bit
+    write_mmc1(&mut *mmc1, 0x8000, 0b0_0000);
+    assert_eq!(mmc1.cpu_read(0x8000), Some(4));
+    assert_eq!(mmc1.cpu_read(0xC000), Some(5));
+}
+
+#[test]
+fn test_mmc1_chr_modes() {
+    let cartridge = mmc1_cartridge(Rom {
+        prg_rom: banked(0x8000, 0x4000),
+        chr_rom: banked(0x20000, 0x1000),
+        ..Rom::default()
+    });
+    let mut mmc1 = cartridge.borrow_mut();
+    write_mmc1(&mut *mmc1, 0xA000, 5);
+    write_mmc1(&mut *mmc1, 0xC000, 9);
+
+    // 8KB switching ignores low bit and second register
+    assert_eq!(mmc1.ppu_read(0x0000), 4);
+    assert_eq!(mmc1.ppu_read(0x1000), 5);
+
+    write_mmc1(&mut *mmc1, 0x8000, 0b1_1100);
+    assert_eq!(mmc1.ppu_read(0x0000), 5);
+    assert_eq!(mmc1.ppu_read(0x1000), 9);
+}
+
+#[test]
+fn test_mmc1_mirroring() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x8000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    let modes = [
+        Mirroring::SingleScreenLower,
+        Mirroring::SingleScreenUpper,
+        Mirroring::Vertical,
+        Mirroring::Horizontal,
+    ];
+    for (value, mirroring) in modes.into_iter().enumerate() {
+        write_mmc1(&mut *mmc1, 0x8000, 0b0_1100 | value as u8);
+        assert_eq!(mmc1.mirroring(), mirroring);
+    }
+}
+
+#[test]
+fn test_mmc1_reset_bit() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x20000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+    write_mmc1(&mut *mmc1, 0x8000, 0b0_0000);
+
+    // Half loaded value is dropped and last bank is fixed again
+    mmc1.cpu_write(0xE000, 1);
+    mmc1.cpu_tick();
+    mmc1.cpu_tick();
+    mmc1.cpu_write(0x8000, 0x80);
+    mmc1.cpu_tick();
+    mmc1.cpu_tick();
+    assert_eq!(mmc1.cpu_read(0xC000), Some(7));
+
+    write_mmc1(&mut *mmc1, 0xE000, 2);
+    assert_eq!(mmc1.cpu_read(0x8000), Some(2));
+}
+
+#[test]
+fn test_mmc1_consecutive_writes_are_ignored() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x20000, 0x4000), ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    // Second write of each pair is dropped, so the value is 0b00011
+    for bit in [1, 1, 0, 0, 0] {
+        mmc1.cpu_write(0xE000, bit);
+        mmc1.cpu_tick();
+        mmc1.cpu_write(0xE000, 0);
+        mmc1.cpu_tick();
+        mmc1.cpu_tick();
+    }
+    assert_eq!(mmc1.cpu_read(0x8000), Some(3));
+}
+
+// INC writes unmodified value and then the result on consecutive
+// cycles, only the first write reaches MMC1
+#[test]
+fn test_mmc1_ignores_second_write_of_read_modify_write() {
+    let mut prg_rom = banked(0x20000, 0x4000);
+    prg_rom[0x0000] = 0x01;
+    let program = [
+        0xEE, 0x00, 0x80, // INC $8000
+        0xA9, 0x01,       // LDA #$01
+        0x8D, 0x00, 0xE0, // STA $E000
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x00, 0xE0, // STA $E000
+        0x8D, 0x00, 0xE0, // STA $E000
+        0x8D, 0x00, 0xE0, // STA $E000
+        0xAD, 0x01, 0x80, // LDA $8001
+        0x85, 0x00,       // STA $00
+        0x02,             // JAM
+    ];
+    prg_rom[0x1C000..0x1C000 + program.len()].copy_from_slice(&program);
+    prg_rom[0x1FFFC..0x1FFFE].copy_from_slice(&[0x00, 0xC0]);
+
+    let bus = NesBus::new(Rom { prg_rom, mapper: 1, ..Rom::default() }).unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    while !cpu.halted {
+        cpu.next();
+    }
+
+    assert_eq!(cpu.bus.mem_read(0x0000), 3);
+}
+
+#[test]
+fn test_mmc1_prg_ram_disable() {
+    let cartridge = mmc1_cartridge(Rom {
+        prg_rom: banked(0x8000, 0x4000),
+        chr_rom: banked(0x2000, 0x1000),
+        prg_ram_size: 0x2000,
+        ..Rom::default()
+    });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    mmc1.cpu_write(0x6000, 0x12);
+    assert_eq!(mmc1.cpu_read(0x6000), Some(0x12));
+
+    write_mmc1(&mut *mmc1, 0xE000, 0b1_0000);
+    assert_eq!(mmc1.cpu_read(0x6000), None);
+    mmc1.cpu_write(0x6000, 0x34);
+
+    write_mmc1(&mut *mmc1, 0xE000, 0b0_0000);
+    assert_eq!(mmc1.cpu_read(0x6000), Some(0x12));
+}
+
+#[test]
+fn test_snrom_prg_ram_disable() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x40000, 0x4000), prg_ram_size: 0x2000, ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    mmc1.cpu_write(0x6000, 0x12);
+    write_mmc1(&mut *mmc1, 0xA000, 0b1_0000);
+    assert_eq!(mmc1.cpu_read(0x6000), None);
+}
+
+#[test]
+fn test_surom_outer_prg_bank() {
+    let cartridge = mmc1_cartridge(Rom { prg_rom: banked(0x80000, 0x4000), prg_ram_size: 0x2000, ..Rom::default() });
+    let mut mmc1 = cartridge.borrow_mut();
+    write_mmc1(&mut *mmc1, 0xE000, 2);
+
+    assert_eq!(mmc1.cpu_read(0x8000), Some(2));
+    assert_eq!(mmc1.cpu_read(0xC000), Some(15));
+
+    // Both windows move to the second half, PRG RAM stays enabled
+    write_mmc1(&mut *mmc1, 0xA000, 0b1_0000);
+    assert_eq!(mmc1.cpu_read(0x8000), Some(18));
+    assert_eq!(mmc1.cpu_read(0xC000), Some(31));
+    mmc1.cpu_write(0x6000, 0x12);
+    assert_eq!(mmc1.cpu_read(0x6000), Some(0x12));
+}
+
+#[test]
+fn test_sorom_prg_ram_banks() {
+    let cartridge = mmc1_cartridge(Rom {
+        prg_rom: banked(0x40000, 0x4000),
+        prg_ram_size: 0x2000,
+        prg_nvram_size: 0x2000,
+        ..Rom::default()
+    });
+    let mut mmc1 = cartridge.borrow_mut();
+
+    mmc1.cpu_write(0x6000, 0x12);
+    write_mmc1(&mut *mmc1, 0xA000, 0b0_1000);
+    assert_eq!(mmc1.cpu_read(0x6000), Some(0x00));
+    mmc1.cpu_write(0x6000, 0x34);
+
+    write_mmc1(&mut *mmc1, 0xA000, 0b0_0000);
+    assert_eq!(mmc1.cpu_read(0x6000), Some(0x12));
+}
+
+#[test]
+fn test_uxrom_switches_lower_bank() {
+    // Submapper 1 keeps bus conflicts out of the way
+    let rom = Rom { prg_rom: banked(0x20000, 0x4000), mapper: 2, submapper: 1, ..Rom::default() };
+    let cartridge = cartridge::new(rom).unwrap();
+    let mut uxrom = cartridge.borrow_mut();
+
+    assert_eq!(uxrom.cpu_read(0x8000), Some(0));
+    assert_eq!(uxrom.cpu_read(0xC000), Some(7));
+
+    uxrom.cpu_write(0x8000, 5);
+    assert_eq!(uxrom.cpu_read(0xBFFF), Some(5));
+    assert_eq!(uxrom.cpu_read(0xFFFF), Some(7));
+
+    // Board has no PRG RAM and decodes register only at $8000-$FFFF
+    uxrom.cpu_write(0x6000, 3);
+    assert_eq!(uxrom.cpu_read(0x6000), None);
+    assert_eq!(uxrom.cpu_read(0x8000), Some(5));
+
+    // CHR RAM
+    uxrom.ppu_write(0x1234, 0x42);
+    assert_eq!(uxrom.ppu_read(0x1234), 0x42);
+}
+
+#[test]
+fn test_uxrom_prg_smaller_than_bank() {
+    // Factory rejects this size, board itself still mustn't panic
+    let mut uxrom = Uxrom::new(Rom { prg_rom: banked(0x2000, 0x1000), mapper: 2, ..Rom::default() });
+
+    assert_eq!(uxrom.cpu_read(0x8000), Some(0));
+    assert_eq!(uxrom.cpu_read(0xC000), Some(0));
+    assert_eq!(uxrom.cpu_read(0xF000), Some(1));
+}
+
+#[test]
+fn test_cnrom_switches_chr() {
+    let cartridge = cartridge::new(Rom {
+        prg_rom: banked(0x4000, 0x4000),
+        chr_rom: banked(0x8000, 0x2000),
+        mapper: 3,
+        submapper: 1,
+        ..Rom::default()
+    })
+    .unwrap();
+    let mut cnrom = cartridge.borrow_mut();
+
+    assert_eq!(cnrom.ppu_read(0x1FFF), 0);
+    cnrom.cpu_write(0xC000, 2);
+    assert_eq!(cnrom.ppu_read(0x0000), 2);
+    assert_eq!(cnrom.ppu_read(0x1FFF), 2);
+
+    // CHR ROM is read only
+    cnrom.ppu_write(0x0000, 0x42);
+    assert_eq!(cnrom.ppu_read(0x0000), 2);
+}
+
+#[test]
+fn test_axrom_switches_prg_and_nametable() {
+    let rom = Rom { prg_rom: banked(0x40000, 0x8000), mapper: 7, submapper: 1, ..Rom::default() };
+    let cartridge = cartridge::new(rom).unwrap();
+    let mut axrom = cartridge.borrow_mut();
+
+    assert_eq!(axrom.cpu_read(0x8000), Some(0));
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+
+    axrom.cpu_write(0x8000, 0b0001_0110);
+    assert_eq!(axrom.cpu_read(0x8000), Some(6));
+    assert_eq!(axrom.cpu_read(0xFFFF), Some(6));
+    assert_eq!(axrom.mirroring(), Mirroring::SingleScreenUpper);
+}
+
+// Writes 0b0011 to $C000 which holds 0b0110 in ROM. With bus
+// conflicts ROM pulls bit 0 low and 0b0010 is latched.
+fn write_over_rom(mapper: u16, submapper: u8, prg_rom: Vec<u8>, rom_offset: usize) -> Cartridge {
+    let mut prg_rom = prg_rom;
+    prg_rom[rom_offset] = 0b0110;
+
+    let rom = Rom { prg_rom, chr_rom: banked(0x8000, 0x2000), mapper, submapper, ..Rom::default() };
+    let cartridge = cartridge::new(rom).unwrap();
+    cartridge.borrow_mut().cpu_write(0xC000, 0b0011);
+    cartridge
+}
+
+#[test]
+fn test_bus_conflicts() {
+    // UxROM has last bank fixed at $C000
+    for (submapper, bank) in [(0, 2), (1, 3), (2, 2)] {
+        let uxrom = write_over_rom(2, submapper, banked(0x20000, 0x4000), 0x1C000);
+        assert_eq!(uxrom.borrow_mut().cpu_read(0x8001), Some(bank));
+    }
+
+    for (submapper, bank) in [(0, 2), (1, 3), (2, 2)] {
+        let cnrom = write_over_rom(3, submapper, banked(0x4000, 0x4000), 0x0000);
+        assert_eq!(cnrom.borrow_mut().ppu_read(0x0000), bank);
+    }
+
+    // AxROM switches away from the bank holding written address
+    for (submapper, bank) in [(0, 2), (1, 3), (2, 2)] {
+        let axrom = write_over_rom(7, submapper, banked(0x40000, 0x8000), 0x4000);
+        assert_eq!(axrom.borrow_mut().cpu_read(0x8001), Some(bank));
+    }
+}
+
+#[test]
+fn test_ines_10_uxrom_has_bus_conflicts() {
+    let mut bytes = vec![b'N', b'E', b'S', 0x1A, 8, 0, 0x20, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    bytes.extend(banked(0x20000, 0x4000));
+    bytes[16 + 0x1C000] = 0b0110;
+
+    let cartridge = cartridge::new(Rom::try_from(bytes).unwrap()).unwrap();
+    let mut uxrom = cartridge.borrow_mut();
+    uxrom.cpu_write(0xC000, 0b0011);
+    assert_eq!(uxrom.cpu_read(0x8001), Some(2));
+}
+
+fn mmc3_cartridge(rom: Rom) -> Cartridge {
+    cartridge::new(Rom {
+        prg_rom: banked(0x20000, 0x2000),
+        chr_rom: banked(0x20000, 0x0400),
+        mapper: 4,
+        prg_ram_size: 0x2000,
+        ..rom
+    })
+    .unwrap()
+}
+
+// PPU fetch from lower pattern table followed by upper one,
+// long enough apart to pass A12 filter
+fn clock_scanline(mapper: &mut dyn Mapper) {
+    mapper.ppu_bus_address(0x0000);
+    for _ in 0..10 {
+        mapper.cpu_tick();
+    }
+    mapper.ppu_bus_address(0x1000);
+}
+
+#[test]
+fn test_mmc3_prg_modes() {
+    let cartridge = mmc3_cartridge(Rom::default());
+    let mut mmc3 = cartridge.borrow_mut();
+    mmc3.cpu_write(0x8000, 6);
+    mmc3.cpu_write(0x8001, 3);
+    mmc3.cpu_write(0x8000, 7);
+    mmc3.cpu_write(0x8001, 5);
+
+    assert_eq!(mmc3.cpu_read(0x8000), Some(3));
+    assert_eq!(mmc3.cpu_read(0xA000), Some(5));
+    assert_eq!(mmc3.cpu_read(0xC000), Some(14));
+    assert_eq!(mmc3.cpu_read(0xE000), Some(15));
+
+    // Second to last bank moves to $8000
+    mmc3.cpu_write(0x8000, 0b0100_0000);
+    assert_eq!(mmc3.cpu_read(0x8000), Some(14));
+    assert_eq!(mmc3.cpu_read(0xA000), Some(5));
+    assert_eq!(mmc3.cpu_read(0xC000), Some(3));
+    assert_eq!(mmc3.cpu_read(0xE000), Some(15));
+}
+
+#[test]
+fn test_mmc3_prg_smaller_than_bank() {
+    let mut mmc3 = Mmc3::new(Rom { prg_rom: banked(0x1000, 0x0800), mapper: 4, ..Rom::default() });
+
+    for addr in [0x8000, 0xA000, 0xC000, 0xE000] {
+        assert_eq!(mmc3.cpu_read(addr), Some(0));
+    }
+    mmc3.cpu_write(0x8000, 0x40);
+    assert_eq!(mmc3.cpu_read(0x8800), Some(1));
+    assert_eq!(mmc3.cpu_read(0xE800), Some(1));
+}
+
+#[test]
+fn test_mmc3_chr_inversion() {
+    let cartridge = mmc3_cartridge(Rom::default());
+    let mut mmc3 = cartridge.borrow_mut();
+    for (register, bank) in [9, 20, 30, 31, 32, 33].into_iter().enumerate() {
+        mmc3.cpu_write(0x8000, register as u8);
+        mmc3.cpu_write(0x8001, bank);
+    }
+
+    // 2KB banks ignore low bit
+    let banks = [8, 9, 20, 21, 30, 31, 32, 33];
+    for (slot, bank) in banks.into_iter().enumerate() {
+        assert_eq!(mmc3.ppu_read(slot as u16 * 0x400), bank);
+    }
+
+    mmc3.cpu_write(0x8000, 0b1000_0000);
+    for (slot, bank) in banks.into_iter().enumerate() {
+        assert_eq!(mmc3.ppu_read((slot as u16 * 0x400) ^ 0x1000), bank);
+    }
+}
+
+#[test]
+fn test_mmc3_mirroring() {
+    let cartridge = mmc3_cartridge(Rom::default());
+    let mut mmc3 = cartridge.borrow_mut();
+
+    mmc3.cpu_write(0xA000, 1);
+    assert_eq!(mmc3.mirroring(), Mirroring::Horizontal);
+    mmc3.cpu_write(0xBFFE, 0);
+    assert_eq!(mmc3.mirroring(), Mirroring::Vertical);
+
+    // Boards with extra VRAM ignore the register
+    let cartridge = mmc3_cartridge(Rom { screen_mirroring: Mirroring::FourScreen, ..Rom::default() });
+    let mut mmc3 = cartridge.borrow_mut();
+    mmc3.cpu_write(0xA000, 1);
+    assert_eq!(mmc3.mirroring(), Mirroring::FourScreen);
+}
+
+#[test]
+fn test_mmc3_prg_ram_protect() {
+    let cartridge = mmc3_cartridge(Rom::default());
+    let mut mmc3 = cartridge.borrow_mut();
+    mmc3.cpu_write(0x6000, 0x12);
+    assert_eq!(mmc3.cpu_read(0x6000), Some(0x12));
+
+    mmc3.cpu_write(0xA001, 0b1100_0000);
+    mmc3.cpu_write(0x6000, 0x34);
+    assert_eq!(mmc3.cpu_read(0x6000), Some(0x12));
+
+    mmc3.cpu_write(0xA001, 0b0000_0000);
+    assert_eq!(mmc3.cpu_read(0x6000), None);
+}
+
+#[test]
+fn test_mmc3_irq_counter() {
+    let cartridge = mmc3_cartridge(Rom::default());
+    let mut mmc3 = cartridge.borrow_mut();
+    mmc3.cpu_write(0xC000, 3);
+    mmc3.cpu_write(0xC001, 0);
+    mmc3.cpu_write(0xE001, 0);
+
+    // Reload, then 3, 2, 1, 0
+    for _ in 0..3 {
+        clock_scanline(&mut *mmc3);
+        assert!(!mmc3.irq());
+    }
+    clock_scanline(&mut *mmc3);
+    assert!(mmc3.irq());
+
+    // Acknowledged by disabling, counter keeps going and reloads
+    mmc3.cpu_write(0xE000, 0);
+    assert!(!mmc3.irq());
+    mmc3.cpu_write(0xE001, 0);
+    for _ in 0..3 {
+        clock_scanline(&mut *mmc3);
+        assert!(!mmc3.irq());
+    }
+    clock_scanline(&mut *mmc3);
+    assert!(mmc3.irq());
+}
+
+#[test]
+fn test_mmc3_a12_filter() {
+    let cartridge = mmc3_cartridge(Rom::default());
+    let mut mmc3 = cartridge.borrow_mut();
+    mmc3.cpu_write(0xC000, 0);
+    mmc3.cpu_write(0xE001, 0);
+
+    // Toggling within few PPU dots, e.g. between 8x16 sprites
+    // from different tables, doesn't count
+    clock_scanline(&mut *mmc3);
+    assert!(mmc3.irq());
+    mmc3.cpu_write(0xE000, 0);
+    mmc3.cpu_write(0xE001, 0);
+
+    mmc3.ppu_bus_address(0x0000);
+    mmc3.cpu_tick();
+    mmc3.ppu_bus_address(0x1000);
+    assert!(!mmc3.irq());
+
+    clock_scanline(&mut *mmc3);
+    assert!(mmc3.irq());
+}
+
+// Program enables rendering in NMI with sprites fetched from $1000
+// and IRQ counter set to 100. IRQ handler turns rendering off,
+// so the picture ends with backdrop color where IRQ fired.
+fn mmc3_split_rom() -> Rom {
+    let mut prg_rom = vec![0u8; 0x8000];
+    let program = [
+        0x78,             // SEI
+        0xA9, 0x40,       // LDA #$40
+        0x8D, 0x17, 0x40, // STA $4017
+        0xA9, 0x3F,       // LDA #$3F
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x16,       // LDA #$16
+        0x8D, 0x07, 0x20, // STA $2007
+        0x8D, 0x07, 0x20, // STA $2007
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x2A,       // LDA #$2A
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x88,       // LDA #$88
+        0x8D, 0x00, 0x20, // STA $2000
+        0x58,             // CLI
+        0x4C, 0x26, 0xE0, // JMP $E026
+    ];
+    let nmi = [
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x05, 0x20, // STA $2005
+        0x8D, 0x05, 0x20, // STA $2005
+        0xA9, 0x1E,       // LDA #$1E
+        0x8D, 0x01, 0x20, // STA $2001
+        0xA9, 0x64,       // LDA #100
+        0x8D, 0x00, 0xC0, // STA $C000
+        0x8D, 0x01, 0xC0, // STA $C001
+        0x8D, 0x01, 0xE0, // STA $E001
+        0x40,             // RTI
+    ];
+    let irq = [
+        0x8D, 0x00, 0xE0, // STA $E000
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x01, 0x20, // STA $2001
+        0x40,             // RTI
+    ];
+    // Last 8KB bank is fixed at $E000
+    prg_rom[0x6000..0x6000 + program.len()].copy_from_slice(&program);
+    prg_rom[0x6100..0x6100 + nmi.len()].copy_from_slice(&nmi);
+    prg_rom[0x6200..0x6200 + irq.len()].copy_from_slice(&irq);
+    prg_rom[0x7FFA..0x8000].copy_from_slice(&[0x00, 0xE1, 0x00, 0xE0, 0x00, 0xE2]);
+
+    // Tile 0 is filled with color 3
+    let mut chr_rom = vec![0u8; 0x2000];
+    chr_rom[0x00..0x10].fill(0xFF);
+
+    Rom { prg_rom, chr_rom, mapper: 4, ..Rom::default() }
+}
+
+#[test]
+fn test_mmc3_scanline_irq() {
+    let frames = Rc::new(RefCell::new(vec![]));
+    let frames_seen = frames.clone();
+    let bus = NesBus::with_frame_callback(mmc3_split_rom(), move |ppu| {
+        frames_seen.borrow_mut().push(ppu.frame().data.clone());
+    })
+    .unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    while frames.borrow().len() < 3 {
+        cpu.next();
+    }
+
+    // Counter is reloaded on pre-render line and reaches zero
+    // at the end of line 99, rendering is off from line 100
+    let frame = Frame { data: frames.borrow_mut().pop().unwrap() };
+    let green = SYSTEM_PALETTE[0x2A];
+    let red = SYSTEM_PALETTE[0x16];
+    for y in [0, 50, 99] {
+        assert_eq!(frame.pixel(128, y), green, "line {y}");
+    }
+    for y in [100, 150, 239] {
+        assert_eq!(frame.pixel(128, y), red, "line {y}");
+    }
+}
+
+fn mmc5_cartridge() -> Cartridge {
+    cartridge::new(Rom {
+        prg_rom: banked(0x20000, 0x2000),
+        chr_rom: banked(0x40000, 0x0400),
+        mapper: 5,
+        prg_ram_size: 0x10000,
+        ..Rom::default()
+    })
+    .unwrap()
+}
+
+// Rendering PPU repeats nametable fetch three times at line start
+fn mmc5_enter_frame(mapper: &mut dyn Mapper) {
+    mapper.ppu_register_write(0x2001, 0b0001_1000);
+    for _ in 0..3 {
+        mapper.ppu_bus_address(0x2000);
+    }
+}
+
+#[test]
+fn test_mmc5_prg_modes() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    assert_eq!(mmc5.cpu_read(0xE000), Some(15));
+
+    for (addr, bank) in [(0x5114, 0x83), (0x5115, 0x85), (0x5116, 0x87), (0x5117, 0x89)] {
+        mmc5.cpu_write(addr, bank);
+    }
+    assert_eq!(mmc5.cpu_read(0x8000), Some(3));
+    assert_eq!(mmc5.cpu_read(0xA000), Some(5));
+    assert_eq!(mmc5.cpu_read(0xC000), Some(7));
+    assert_eq!(mmc5.cpu_read(0xE000), Some(9));
+
+    // 16KB banks ignore low bit
+    mmc5.cpu_write(0x5100, 2);
+    assert_eq!(mmc5.cpu_read(0x8000), Some(4));
+    assert_eq!(mmc5.cpu_read(0xA000), Some(5));
+    assert_eq!(mmc5.cpu_read(0xC000), Some(7));
+    assert_eq!(mmc5.cpu_read(0xE000), Some(9));
+
+    mmc5.cpu_write(0x5100, 1);
+    assert_eq!(mmc5.cpu_read(0xC000), Some(8));
+    assert_eq!(mmc5.cpu_read(0xE000), Some(9));
+
+    mmc5.cpu_write(0x5100, 0);
+    assert_eq!(mmc5.cpu_read(0x8000), Some(8));
+    assert_eq!(mmc5.cpu_read(0xE000), Some(11));
+}
+
+#[test]
+fn test_mmc5_prg_ram() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+
+    // Writes need both protect registers set
+    mmc5.cpu_write(0x6000, 0x12);
+    assert_eq!(mmc5.cpu_read(0x6000), Some(0));
+    mmc5.cpu_write(0x5102, 0b10);
+    mmc5.cpu_write(0x5103, 0b01);
+    mmc5.cpu_write(0x6000, 0x12);
+    assert_eq!(mmc5.cpu_read(0x6000), Some(0x12));
+
+    // RAM bank switched in at $6000 and $8000
+    mmc5.cpu_write(0x5113, 3);
+    mmc5.cpu_write(0x6000, 0x34);
+    mmc5.cpu_write(0x5114, 0x03);
+    assert_eq!(mmc5.cpu_read(0x8000), Some(0x34));
+    mmc5.cpu_write(0x8000, 0x56);
+    assert_eq!(mmc5.cpu_read(0x6000), Some(0x56));
+
+    mmc5.cpu_write(0x5113, 0);
+    assert_eq!(mmc5.cpu_read(0x6000), Some(0x12));
+}
+
+#[test]
+fn test_mmc5_chr_modes() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5101, 3);
+    for register in 0..8 {
+        mmc5.cpu_write(0x5120 + register, 10 + register as u8);
+    }
+    for slot in 0..8 {
+        assert_eq!(mmc5.ppu_read(slot * 0x400), 10 + slot as u8);
+    }
+
+    // With 8x8 sprites last written set is used everywhere
+    for register in 0..4 {
+        mmc5.cpu_write(0x5128 + register, 20 + register as u8);
+    }
+    for slot in 0..8 {
+        assert_eq!(mmc5.ppu_read(slot * 0x400), 20 + slot as u8 % 4);
+    }
+
+    // Upper bits are latched with bank register writes
+    mmc5.cpu_write(0x5101, 1);
+    mmc5.cpu_write(0x5130, 0b01);
+    mmc5.cpu_write(0x5127, 2);
+    mmc5.cpu_write(0x5130, 0);
+    assert_eq!(mmc5.ppu_read(0x1000), (0x102u16 * 4) as u8);
+}
+
+#[test]
+fn test_mmc5_tall_sprites_use_both_chr_sets() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5101, 3);
+    mmc5.cpu_write(0x5120, 10);
+    mmc5.cpu_write(0x5128, 20);
+    mmc5.ppu_register_write(0x2000, 0b0010_0000);
+    mmc5_enter_frame(&mut *mmc5);
+
+    // Sprites are fetched after 32 tiles of 4 reads each
+    for read in 1..160 {
+        mmc5.ppu_bus_address(0x0000);
+        let expected = if read < 128 { 20 } else { 10 };
+        assert_eq!(mmc5.ppu_read(0x0000), expected, "read {read}");
+    }
+}
+
+#[test]
+fn test_mmc5_multiplier() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    assert_eq!(mmc5.cpu_read(0x5205), Some(0x01));
+    assert_eq!(mmc5.cpu_read(0x5206), Some(0xFE));
+
+    mmc5.cpu_write(0x5205, 200);
+    mmc5.cpu_write(0x5206, 123);
+    assert_eq!(mmc5.cpu_read(0x5205), Some((24600u16 & 0xFF) as u8));
+    assert_eq!(mmc5.cpu_read(0x5206), Some((24600u16 >> 8) as u8));
+}
+
+#[test]
+fn test_mmc5_exram_modes() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+
+    mmc5.cpu_write(0x5104, 2);
+    mmc5.cpu_write(0x5C10, 0x12);
+    assert_eq!(mmc5.cpu_read(0x5C10), Some(0x12));
+
+    mmc5.cpu_write(0x5104, 3);
+    mmc5.cpu_write(0x5C10, 0x34);
+    assert_eq!(mmc5.cpu_read(0x5C10), Some(0x12));
+
+    // Nametable modes can't be read, and outside
+    // rendering they store 0
+    mmc5.cpu_write(0x5104, 0);
+    assert_eq!(mmc5.cpu_read(0x5C10), None);
+    mmc5.cpu_write(0x5C10, 0x56);
+    mmc5_enter_frame(&mut *mmc5);
+    mmc5.cpu_write(0x5C11, 0x78);
+    mmc5.cpu_write(0x5104, 2);
+    assert_eq!(mmc5.cpu_read(0x5C10), Some(0));
+    assert_eq!(mmc5.cpu_read(0x5C11), Some(0x78));
+}
+
+#[test]
+fn test_mmc5_nametable_mapping() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5106, 0x42);
+    mmc5.cpu_write(0x5107, 2);
+    mmc5.cpu_write(0x5105, 0b11_10_00_01);
+
+    assert_eq!(mmc5.nametable_page(0), 1);
+    assert_eq!(mmc5.nametable_page(1), 0);
+    assert_eq!(mmc5.read_nametable(0x2000), None);
+    assert!(!mmc5.write_nametable(0x2400, 0));
+
+    // ExRAM as nametable
+    assert!(mmc5.write_nametable(0x2805, 0x99));
+    assert_eq!(mmc5.read_nametable(0x2805), Some(0x99));
+    mmc5.cpu_write(0x5104, 2);
+    assert_eq!(mmc5.cpu_read(0x5C05), Some(0x99));
+    assert_eq!(mmc5.read_nametable(0x2805), Some(0));
+
+    // Fill mode
+    assert_eq!(mmc5.read_nametable(0x2C00), Some(0x42));
+    assert_eq!(mmc5.read_nametable(0x2FC0), Some(0b1010_1010));
+    assert!(mmc5.write_nametable(0x2C00, 0));
+    assert_eq!(mmc5.read_nametable(0x2C00), Some(0x42));
+}
+
+#[test]
+fn test_mmc5_extended_attributes() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5104, 1);
+    mmc5.cpu_write(0x5130, 1);
+    mmc5_enter_frame(&mut *mmc5);
+    mmc5.cpu_write(0x5C21, 0b1100_0011);
+
+    // Tile at column 1, row 1 picks palette 3 and 4KB bank $43
+    mmc5.ppu_bus_address(0x2021);
+    assert_eq!(mmc5.read_nametable(0x2021), None);
+    mmc5.ppu_bus_address(0x23C0);
+    assert_eq!(mmc5.read_nametable(0x23C0), Some(0xFF));
+    mmc5.ppu_bus_address(0x1010);
+    assert_eq!(mmc5.ppu_read(0x1010), (0x43u16 * 4) as u8);
+}
+
+#[test]
+fn test_mmc5_split_screen() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5104, 2);
+    // Row 2 of split nametable, and its attribute
+    mmc5.cpu_write(0x5C42, 0x07);
+    mmc5.cpu_write(0x5C43, 0x08);
+    mmc5.cpu_write(0x5FC0, 0b0100_0000);
+    mmc5.cpu_write(0x5104, 0);
+    mmc5.cpu_write(0x5200, 0b1000_0011);
+    mmc5.cpu_write(0x5201, 16);
+    mmc5.cpu_write(0x5202, 1);
+
+    // Line start fetches column 2, the first one inside split
+    mmc5_enter_frame(&mut *mmc5);
+    assert_eq!(mmc5.read_nametable(0x2000), Some(0x07));
+    mmc5.ppu_bus_address(0x23C0);
+    assert_eq!(mmc5.read_nametable(0x23C0), Some(0b0101_0101));
+    mmc5.ppu_bus_address(0x0070);
+    assert_eq!(mmc5.ppu_read(0x0070), 4);
+    mmc5.ppu_bus_address(0x0078);
+    assert_eq!(mmc5.ppu_read(0x0078), 4);
+
+    // Column 3 is outside, pattern fetch uses regular banks
+    mmc5.ppu_bus_address(0x2001);
+    assert_eq!(mmc5.read_nametable(0x2001), None);
+    mmc5.ppu_bus_address(0x0080);
+    assert_eq!(mmc5.ppu_read(0x0080), 0);
+}
+
+#[test]
+fn test_mmc5_scanline_counter() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5203, 2);
+    mmc5.cpu_write(0x5204, 0x80);
+
+    mmc5_enter_frame(&mut *mmc5);
+    assert_eq!(mmc5.cpu_read(0x5204), Some(0b0100_0000));
+    for _ in 0..2 {
+        mmc5.ppu_bus_address(0x0000);
+        for _ in 0..3 {
+            mmc5.ppu_bus_address(0x2000);
+        }
+    }
+    assert!(mmc5.irq());
+    assert_eq!(mmc5.cpu_read(0x5204), Some(0b1100_0000));
+    assert!(!mmc5.irq());
+
+    // PPU going idle ends the frame
+    for _ in 0..3 {
+        mmc5.cpu_tick();
+    }
+    assert_eq!(mmc5.cpu_read(0x5204), Some(0));
+}
+
+#[test]
+fn test_mmc5_audio() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    assert_eq!(mmc5.audio_output(), 0.0);
+
+    // Constant volume pulse with 50% duty
+    mmc5.cpu_write(0x5015, 0b01);
+    mmc5.cpu_write(0x5000, 0b1011_1111);
+    mmc5.cpu_write(0x5002, 0x10);
+    mmc5.cpu_write(0x5003, 0b0000_1000);
+    assert_eq!(mmc5.cpu_read(0x5015), Some(0b01));
+
+    let mut outputs = vec![];
+    for _ in 0..200 {
+        mmc5.cpu_tick();
+        outputs.push(mmc5.audio_output());
+    }
+    assert!(outputs.contains(&0.0));
+    assert!(outputs.iter().any(|&output| output > 0.0));
+
+    mmc5.cpu_write(0x5015, 0);
+    assert_eq!(mmc5.cpu_read(0x5015), Some(0));
+    assert_eq!(mmc5.audio_output(), 0.0);
+
+    mmc5.cpu_write(0x5011, 0x80);
+    assert!(mmc5.audio_output() > 0.0);
+}
+
+#[test]
+fn test_mmc5_pcm_read_mode_irq() {
+    let cartridge = mmc5_cartridge();
+    let mut mmc5 = cartridge.borrow_mut();
+    mmc5.cpu_write(0x5010, 0b1000_0001);
+    // Bank 0 starts with 0, bank 1 with 1
+    mmc5.cpu_write(0x5114, 0x81);
+    mmc5.cpu_read(0x8000);
+    assert!(!mmc5.irq());
+
+    mmc5.cpu_write(0x5114, 0x80);
+    mmc5.cpu_read(0x8000);
+    assert!(mmc5.irq());
+    assert_eq!(mmc5.cpu_read(0x5010), Some(0x80));
+    assert!(!mmc5.irq());
+}
+
+fn mmc5_split_rom() -> Rom {
+    let mut rom = mmc3_split_rom();
+    let nmi = [
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x05, 0x20, // STA $2005
+        0x8D, 0x05, 0x20, // STA $2005
+        0xA9, 0x1E,       // LDA #$1E
+        0x8D, 0x01, 0x20, // STA $2001
+        0xA9, 0x64,       // LDA #100
+        0x8D, 0x03, 0x52, // STA $5203
+        0xA9, 0x80,       // LDA #$80
+        0x8D, 0x04, 0x52, // STA $5204
+        0x40,             // RTI
+    ];
+    let irq = [
+        0xAD, 0x04, 0x52, // LDA $5204
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x01, 0x20, // STA $2001
+        0x40,             // RTI
+    ];
+    rom.prg_rom[0x6100..0x6100 + nmi.len()].copy_from_slice(&nmi);
+    rom.prg_rom[0x6200..0x6200 + irq.len()].copy_from_slice(&irq);
+    rom.mapper = 5;
+    rom
+}
+
+#[test]
+fn test_mmc5_scanline_irq() {
+    let frames = Rc::new(RefCell::new(vec![]));
+    let frames_seen = frames.clone();
+    let bus = NesBus::with_frame_callback(mmc5_split_rom(), move |ppu| {
+        frames_seen.borrow_mut().push(ppu.frame().data.clone());
+    })
+    .unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    while frames.borrow().len() < 3 {
+        cpu.next();
+    }
+
+    // IRQ fires at the start of line 100 and rendering
+    // is turned off during it
+    let frame = Frame { data: frames.borrow_mut().pop().unwrap() };
+    let green = SYSTEM_PALETTE[0x2A];
+    let red = SYSTEM_PALETTE[0x16];
+    for y in [0, 50, 99] {
+        assert_eq!(frame.pixel(128, y), green, "line {y}");
+    }
+    for y in [101, 150, 239] {
+        assert_eq!(frame.pixel(128, y), red, "line {y}");
+    }
+}
+
+fn vrc_cartridge(mapper: u16, submapper: u8) -> Cartridge {
+    cartridge::new(Rom {
+        prg_rom: banked(0x20000, 0x2000),
+        chr_rom: banked(0x40000, 0x0400),
+        mapper,
+        submapper,
+        prg_ram_size: 0x2000,
+        ..Rom::default()
+    })
+    .unwrap()
+}
+
+#[test]
+fn test_vrc4_register_wiring() {
+    let boards = [
+        (21, 1, 0x02, 0x04),
+        (21, 2, 0x40, 0x80),
+        (21, 0, 0x02, 0x04),
+        (21, 0, 0x40, 0x80),
+        (23, 1, 0x01, 0x02),
+        (23, 2, 0x04, 0x08),
+        (23, 0, 0x04, 0x08),
+        (25, 1, 0x02, 0x01),
+        (25, 2, 0x08, 0x04),
+        (25, 0, 0x08, 0x04),
+    ];
+    for (mapper, submapper, a0, a1) in boards {
+        let cartridge = vrc_cartridge(mapper, submapper);
+        let mut vrc4 = cartridge.borrow_mut();
+        // Second CHR bank is written through registers 2 and 3
+        vrc4.cpu_write(0xB000 | a1, 0x05);
+        vrc4.cpu_write(0xB000 | a1 | a0, 0x11);
+        assert_eq!(vrc4.ppu_read(0x0400), 0x15, "mapper {mapper}, submapper {submapper}");
+        assert_eq!(vrc4.ppu_read(0x0000), 0, "mapper {mapper}, submapper {submapper}");
+    }
+}
+
+#[test]
+fn test_vrc4_prg_swap_mode() {
+    let cartridge = vrc_cartridge(21, 1);
+    let mut vrc4 = cartridge.borrow_mut();
+    vrc4.cpu_write(0x8000, 3);
+    vrc4.cpu_write(0xA000, 5);
+    assert_eq!(vrc4.cpu_read(0x8000), Some(3));
+    assert_eq!(vrc4.cpu_read(0xA000), Some(5));
+    assert_eq!(vrc4.cpu_read(0xC000), Some(14));
+    assert_eq!(vrc4.cpu_read(0xE000), Some(15));
+
+    vrc4.cpu_write(0x9004, 0b10);
+    assert_eq!(vrc4.cpu_read(0x8000), Some(14));
+    assert_eq!(vrc4.cpu_read(0xC000), Some(3));
+}
+
+#[test]
+fn test_vrc4_mirroring() {
+    let cartridge = vrc_cartridge(25, 1);
+    let mut vrc4 = cartridge.borrow_mut();
+    for (data, mirroring) in [
+        (0, Mirroring::Vertical),
+        (1, Mirroring::Horizontal),
+        (2, Mirroring::SingleScreenLower),
+        (3, Mirroring::SingleScreenUpper),
+    ] {
+        vrc4.cpu_write(0x9000, data);
+        assert_eq!(vrc4.mirroring(), mirroring);
+    }
+}
+
+#[test]
+fn test_vrc2() {
+    let cartridge = cartridge::new(Rom {
+        prg_rom: banked(0x20000, 0x2000),
+        chr_rom: banked(0x40000, 0x0400),
+        mapper: 22,
+        ..Rom::default()
+    })
+    .unwrap();
+    let mut vrc2 = cartridge.borrow_mut();
+
+    // VRC2a drops the lowest bit of CHR banks
+    vrc2.cpu_write(0xB000, 0x05);
+    vrc2.cpu_write(0xB002, 0x01);
+    assert_eq!(vrc2.ppu_read(0x0000), 0x0A);
+
+    vrc2.cpu_write(0x9000, 3);
+    assert_eq!(vrc2.mirroring(), Mirroring::Horizontal);
+
+    vrc2.cpu_write(0x6000, 0xFF);
+    assert_eq!(vrc2.cpu_read(0x6000), Some(1));
+    assert_eq!(vrc2.cpu_read(0x7000), None);
+}
+
+#[test]
+fn test_vrc_irq_cycle_mode() {
+    let cartridge = vrc_cartridge(21, 1);
+    let mut vrc4 = cartridge.borrow_mut();
+    vrc4.cpu_write(0xF000, 0x0D);
+    vrc4.cpu_write(0xF002, 0x0F);
+    vrc4.cpu_write(0xF004, 0b110);
+
+    // Counter goes $FD, $FE, $FF and overflows on the third cycle
+    for _ in 0..2 {
+        vrc4.cpu_tick();
+    }
+    assert!(!vrc4.irq());
+    vrc4.cpu_tick();
+    assert!(vrc4.irq());
+
+    // Acknowledge without enable after ack bit stops the counter
+    vrc4.cpu_write(0xF006, 0);
+    assert!(!vrc4.irq());
+    for _ in 0..1000 {
+        vrc4.cpu_tick();
+    }
+    assert!(!vrc4.irq());
+}
+
+#[test]
+fn test_vrc_irq_scanline_mode() {
+    let cartridge = vrc_cartridge(24, 0);
+    let mut vrc6 = cartridge.borrow_mut();
+    vrc6.cpu_write(0xF000, 0xFF);
+    vrc6.cpu_write(0xF001, 0b011);
+
+    // Scanline is 341 / 3 CPU cycles long
+    for _ in 0..113 {
+        vrc6.cpu_tick();
+    }
+    assert!(!vrc6.irq());
+    vrc6.cpu_tick();
+    assert!(vrc6.irq());
+
+    // Counter keeps running after acknowledge
+    vrc6.cpu_write(0xF002, 0);
+    assert!(!vrc6.irq());
+    for _ in 0..114 {
+        vrc6.cpu_tick();
+    }
+    assert!(vrc6.irq());
+}
+
+#[test]
+fn test_vrc6_banking() {
+    let cartridge = vrc_cartridge(24, 0);
+    let mut vrc6 = cartridge.borrow_mut();
+    vrc6.cpu_write(0x8000, 2);
+    vrc6.cpu_write(0xC000, 9);
+    assert_eq!(vrc6.cpu_read(0x8000), Some(4));
+    assert_eq!(vrc6.cpu_read(0xA000), Some(5));
+    assert_eq!(vrc6.cpu_read(0xC000), Some(9));
+    assert_eq!(vrc6.cpu_read(0xE000), Some(15));
+
+    for register in 0..8 {
+        vrc6.cpu_write(0xD000 + (register / 4) * 0x1000 + register % 4, 10 + register as u8);
+    }
+    for slot in 0..8 {
+        assert_eq!(vrc6.ppu_read(slot * 0x400), 10 + slot as u8);
+    }
+
+    // 2KB banks with PPU A10 as the lowest bit
+    vrc6.cpu_write(0xB003, 0b0010_0101);
+    let banks = [10, 11, 10, 11, 12, 13, 12, 13];
+    for (slot, bank) in banks.into_iter().enumerate() {
+        assert_eq!(vrc6.ppu_read(slot as u16 * 0x400), bank);
+    }
+    assert_eq!(vrc6.mirroring(), Mirroring::Horizontal);
+}
+
+#[test]
+fn test_vrc6_swapped_pins() {
+    let cartridge = vrc_cartridge(26, 0);
+    let mut vrc6 = cartridge.borrow_mut();
+    vrc6.cpu_write(0xD001, 20);
+    vrc6.cpu_write(0xD002, 30);
+    assert_eq!(vrc6.ppu_read(0x0400), 30);
+    assert_eq!(vrc6.ppu_read(0x0800), 20);
+}
+
+#[test]
+fn test_vrc6_prg_ram_enable() {
+    let cartridge = vrc_cartridge(24, 0);
+    let mut vrc6 = cartridge.borrow_mut();
+    vrc6.cpu_write(0x6000, 0x12);
+    assert_eq!(vrc6.cpu_read(0x6000), None);
+
+    vrc6.cpu_write(0xB003, 0b1000_0000);
+    vrc6.cpu_write(0x6000, 0x12);
+    assert_eq!(vrc6.cpu_read(0x6000), Some(0x12));
+}
+
+fn collect_audio(mapper: &mut dyn Mapper, cycles: usize) -> Vec<f32> {
+    (0..cycles)
+        .map(|_| {
+            mapper.cpu_tick();
+            mapper.audio_output()
+        })
+        .collect()
+}
+
+#[test]
+fn test_vrc6_audio() {
+    let cartridge = vrc_cartridge(24, 0);
+    let mut vrc6 = cartridge.borrow_mut();
+    assert_eq!(vrc6.audio_output(), 0.0);
+
+    // Pulse at 50% duty
+    vrc6.cpu_write(0x9000, 0b0111_1111);
+    vrc6.cpu_write(0x9001, 0x10);
+    vrc6.cpu_write(0x9002, 0x80);
+    let outputs = collect_audio(&mut *vrc6, 1000);
+    assert!(outputs.contains(&0.0));
+    assert!(outputs.iter().any(|&output| output > 0.0));
+
+    // Halted channels keep their output
+    vrc6.cpu_write(0x9003, 1);
+    let outputs = collect_audio(&mut *vrc6, 1000);
+    assert!(outputs.iter().all(|&output| output == outputs[0]));
+
+    vrc6.cpu_write(0x9003, 0);
+    vrc6.cpu_write(0x9002, 0);
+    vrc6.cpu_write(0xB000, 0x3F);
+    vrc6.cpu_write(0xB001, 0x10);
+    vrc6.cpu_write(0xB002, 0x80);
+    let outputs = collect_audio(&mut *vrc6, 1000);
+    assert!(outputs.iter().any(|&output| output > 0.0));
+}
+
+#[test]
+fn test_vrc7_banking() {
+    let cartridge = vrc_cartridge(85, 2);
+    let mut vrc7 = cartridge.borrow_mut();
+    vrc7.cpu_write(0x8000, 3);
+    vrc7.cpu_write(0x8010, 5);
+    vrc7.cpu_write(0x9000, 7);
+    assert_eq!(vrc7.cpu_read(0x8000), Some(3));
+    assert_eq!(vrc7.cpu_read(0xA000), Some(5));
+    assert_eq!(vrc7.cpu_read(0xC000), Some(7));
+    assert_eq!(vrc7.cpu_read(0xE000), Some(15));
+
+    for slot in 0..8u16 {
+        vrc7.cpu_write(0xA000 + (slot / 2) * 0x1000 + (slot % 2) * 0x10, 10 + slot as u8);
+    }
+    for slot in 0..8 {
+        assert_eq!(vrc7.ppu_read(slot * 0x400), 10 + slot as u8);
+    }
+
+    vrc7.cpu_write(0xE000, 1);
+    assert_eq!(vrc7.mirroring(), Mirroring::Horizontal);
+
+    // VRC7b selects the second register with A3
+    let cartridge = vrc_cartridge(85, 1);
+    let mut vrc7 = cartridge.borrow_mut();
+    vrc7.cpu_write(0x8008, 5);
+    assert_eq!(vrc7.cpu_read(0xA000), Some(5));
+}
+
+#[test]
+fn test_vrc7_audio() {
+    let cartridge = vrc_cartridge(85, 2);
+    let mut vrc7 = cartridge.borrow_mut();
+    let mut write_opll = |register, data| {
+        vrc7.cpu_write(0x9010, register);
+        vrc7.cpu_write(0x9030, data);
+    };
+
+    // Instrument 1 at full volume, key on
+    write_opll(0x30, 0x10);
+    write_opll(0x10, 0xAC);
+    write_opll(0x20, 0b0001_1100);
+    let outputs = collect_audio(&mut *vrc7, 36 * 200);
+    assert!(outputs.iter().any(|&output| output > 0.01));
+    assert!(outputs.iter().any(|&output| output < -0.01));
+
+    // Audio reset silences the chip
+    vrc7.cpu_write(0xE000, 0b0100_0000);
+    assert_eq!(vrc7.audio_output(), 0.0);
+}
@@ -0,0 +1,113 @@
+use crate::bus::{Bus, NesBus};
+use crate::controller::{Button, Buttons, Controller};
+use crate::cpu::CPU;
+use crate::tests::nrom_with_program;
+
+fn read_serial(controller: &mut Controller, count: usize) -> Vec<u8> {
+    (0..count).map(|_| controller.read()).collect()
+}
+
+#[test]
+fn test_buttons() {
+    let buttons = Buttons::default();
+    buttons.set(Button::A, true);
+    buttons.set(Button::Left, true);
+    assert!(buttons.pressed(Button::Left));
+    assert!(!buttons.pressed(Button::Right));
+    assert_eq!(buttons.state(), 0b0100_0001);
+
+    buttons.set(Button::A, false);
+    assert_eq!(buttons.state(), 0b0100_0000);
+
+    buttons.set_state(0b1000_0001);
+    assert!(buttons.pressed(Button::A) && buttons.pressed(Button::Right));
+}
+
+#[test]
+fn test_serial_read_out() {
+    let mut controller = Controller::new();
+    let buttons = controller.buttons();
+    buttons.set(Button::A, true);
+    buttons.set(Button::Start, true);
+    buttons.set(Button::Right, true);
+
+    controller.write(1);
+    controller.write(0);
+    // A, B, Select, Start, Up, Down, Left, Right, then all ones
+    assert_eq!(read_serial(&mut controller, 10), vec![1, 0, 0, 1, 0, 0, 0, 1, 1, 1]);
+}
+
+#[test]
+fn test_latch_holds_state() {
+    let mut controller = Controller::new();
+    let buttons = controller.buttons();
+    buttons.set(Button::B, true);
+
+    controller.write(1);
+    controller.write(0);
+    // Changes after strobe are seen only after the next one
+    buttons.set(Button::B, false);
+    buttons.set(Button::A, true);
+    assert_eq!(read_serial(&mut controller, 2), vec![0, 1]);
+
+    controller.write(1);
+    controller.write(0);
+    assert_eq!(read_serial(&mut controller, 2), vec![1, 0]);
+}
+
+#[test]
+fn test_strobe_high_reloads() {
+    let mut controller = Controller::new();
+    let buttons = controller.buttons();
+    buttons.set(Button::A, true);
+
+    // While strobe is high, every read returns A
+    controller.write(1);
+    assert_eq!(read_serial(&mut controller, 3), vec![1, 1, 1]);
+    buttons.set(Button::A, false);
+    assert_eq!(controller.read(), 0);
+}
+
+#[test]
+fn test_bus_controller_ports() {
+    let mut bus = NesBus::new(nrom_with_program(&[], None)).unwrap();
+    bus.buttons(0).set(Button::A, true);
+    bus.buttons(1).set(Button::B, true);
+
+    bus.mem_write(0x4016, 1);
+    bus.mem_write(0x4016, 0);
+
+    // Upper bits come from open bus, last write here
+    assert_eq!(bus.mem_read(0x4016), 0x01);
+    bus.mem_write(0x0000, 0xFF);
+    assert_eq!(bus.mem_read(0x4016), 0xE0);
+    bus.mem_write(0x0000, 0x00);
+    assert_eq!(bus.mem_read(0x4017), 0x00);
+    assert_eq!(bus.mem_read(0x4017), 0x01);
+}
+
+#[test]
+fn test_cpu_reads_controller() {
+    // Strobe, then LDA $4016 and LDX $4016
+    let program = [
+        0xA9, 0x01,       // LDA #$01
+        0x8D, 0x16, 0x40, // STA $4016
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x16, 0x40, // STA $4016
+        0xAD, 0x16, 0x40, // LDA $4016
+        0xAE, 0x16, 0x40, // LDX $4016
+    ];
+    let bus = NesBus::new(nrom_with_program(&program, None)).unwrap();
+    // Front end keeps the handle, as CPU takes ownership of the bus
+    let buttons = bus.buttons(0);
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+
+    buttons.set(Button::A, true);
+    for _ in 0..6 {
+        cpu.next();
+    }
+    // Address high byte $40 is left on the bus by operand fetch
+    assert_eq!(cpu.register_a, 0x41);
+    assert_eq!(cpu.register_x, 0x40);
+}
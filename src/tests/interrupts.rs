@@ -0,0 +1,277 @@
+use crate::bus::{Bus, TestBus};
+use crate::cpu::interrupts::{InterruptLines, IrqSource};
+use crate::cpu::CPU;
+use crate::tests::cpu_with_program;
+
+#[test]
+fn test_brk_pushes_state_and_jumps_to_irq_vector() {
+    let mut cpu = cpu_with_program(vec![0x00, 0xEA]);
+
+    let result = cpu.next();
+
+    assert!(!result.end_of_program);
+    assert_eq!(cpu.program_counter, 0x9000);
+    assert_eq!(cpu.stack_pointer, 0xFA);
+    assert_eq!(cpu.bus.mem_read(0x01FD), 0x80);
+    assert_eq!(cpu.bus.mem_read(0x01FC), 0x02);
+    assert_eq!(cpu.bus.mem_read(0x01FB), 0b0011_0000);
+    assert_eq!(cpu.status & 0b0000_0100, 0b0000_0100);
+}
+
+#[test]
+fn test_rti_returns_from_brk() {
+    let mut cpu = cpu_with_program(vec![0x00, 0xEA, 0xE8]);
+    cpu.bus.mem_write(0x9000, 0x40);
+    cpu.status = 0b1010_0001;
+
+    cpu.next();
+    cpu.next();
+
+    assert_eq!(cpu.program_counter, 0x8002);
+    assert_eq!(cpu.stack_pointer, 0xFD);
+    assert_eq!(cpu.status, 0b1010_0001);
+}
+
+#[test]
+fn test_stop_on_brk_ends_program() {
+    let mut cpu = cpu_with_program(vec![0xE8, 0x00]);
+    cpu.stop_on_brk = true;
+
+    cpu.run();
+
+    assert_eq!(cpu.register_x, 1);
+    assert_eq!(cpu.program_counter, 0x8001);
+}
+
+// Bus which asserts interrupt lines starting from given cycle
+struct LineBus {
+    bus: Box<TestBus>,
+    cycle: usize,
+    nmi_from: Option<usize>,
+    irq_from: Option<usize>,
+}
+
+impl Bus for LineBus {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.bus.mem_read(addr)
+    }
+
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.bus.mem_write(addr, data)
+    }
+
+    fn tick(&mut self, interrupts: &mut InterruptLines) {
+        self.cycle += 1;
+        if self.nmi_from.is_some_and(|cycle| self.cycle >= cycle) {
+            interrupts.set_nmi(true);
+        }
+        if self.irq_from.is_some_and(|cycle| self.cycle >= cycle) {
+            interrupts.assert_irq(IrqSource::External);
+        }
+    }
+}
+
+fn cpu_with_lines(
+    start: u16,
+    program: Vec<u8>,
+    nmi_from: Option<usize>,
+    irq_from: Option<usize>,
+) -> CPU {
+    let mut bus = TestBus::new();
+    bus.load_to_specific_address(start, program);
+    bus.mem_write_u16(0xFFFA, 0xA000);
+    bus.mem_write_u16(0xFFFE, 0x9000);
+
+    let mut cpu = CPU::new(Box::new(LineBus {
+        bus,
+        cycle: 0,
+        nmi_from,
+        irq_from,
+    }));
+    cpu.reset();
+    cpu.stack_pointer = 0xFD;
+    cpu.status = 0b0010_0000;
+    cpu
+}
+
+#[test]
+fn test_nmi_is_serviced_after_current_instruction() {
+    let mut cpu = cpu_with_program(vec![0xE8, 0xE8, 0xE8]);
+    cpu.bus.mem_write_u16(0xFFFA, 0xA000);
+    cpu.bus.mem_write(0xA000, 0xE8);
+    cpu.interrupts.set_nmi(true);
+
+    cpu.next();
+    let result = cpu.next();
+
+    assert_eq!(result.cycles, 7);
+    assert_eq!(cpu.register_x, 1);
+    assert_eq!(cpu.program_counter, 0xA000);
+    assert_eq!(cpu.bus.mem_read(0x01FB), 0b0010_0000);
+    assert_eq!(cpu.status & 0b0000_0100, 0b0000_0100);
+
+    // Line is still asserted, but there was no new edge
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0xA001);
+}
+
+#[test]
+fn test_nmi_is_edge_triggered() {
+    let mut cpu = cpu_with_program(vec![0xE8; 8]);
+    cpu.bus.mem_write_u16(0xFFFA, 0xA000);
+    cpu.bus.mem_write(0xA000, 0x40);
+
+    cpu.interrupts.set_nmi(true);
+    cpu.next();
+    cpu.next();
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0x8001);
+
+    cpu.interrupts.set_nmi(false);
+    cpu.next();
+    cpu.interrupts.set_nmi(true);
+    cpu.next();
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0xA000);
+}
+
+#[test]
+fn test_irq_is_masked_by_interrupt_disable_flag() {
+    let mut cpu = cpu_with_program(vec![0xE8, 0xE8, 0xE8]);
+    cpu.status |= 0b0000_0100;
+    cpu.interrupts.assert_irq(IrqSource::External);
+
+    cpu.next();
+    cpu.next();
+    cpu.next();
+
+    assert_eq!(cpu.register_x, 3);
+    assert_eq!(cpu.program_counter, 0x8003);
+}
+
+#[test]
+fn test_irq_is_level_triggered_by_any_source() {
+    let mut cpu = cpu_with_program(vec![0xE8, 0xE8, 0xE8]);
+    // Handler is a single RTI
+    cpu.bus.mem_write(0x9000, 0x40);
+    cpu.interrupts.assert_irq(IrqSource::External);
+    cpu.interrupts.assert_irq(IrqSource::Mapper);
+
+    cpu.next();
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0x9000);
+    assert_eq!(cpu.bus.mem_read(0x01FB), 0b0010_0000);
+
+    // RTI clears interrupt disable flag, line is still held by mapper
+    cpu.interrupts.release_irq(IrqSource::External);
+    cpu.next();
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0x9000);
+    assert_eq!(cpu.register_x, 1);
+
+    cpu.interrupts.release_irq(IrqSource::Mapper);
+    cpu.next();
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0x8002);
+    assert_eq!(cpu.register_x, 2);
+}
+
+#[test]
+fn test_cli_delays_irq_by_one_instruction() {
+    // CLI; INX; INX
+    let mut cpu = cpu_with_lines(0x8000, vec![0x58, 0xE8, 0xE8], None, Some(0));
+    cpu.status |= 0b0000_0100;
+
+    cpu.next();
+    cpu.next();
+    cpu.next();
+
+    assert_eq!(cpu.register_x, 1);
+    assert_eq!(cpu.program_counter, 0x9000);
+}
+
+#[test]
+fn test_irq_during_sei_is_serviced_with_flag_set() {
+    // SEI; INX
+    let mut cpu = cpu_with_lines(0x8000, vec![0x78, 0xE8], None, Some(1));
+
+    cpu.next();
+    cpu.next();
+
+    assert_eq!(cpu.register_x, 0);
+    assert_eq!(cpu.program_counter, 0x9000);
+    assert_eq!(cpu.bus.mem_read(0x01FB), 0b0010_0100);
+}
+
+#[test]
+fn test_nmi_before_branch_operand_is_serviced_after_branch() {
+    // BNE +2; INX; INX; INX
+    let mut cpu = cpu_with_lines(0x8000, vec![0xD0, 0x02, 0xE8, 0xE8, 0xE8], Some(1), None);
+
+    let result = cpu.next();
+    assert_eq!(result.cycles, 3);
+    cpu.next();
+
+    assert_eq!(cpu.register_x, 0);
+    assert_eq!(cpu.program_counter, 0xA000);
+}
+
+#[test]
+fn test_nmi_during_taken_branch_is_delayed() {
+    // BNE +2; INX; INX; INX
+    let mut cpu = cpu_with_lines(0x8000, vec![0xD0, 0x02, 0xE8, 0xE8, 0xE8], Some(2), None);
+
+    cpu.next();
+    cpu.next();
+    cpu.next();
+
+    assert_eq!(cpu.register_x, 1);
+    assert_eq!(cpu.program_counter, 0xA000);
+}
+
+#[test]
+fn test_nmi_during_page_crossing_branch_is_not_delayed() {
+    // BNE +16 from $80F0 crosses page to $8102
+    let mut cpu = cpu_with_lines(0x80F0, vec![0xD0, 0x10], Some(3), None);
+    cpu.bus.mem_write(0x8102, 0xE8);
+
+    let result = cpu.next();
+    assert_eq!(result.cycles, 4);
+    cpu.next();
+
+    assert_eq!(cpu.register_x, 0);
+    assert_eq!(cpu.program_counter, 0xA000);
+    assert_eq!(cpu.bus.mem_read(0x01FD), 0x81);
+    assert_eq!(cpu.bus.mem_read(0x01FC), 0x02);
+}
+
+#[test]
+fn test_nmi_hijacks_brk() {
+    // BRK; padding
+    let mut cpu = cpu_with_lines(0x8000, vec![0x00, 0xEA], Some(3), None);
+    cpu.bus.mem_write(0xA000, 0xE8);
+
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0xA000);
+    assert_eq!(cpu.bus.mem_read(0x01FB), 0b0011_0000);
+
+    // NMI was consumed by BRK
+    cpu.next();
+    assert_eq!(cpu.register_x, 1);
+    assert_eq!(cpu.program_counter, 0xA001);
+}
+
+#[test]
+fn test_nmi_after_brk_vector_fetch_runs_after_first_handler_instruction() {
+    // BRK; padding
+    let mut cpu = cpu_with_lines(0x8000, vec![0x00, 0xEA], Some(6), None);
+    cpu.bus.mem_write(0x9000, 0xE8);
+
+    cpu.next();
+    assert_eq!(cpu.program_counter, 0x9000);
+
+    cpu.next();
+    cpu.next();
+    assert_eq!(cpu.register_x, 1);
+    assert_eq!(cpu.program_counter, 0xA000);
+}
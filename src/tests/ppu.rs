@@ -0,0 +1,550 @@
+use crate::bus::NesBus;
+use crate::cartridge::{self, Mapper};
+use crate::cpu::CPU;
+use crate::ppu::palette::SYSTEM_PALETTE;
+use crate::ppu::registers::*;
+use crate::ppu::{PPU, DOTS_PER_SCANLINE, SCANLINES_PER_FRAME};
+use crate::rom::{Mirroring, Rom};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const DOTS_PER_FRAME: u64 = DOTS_PER_SCANLINE as u64 * SCANLINES_PER_FRAME as u64;
+
+const RENDER_ALL: u8 = MASK_BACKGROUND | MASK_BACKGROUND_LEFT | MASK_SPRITES | MASK_SPRITES_LEFT;
+
+// Tile 1 is filled with color 3, tile 2 with color 1,
+// tile 3 has only its top left pixel set to color 1
+fn chr_rom() -> Vec<u8> {
+    let mut chr = vec![0u8; 0x2000];
+    chr[0x10..0x20].fill(0xFF);
+    chr[0x20..0x28].fill(0xFF);
+    chr[0x30] = 0x80;
+    chr
+}
+
+fn new_ppu(chr_rom: Vec<u8>, mirroring: Mirroring) -> PPU {
+    let cartridge = cartridge::new(Rom {
+        prg_rom: vec![0u8; 0x4000],
+        chr_rom,
+        screen_mirroring: mirroring,
+        ..Rom::default()
+    });
+    PPU::new(cartridge.unwrap())
+}
+
+fn set_addr(ppu: &mut PPU, addr: u16) {
+    ppu.write_register(PPUADDR, (addr >> 8) as u8);
+    ppu.write_register(PPUADDR, addr as u8);
+}
+
+fn write_data(ppu: &mut PPU, addr: u16, data: &[u8]) {
+    set_addr(ppu, addr);
+    for byte in data {
+        ppu.write_register(PPUDATA, *byte);
+    }
+}
+
+fn run_frame(ppu: &mut PPU) {
+    while !ppu.tick() {}
+}
+
+// Resets scroll and lets it reach v on pre-render line,
+// so that the next frame is rendered from top left corner
+fn start_rendering(ppu: &mut PPU, ctrl: u8, mask: u8) {
+    ppu.read_register(PPUSTATUS);
+    ppu.write_register(PPUCTRL, ctrl);
+    ppu.write_register(PPUSCROLL, 0);
+    ppu.write_register(PPUSCROLL, 0);
+    ppu.write_register(PPUMASK, mask);
+    run_frame(ppu);
+}
+
+#[test]
+fn test_vblank_and_nmi() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    ppu.write_register(PPUCTRL, CTRL_NMI_ENABLE);
+
+    let mut dots = 0;
+    while !ppu.tick() {
+        dots += 1;
+        assert!(!ppu.nmi_line());
+    }
+    assert_eq!(dots, 241 * 341 + 1);
+    assert!(ppu.nmi_line());
+
+    // Reading status acknowledges vertical blank
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_VBLANK, STATUS_VBLANK);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_VBLANK, 0);
+    assert!(!ppu.nmi_line());
+}
+
+#[test]
+fn test_vblank_cleared_on_pre_render_line() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    run_frame(&mut ppu);
+    while ppu.scanline != 261 || ppu.dot != 2 {
+        ppu.tick();
+    }
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_VBLANK, 0);
+}
+
+#[test]
+fn test_odd_frames_are_shorter_when_rendering() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    run_frame(&mut ppu);
+
+    let mut dots = 0;
+    for _ in 0..2 {
+        dots += 1;
+        while !ppu.tick() {
+            dots += 1;
+        }
+    }
+    assert_eq!(dots, 2 * DOTS_PER_FRAME);
+
+    ppu.write_register(PPUMASK, MASK_BACKGROUND);
+    let mut dots = 0;
+    for _ in 0..2 {
+        dots += 1;
+        while !ppu.tick() {
+            dots += 1;
+        }
+    }
+    assert_eq!(dots, 2 * DOTS_PER_FRAME - 1);
+}
+
+#[test]
+fn test_ppudata_reads_are_buffered() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x2000, &[0x11, 0x22]);
+
+    set_addr(&mut ppu, 0x2000);
+    ppu.read_register(PPUDATA);
+    assert_eq!(ppu.read_register(PPUDATA), 0x11);
+    assert_eq!(ppu.read_register(PPUDATA), 0x22);
+
+    // Palette is not buffered
+    write_data(&mut ppu, 0x3F01, &[0x2A]);
+    set_addr(&mut ppu, 0x3F01);
+    assert_eq!(ppu.read_register(PPUDATA), 0x2A);
+}
+
+#[test]
+fn test_ppudata_increment_32() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    ppu.write_register(PPUCTRL, CTRL_INCREMENT);
+    write_data(&mut ppu, 0x2000, &[0x11, 0x22]);
+
+    assert_eq!(ppu.read_vram(0x2000), 0x11);
+    assert_eq!(ppu.read_vram(0x2020), 0x22);
+}
+
+#[test]
+fn test_status_read_resets_write_latch() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+
+    ppu.write_register(PPUADDR, 0x3F);
+    ppu.read_register(PPUSTATUS);
+    write_data(&mut ppu, 0x2105, &[0x33]);
+
+    assert_eq!(ppu.read_vram(0x2105), 0x33);
+}
+
+#[test]
+fn test_ppuscroll_and_ppuaddr_share_latch() {
+    let mut ppu = new_ppu(vec![], Mirroring::Horizontal);
+
+    // After first PPUSCROLL write, PPUADDR write is the second one,
+    // it replaces low byte of address and loads v
+    write_data(&mut ppu, 0x2108, &[]);
+    ppu.write_register(PPUSCROLL, 0x00);
+    ppu.write_register(PPUADDR, 0x10);
+    ppu.write_register(PPUDATA, 0x42);
+    assert_eq!(ppu.read_vram(0x2110), 0x42);
+
+    // Fine Y scroll lands in bits 12-14 of address
+    ppu.read_register(PPUSTATUS);
+    ppu.write_register(PPUSCROLL, 0x00);
+    ppu.write_register(PPUSCROLL, 0b0000_1001);
+    ppu.write_register(PPUSCROLL, 0x00);
+    ppu.write_register(PPUADDR, 0x00);
+    ppu.write_register(PPUDATA, 0x43);
+    assert_eq!(ppu.read_vram(0x1000), 0x43);
+}
+
+#[test]
+fn test_palette_mirroring() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x3F10, &[0x0F]);
+    write_data(&mut ppu, 0x3F25, &[0x16]);
+
+    assert_eq!(ppu.read_vram(0x3F00), 0x0F);
+    assert_eq!(ppu.read_vram(0x3F05), 0x16);
+}
+
+#[test]
+fn test_nametable_mirroring() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x2001, &[0x11]);
+    write_data(&mut ppu, 0x2801, &[0x22]);
+    assert_eq!(ppu.read_vram(0x2401), 0x11);
+    assert_eq!(ppu.read_vram(0x2C01), 0x22);
+    assert_eq!(ppu.read_vram(0x3001), 0x11);
+
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Vertical);
+    write_data(&mut ppu, 0x2001, &[0x11]);
+    write_data(&mut ppu, 0x2401, &[0x22]);
+    assert_eq!(ppu.read_vram(0x2801), 0x11);
+    assert_eq!(ppu.read_vram(0x2C01), 0x22);
+}
+
+// Board which lets test rewire nametables
+struct SwitchableMirroring(Rc<RefCell<Mirroring>>);
+
+impl Mapper for SwitchableMirroring {
+    fn cpu_read(&mut self, _addr: u16) -> Option<u8> {
+        None
+    }
+
+    fn cpu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn ppu_read(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn ppu_write(&mut self, _addr: u16, _data: u8) {}
+
+    fn mirroring(&self) -> Mirroring {
+        *self.0.borrow()
+    }
+}
+
+#[test]
+fn test_single_screen_mirroring() {
+    let mirroring = Rc::new(RefCell::new(Mirroring::SingleScreenLower));
+    let mut ppu = PPU::new(Rc::new(RefCell::new(SwitchableMirroring(mirroring.clone()))));
+    write_data(&mut ppu, 0x2001, &[0x11]);
+    for table in [0x2001, 0x2401, 0x2801, 0x2C01] {
+        assert_eq!(ppu.read_vram(table), 0x11);
+    }
+
+    // Switching at runtime exposes the other half of VRAM
+    *mirroring.borrow_mut() = Mirroring::SingleScreenUpper;
+    assert_eq!(ppu.read_vram(0x2001), 0x00);
+    write_data(&mut ppu, 0x2C01, &[0x22]);
+    assert_eq!(ppu.read_vram(0x2001), 0x22);
+
+    *mirroring.borrow_mut() = Mirroring::Vertical;
+    assert_eq!(ppu.read_vram(0x2001), 0x11);
+    assert_eq!(ppu.read_vram(0x2401), 0x22);
+}
+
+#[test]
+fn test_chr_rom_is_read_only() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x0010, &[0x00]);
+    assert_eq!(ppu.read_vram(0x0010), 0xFF);
+
+    // Without CHR ROM cartridge provides RAM
+    let mut ppu = new_ppu(vec![], Mirroring::Horizontal);
+    write_data(&mut ppu, 0x0010, &[0x42]);
+    assert_eq!(ppu.read_vram(0x0010), 0x42);
+}
+
+#[test]
+fn test_background_rendering() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x3F00, &[0x0F, 0x00, 0x00, 0x30, 0x00, 0x16]);
+
+    // Tile 1 in top left corner, tile 2 next to it with palette 1
+    // chosen for its 2x2 tile quadrant
+    write_data(&mut ppu, 0x2000, &[0x01, 0x00, 0x02]);
+    write_data(&mut ppu, 0x23C0, &[0b0000_0100]);
+
+    start_rendering(&mut ppu, 0, MASK_BACKGROUND | MASK_BACKGROUND_LEFT);
+    run_frame(&mut ppu);
+
+    let frame = ppu.frame();
+    assert_eq!(frame.pixel(0, 0), SYSTEM_PALETTE[0x30]);
+    assert_eq!(frame.pixel(7, 7), SYSTEM_PALETTE[0x30]);
+    assert_eq!(frame.pixel(8, 0), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(16, 0), SYSTEM_PALETTE[0x16]);
+    assert_eq!(frame.pixel(255, 239), SYSTEM_PALETTE[0x0F]);
+}
+
+#[test]
+fn test_background_left_column_clipping() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x3F00, &[0x0F, 0x00, 0x00, 0x30]);
+    write_data(&mut ppu, 0x2000, &[0x01, 0x01]);
+
+    start_rendering(&mut ppu, 0, MASK_BACKGROUND);
+    run_frame(&mut ppu);
+
+    assert_eq!(ppu.frame().pixel(7, 0), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(ppu.frame().pixel(8, 0), SYSTEM_PALETTE[0x30]);
+}
+
+#[test]
+fn test_background_scrolling() {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Vertical);
+    write_data(&mut ppu, 0x3F00, &[0x0F, 0x00, 0x00, 0x30]);
+    // Top left tile of the second nametable
+    write_data(&mut ppu, 0x2400, &[0x01]);
+
+    start_rendering(&mut ppu, 0, MASK_BACKGROUND | MASK_BACKGROUND_LEFT);
+    ppu.write_register(PPUSCROLL, 252);
+    ppu.write_register(PPUSCROLL, 0);
+    run_frame(&mut ppu);
+
+    assert_eq!(ppu.frame().pixel(3, 0), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(ppu.frame().pixel(4, 0), SYSTEM_PALETTE[0x30]);
+    assert_eq!(ppu.frame().pixel(11, 7), SYSTEM_PALETTE[0x30]);
+    assert_eq!(ppu.frame().pixel(12, 0), SYSTEM_PALETTE[0x0F]);
+}
+
+// Backdrop and tile colors: background palette 0 and sprite palette 0
+fn sprite_ppu() -> PPU {
+    let mut ppu = new_ppu(chr_rom(), Mirroring::Horizontal);
+    write_data(&mut ppu, 0x3F00, &[0x0F, 0x00, 0x00, 0x30]);
+    write_data(&mut ppu, 0x3F11, &[0x16, 0x00, 0x2A]);
+    // Sprites are hidden below the screen unless placed
+    ppu.oam.fill(0xFF);
+    ppu
+}
+
+#[test]
+fn test_sprite_rendering() {
+    let mut ppu = sprite_ppu();
+    ppu.oam[0..4].copy_from_slice(&[10, 0x01, 0x00, 20]);
+    start_rendering(&mut ppu, 0, RENDER_ALL);
+    run_frame(&mut ppu);
+
+    // Sprite data is delayed by one scanline
+    let frame = ppu.frame();
+    assert_eq!(frame.pixel(20, 10), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(20, 11), SYSTEM_PALETTE[0x2A]);
+    assert_eq!(frame.pixel(27, 18), SYSTEM_PALETTE[0x2A]);
+    assert_eq!(frame.pixel(28, 18), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(27, 19), SYSTEM_PALETTE[0x0F]);
+}
+
+#[test]
+fn test_sprite_flipping() {
+    let mut ppu = sprite_ppu();
+    ppu.oam[0..4].copy_from_slice(&[10, 0x03, 0b0100_0000, 20]);
+    ppu.oam[4..8].copy_from_slice(&[10, 0x03, 0b1000_0000, 40]);
+    start_rendering(&mut ppu, 0, RENDER_ALL);
+    run_frame(&mut ppu);
+
+    let frame = ppu.frame();
+    assert_eq!(frame.pixel(20, 11), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(27, 11), SYSTEM_PALETTE[0x16]);
+    assert_eq!(frame.pixel(40, 11), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(40, 18), SYSTEM_PALETTE[0x16]);
+}
+
+#[test]
+fn test_tall_sprites() {
+    let mut ppu = sprite_ppu();
+    // Even tile number picks left pattern table, tiles 2 and 3
+    ppu.oam[0..4].copy_from_slice(&[10, 0x02, 0x00, 20]);
+    ppu.oam[4..8].copy_from_slice(&[10, 0x02, 0b1000_0000, 40]);
+    start_rendering(&mut ppu, CTRL_SPRITE_SIZE, RENDER_ALL);
+    run_frame(&mut ppu);
+
+    let frame = ppu.frame();
+    assert_eq!(frame.pixel(21, 18), SYSTEM_PALETTE[0x16]);
+    assert_eq!(frame.pixel(20, 19), SYSTEM_PALETTE[0x16]);
+    assert_eq!(frame.pixel(21, 19), SYSTEM_PALETTE[0x0F]);
+    // Flipping swaps the tiles as well
+    assert_eq!(frame.pixel(41, 11), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(40, 18), SYSTEM_PALETTE[0x16]);
+    assert_eq!(frame.pixel(41, 18), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(frame.pixel(41, 26), SYSTEM_PALETTE[0x16]);
+}
+
+#[test]
+fn test_sprite_priority() {
+    let mut ppu = sprite_ppu();
+    write_data(&mut ppu, 0x2000, &[0x01]);
+    // Behind background, visible only where background is transparent
+    ppu.oam[0..4].copy_from_slice(&[0, 0x01, 0b0010_0000, 4]);
+    // Lower OAM index wins, even if it is drawn behind background
+    ppu.oam[4..8].copy_from_slice(&[0, 0x02, 0x00, 4]);
+    start_rendering(&mut ppu, 0, RENDER_ALL);
+    run_frame(&mut ppu);
+
+    let frame = ppu.frame();
+    assert_eq!(frame.pixel(4, 1), SYSTEM_PALETTE[0x30]);
+    assert_eq!(frame.pixel(8, 1), SYSTEM_PALETTE[0x2A]);
+}
+
+#[test]
+fn test_sprite_limit_per_scanline() {
+    let mut ppu = sprite_ppu();
+    for n in 0..9 {
+        ppu.oam[n * 4..n * 4 + 4].copy_from_slice(&[10, 0x01, 0x00, n as u8 * 8]);
+    }
+    start_rendering(&mut ppu, 0, RENDER_ALL);
+    run_frame(&mut ppu);
+
+    assert_eq!(ppu.frame().pixel(63, 11), SYSTEM_PALETTE[0x2A]);
+    assert_eq!(ppu.frame().pixel(64, 11), SYSTEM_PALETTE[0x0F]);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_OVERFLOW, STATUS_SPRITE_OVERFLOW);
+}
+
+#[test]
+fn test_sprite_overflow() {
+    let mut ppu = sprite_ppu();
+    for n in 0..8 {
+        ppu.oam[n * 4..n * 4 + 4].copy_from_slice(&[10, 0x01, 0x00, 0x00]);
+    }
+    start_rendering(&mut ppu, 0, RENDER_ALL);
+    run_frame(&mut ppu);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_OVERFLOW, 0);
+
+    // After eighth sprite, tile number of sprite 9 is compared
+    // instead of its Y coordinate, causing false positive
+    ppu.oam[32..40].copy_from_slice(&[100, 0x00, 0x00, 0x00, 100, 10, 0x00, 0x00]);
+    run_frame(&mut ppu);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_OVERFLOW, STATUS_SPRITE_OVERFLOW);
+
+    // And Y coordinate of sprite 9 is missed
+    ppu.oam[32..40].copy_from_slice(&[100, 0x00, 0x00, 0x00, 10, 100, 0x00, 0x00]);
+    run_frame(&mut ppu);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_OVERFLOW, 0);
+}
+
+#[test]
+fn test_sprite_zero_hit() {
+    let mut ppu = sprite_ppu();
+    write_data(&mut ppu, 0x2000, &[0x00, 0x00, 0x01]);
+    ppu.oam[0..4].copy_from_slice(&[0, 0x01, 0x00, 8]);
+    start_rendering(&mut ppu, 0, RENDER_ALL);
+    run_frame(&mut ppu);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_ZERO_HIT, 0);
+
+    // Overlap with opaque background pixel
+    ppu.oam[3] = 9;
+    run_frame(&mut ppu);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_ZERO_HIT, STATUS_SPRITE_ZERO_HIT);
+
+    // Other sprites do not trigger it
+    ppu.oam[0..8].copy_from_slice(&[200, 0x01, 0x00, 0, 0, 0x01, 0x00, 16]);
+    run_frame(&mut ppu);
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_ZERO_HIT, 0);
+}
+
+#[test]
+fn test_sprite_zero_hit_clipped() {
+    let mut ppu = sprite_ppu();
+    write_data(&mut ppu, 0x2000, &[0x01]);
+    ppu.oam[0..4].copy_from_slice(&[0, 0x01, 0x00, 0]);
+    start_rendering(&mut ppu, 0, MASK_BACKGROUND | MASK_SPRITES);
+    run_frame(&mut ppu);
+
+    assert_eq!(ppu.read_register(PPUSTATUS) & STATUS_SPRITE_ZERO_HIT, 0);
+}
+
+// Program enables NMI and counts interrupts at $00
+fn nmi_counting_rom() -> Rom {
+    let mut prg_rom = vec![0u8; 0x4000];
+    let program = [
+        0xA9, 0x80,       // LDA #$80
+        0x8D, 0x00, 0x20, // STA $2000
+        0x4C, 0x05, 0x80, // JMP $8005
+    ];
+    prg_rom[..program.len()].copy_from_slice(&program);
+
+    let handler = [
+        0xE6, 0x00, // INC $00
+        0x40,       // RTI
+    ];
+    prg_rom[0x100..0x100 + handler.len()].copy_from_slice(&handler);
+
+    // NMI and reset vectors
+    prg_rom[0x3FFA..0x3FFE].copy_from_slice(&[0x00, 0x81, 0x00, 0x80]);
+
+    Rom {
+        prg_rom,
+        chr_rom: chr_rom(),
+        mapper: 0,
+        screen_mirroring: Mirroring::Horizontal,
+        ..Rom::default()
+    }
+}
+
+#[test]
+fn test_nmi_delivered_to_cpu() {
+    let frames = Rc::new(RefCell::new(0));
+    let frames_seen = frames.clone();
+
+    let bus = NesBus::with_frame_callback(nmi_counting_rom(), move |_| *frames_seen.borrow_mut() += 1).unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+
+    while *frames.borrow() < 3 {
+        cpu.next();
+    }
+    // Let CPU enter the handler of the last frame
+    for _ in 0..10 {
+        cpu.next();
+    }
+
+    assert_eq!(cpu.bus.mem_read(0x0000), 3);
+}
+
+// Program fills page 2 and copies it to OAM with DMA twice,
+// second time shifted by one cycle
+fn oam_dma_rom() -> Rom {
+    let mut prg_rom = vec![0u8; 0x4000];
+    let program = [
+        0xA2, 0x00,       // LDX #$00
+        0x8A,             // TXA
+        0x9D, 0x00, 0x02, // STA $0200,X
+        0xE8,             // INX
+        0xD0, 0xF9,       // BNE -7
+        0xA9, 0x02,       // LDA #$02
+        0x8D, 0x14, 0x40, // STA $4014
+        0xA9, 0x02,       // LDA #$02
+        0x8D, 0x14, 0x40, // STA $4014
+        0x02,             // JAM
+    ];
+    prg_rom[..program.len()].copy_from_slice(&program);
+    prg_rom[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+
+    Rom {
+        prg_rom,
+        chr_rom: chr_rom(),
+        mapper: 0,
+        screen_mirroring: Mirroring::Horizontal,
+        ..Rom::default()
+    }
+}
+
+#[test]
+fn test_oam_dma() {
+    let mut cpu = CPU::new(NesBus::new(oam_dma_rom()).unwrap());
+    cpu.reset();
+
+    // Instructions take at most 7 cycles, longer steps are DMA stalls
+    let mut stalls = vec![];
+    loop {
+        let result = cpu.next();
+        if result.end_of_program {
+            break;
+        }
+        if result.cycles > 7 {
+            stalls.push(result.cycles);
+        }
+    }
+    stalls.sort();
+    assert_eq!(stalls, vec![513, 514]);
+
+    cpu.bus.mem_write(0x2003, 0x00);
+    for i in 0..=0xFF {
+        cpu.bus.mem_write(0x2003, i);
+        assert_eq!(cpu.bus.mem_read(0x2004), i);
+    }
+}
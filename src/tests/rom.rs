@@ -0,0 +1,285 @@
+use rand::Rng;
+
+use crate::bus::{Bus, NesBus};
+use crate::rom::{ConsoleType, HeaderFormat, Mirroring, Rom, RomError, Timing};
+
+fn image(header: [u8; 16], prg_rom_size: usize, chr_rom_size: usize) -> Vec<u8> {
+    let mut bytes = header.to_vec();
+    bytes.extend((0..prg_rom_size).map(|i| i as u8));
+    bytes.extend((0..chr_rom_size).map(|i| !(i as u8)));
+    bytes
+}
+
+// iNES 1.0 header with given flags 6 and 7
+fn ines_header(prg_banks: u8, chr_banks: u8, flags_6: u8, flags_7: u8) -> [u8; 16] {
+    let mut header = [0u8; 16];
+    header[0..4].copy_from_slice(b"NES\x1A");
+    header[4] = prg_banks;
+    header[5] = chr_banks;
+    header[6] = flags_6;
+    header[7] = flags_7;
+    header
+}
+
+// NES 2.0 header with given bytes 6-15
+fn nes_20_header(prg_banks: u8, chr_banks: u8, flags: [u8; 10]) -> [u8; 16] {
+    let mut header = [0u8; 16];
+    header[0..4].copy_from_slice(b"NES\x1A");
+    header[4] = prg_banks;
+    header[5] = chr_banks;
+    header[6..16].copy_from_slice(&flags);
+    header[7] |= 0b0000_1000;
+    header
+}
+
+#[test]
+fn test_ines_header() {
+    let rom: Rom = std::fs::read("examples/snake.nes").unwrap().try_into().unwrap();
+
+    assert_eq!(rom.format, HeaderFormat::INes);
+    assert_eq!(rom.mapper, 0);
+    assert_eq!(rom.prg_rom.len(), 2 * 16384);
+    assert!(rom.chr_rom.is_empty());
+    assert_eq!(rom.chr_ram_size, 8192);
+    assert_eq!(rom.prg_ram_size, 8192);
+    assert_eq!(rom.timing, Timing::Ntsc);
+    assert_eq!(rom.console_type, ConsoleType::Nes);
+}
+
+#[test]
+fn test_ines_header_with_garbage() {
+    let mut header = [0u8; 16];
+    header[0..4].copy_from_slice(b"NES\x1A");
+    header[4] = 1;
+    header[6] = 0x12;
+    header[7..16].copy_from_slice(b"DiskDude!");
+
+    let rom: Rom = image(header, 16384, 0).try_into().unwrap();
+    assert_eq!(rom.format, HeaderFormat::INes);
+    assert_eq!(rom.mapper, 0x01);
+    assert!(rom.battery);
+    assert_eq!(rom.prg_nvram_size, 8192);
+    assert_eq!(rom.prg_ram_size, 0);
+}
+
+#[test]
+fn test_ines_mirroring() {
+    let cases = [
+        (0b0000, Mirroring::Horizontal),
+        (0b0001, Mirroring::Vertical),
+        (0b1000, Mirroring::FourScreen),
+        // Four screen bit overrides vertical one
+        (0b1001, Mirroring::FourScreen),
+    ];
+    for (flags_6, mirroring) in cases {
+        let rom: Rom = image(ines_header(1, 1, flags_6, 0), 16384, 8192).try_into().unwrap();
+        assert_eq!(rom.screen_mirroring, mirroring, "flags 6: {flags_6:#06b}");
+    }
+}
+
+#[test]
+fn test_ines_flags() {
+    // Mapper 0x42, battery backed vertical cartridge
+    let header = ines_header(2, 1, 0x23, 0x40);
+    let rom: Rom = image(header, 32768, 8192).try_into().unwrap();
+
+    assert_eq!(rom.mapper, 0x42);
+    assert_eq!(rom.screen_mirroring, Mirroring::Vertical);
+    assert!(rom.battery);
+    assert_eq!(rom.prg_rom.len(), 32768);
+    assert_eq!(rom.prg_rom[0x100], 0x00);
+    assert_eq!(rom.prg_rom[0x101], 0x01);
+    assert_eq!(rom.chr_rom.len(), 8192);
+    assert_eq!(rom.chr_ram_size, 0);
+}
+
+#[test]
+fn test_nes_20_mapper_and_submapper() {
+    let header = nes_20_header(1, 1, [0x10, 0x20, 0x34, 0, 0, 0, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 16384, 8192).try_into().unwrap();
+
+    assert_eq!(rom.format, HeaderFormat::Nes20);
+    assert_eq!(rom.mapper, 0x421);
+    assert_eq!(rom.submapper, 3);
+    assert_eq!(rom.prg_rom.len(), 16384);
+    assert_eq!(rom.chr_rom.len(), 8192);
+    assert_eq!(rom.chr_rom[0], 0xFF);
+}
+
+#[test]
+fn test_nes_20_rom_sizes() {
+    // Upper nibbles of bank counts
+    let header = nes_20_header(1, 0, [0, 0, 0, 0x10, 0, 0, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 16384, 256 * 8192).try_into().unwrap();
+    assert_eq!(rom.prg_rom.len(), 16384);
+    assert_eq!(rom.chr_rom.len(), 256 * 8192);
+
+    // Exponent-multiplier notation: 2^2 * 3 and 2^4 * 1 bytes
+    let header = nes_20_header(0b0000_1001, 0b0001_0000, [0, 0, 0, 0xFF, 0, 0, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 12, 16).try_into().unwrap();
+    assert_eq!(rom.prg_rom.len(), 12);
+    assert_eq!(rom.chr_rom.len(), 16);
+    assert_eq!(rom.chr_rom[0], 0xFF);
+}
+
+#[test]
+fn test_nes_20_ram_sizes() {
+    let header = nes_20_header(1, 0, [0x02, 0, 0, 0, 0x97, 0x70, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 16384, 0).try_into().unwrap();
+
+    assert!(rom.battery);
+    assert_eq!(rom.prg_ram_size, 64 << 7);
+    assert_eq!(rom.prg_nvram_size, 64 << 9);
+    assert_eq!(rom.chr_ram_size, 0);
+    assert_eq!(rom.chr_nvram_size, 64 << 7);
+}
+
+#[test]
+fn test_nes_20_timing() {
+    let timings = [Timing::Ntsc, Timing::Pal, Timing::MultiRegion, Timing::Dendy];
+    for (value, timing) in timings.iter().enumerate() {
+        let header = nes_20_header(1, 1, [0, 0, 0, 0, 0, 0, value as u8, 0, 0, 0]);
+        let rom: Rom = image(header, 16384, 8192).try_into().unwrap();
+        assert_eq!(rom.timing, *timing);
+    }
+}
+
+#[test]
+fn test_nes_20_console_type() {
+    let header = nes_20_header(1, 1, [0, 0x01, 0, 0, 0, 0, 0, 0x52, 0, 0]);
+    let rom: Rom = image(header, 16384, 8192).try_into().unwrap();
+    assert_eq!(rom.console_type, ConsoleType::VsSystem { ppu: 2, hardware: 5 });
+
+    let header = nes_20_header(1, 1, [0, 0x02, 0, 0, 0, 0, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 16384, 8192).try_into().unwrap();
+    assert_eq!(rom.console_type, ConsoleType::Playchoice10);
+
+    let header = nes_20_header(1, 1, [0, 0x03, 0, 0, 0, 0, 0, 0x04, 0, 0]);
+    let rom: Rom = image(header, 16384, 8192).try_into().unwrap();
+    assert_eq!(rom.console_type, ConsoleType::Extended(4));
+}
+
+#[test]
+fn test_nes_20_misc_rom_and_expansion_device() {
+    let header = nes_20_header(1, 1, [0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x2A]);
+    let mut bytes = image(header, 16384, 8192);
+    bytes.extend([0xDE, 0xAD]);
+    let rom: Rom = bytes.try_into().unwrap();
+
+    assert_eq!(rom.misc_rom_count, 1);
+    assert_eq!(rom.misc_rom, vec![0xDE, 0xAD]);
+    assert_eq!(rom.expansion_device, 0x2A);
+}
+
+#[test]
+fn test_bad_magic() {
+    let mut bytes = std::fs::read("examples/snake.nes").unwrap();
+    bytes[3] = 0x00;
+    assert_eq!(Rom::try_from(bytes).err(), Some(RomError::BadMagic));
+}
+
+#[test]
+fn test_truncated_header() {
+    let bytes = b"NES\x1A\x01".to_vec();
+    assert_eq!(Rom::try_from(bytes).err(), Some(RomError::TruncatedHeader { length: 5 }));
+}
+
+#[test]
+fn test_trainer_missing() {
+    let header = nes_20_header(1, 0, [0b0100, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(Rom::try_from(image(header, 0, 0)).err(), Some(RomError::TrainerMissing));
+}
+
+// Image with trainer filled with its offsets, followed by PRG ROM
+// starting with $AA
+fn trainer_image(header: [u8; 16]) -> Vec<u8> {
+    let mut bytes = header.to_vec();
+    bytes.extend((0..512).map(|i| i as u8));
+    bytes.extend([0xAA; 16384]);
+    bytes
+}
+
+#[test]
+fn test_trainer() {
+    let rom = Rom::try_from(trainer_image(ines_header(1, 0, 0b0100, 0))).unwrap();
+    let trainer = rom.trainer.as_ref().unwrap();
+    assert_eq!(trainer.len(), 512);
+    assert_eq!(trainer[0x123], 0x23);
+    assert_eq!(rom.prg_rom[0], 0xAA);
+
+    let rom = Rom::try_from(image(ines_header(1, 0, 0, 0), 16384, 0)).unwrap();
+    assert!(rom.trainer.is_none());
+}
+
+#[test]
+fn test_trainer_mapped_at_power_on() {
+    let rom = Rom::try_from(trainer_image(ines_header(1, 0, 0b0100, 0))).unwrap();
+    let mut bus = NesBus::new(rom).unwrap();
+    assert_eq!(bus.mem_read(0x7000), 0x00);
+    assert_eq!(bus.mem_read(0x7123), 0x23);
+    assert_eq!(bus.mem_read(0x71FF), 0xFF);
+    assert_eq!(bus.mem_read(0x6000), 0x00);
+
+    // Header without PRG RAM still gets RAM for trainer
+    let rom = Rom::try_from(trainer_image(nes_20_header(1, 0, [0b0100, 0, 0, 0, 0, 0, 0, 0, 0, 0]))).unwrap();
+    let mut bus = NesBus::new(rom).unwrap();
+    assert_eq!(bus.mem_read(0x7123), 0x23);
+}
+
+#[test]
+fn test_truncated_prg_and_chr_rom() {
+    let header = nes_20_header(2, 1, [0; 10]);
+    assert_eq!(
+        Rom::try_from(image(header, 16384, 0)).err(),
+        Some(RomError::TruncatedPrgRom { expected: 32768, found: 16384 })
+    );
+    assert_eq!(
+        Rom::try_from(image(header, 32768, 100)).err(),
+        Some(RomError::TruncatedChrRom { expected: 8192, found: 100 })
+    );
+}
+
+#[test]
+fn test_invalid_sizes() {
+    // 2^63 * 3 bytes
+    let header = nes_20_header(0xFF, 0, [0, 0, 0, 0x0F, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(Rom::try_from(image(header, 0, 0)).err(), Some(RomError::InvalidSize("PRG ROM")));
+
+    let header = nes_20_header(0, 1, [0; 10]);
+    assert_eq!(Rom::try_from(image(header, 0, 8192)).err(), Some(RomError::InvalidSize("PRG ROM")));
+}
+
+#[test]
+fn test_unsupported_mapper() {
+    let header = nes_20_header(1, 1, [0x10, 0x20, 0x34, 0, 0, 0, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 16384, 8192).try_into().unwrap();
+    assert_eq!(NesBus::new(rom).err(), Some(RomError::UnsupportedMapper(0x421)));
+
+    // NROM can't map 12 bytes of PRG ROM
+    let header = nes_20_header(0b0000_1001, 0, [0, 0, 0, 0x0F, 0, 0, 0, 0, 0, 0]);
+    let rom: Rom = image(header, 12, 0).try_into().unwrap();
+    assert_eq!(NesBus::new(rom).err(), Some(RomError::InvalidSize("PRG ROM")));
+
+    // Board can't run without PRG ROM
+    assert_eq!(NesBus::new(Rom::default()).err(), Some(RomError::InvalidSize("PRG ROM")));
+}
+
+// Quick stand-in for fuzz target in fuzz/, loading must never panic
+#[test]
+fn test_malformed_files_never_panic() {
+    let snake = std::fs::read("examples/snake.nes").unwrap();
+    for length in 0..snake.len() {
+        assert!(Rom::try_from(snake[..length].to_vec()).is_err());
+    }
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..10000 {
+        let mut bytes: Vec<u8> = (0..rng.gen_range(0..64)).map(|_| rng.gen()).collect();
+        if bytes.len() >= 4 && rng.gen() {
+            bytes[0..4].copy_from_slice(b"NES\x1A");
+        }
+        if let Ok(rom) = Rom::try_from(bytes) {
+            let _ = NesBus::new(rom);
+        }
+    }
+}
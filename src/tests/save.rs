@@ -0,0 +1,76 @@
+use crate::bus::{Bus, NesBus};
+use crate::rom::Rom;
+use crate::save::{save_path, SaveFile};
+use std::fs;
+use std::path::PathBuf;
+
+fn battery_bus(battery: bool) -> Box<NesBus> {
+    NesBus::new(Rom {
+        prg_rom: vec![0; 0x8000],
+        mapper: 1,
+        prg_nvram_size: 0x2000,
+        battery,
+        ..Rom::default()
+    })
+    .unwrap()
+}
+
+// ROM path unique to the test, in a fresh directory
+fn rom_path(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_nes_emu_{test}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.join("game.nes")
+}
+
+#[test]
+fn test_save_path() {
+    assert_eq!(save_path("roms/zelda.nes"), PathBuf::from("roms/zelda.sav"));
+}
+
+#[test]
+fn test_save_data() {
+    let mut bus = battery_bus(false);
+    bus.mem_write(0x6000, 0x12);
+    assert_eq!(bus.save_data(), None);
+
+    let mut bus = battery_bus(true);
+    bus.mem_write(0x6000, 0x12);
+    let data = bus.save_data().unwrap();
+    assert_eq!(data.len(), 0x2000);
+    assert_eq!(data[0], 0x12);
+
+    let mut bus = battery_bus(true);
+    bus.load_save_data(&data);
+    assert_eq!(bus.mem_read(0x6000), 0x12);
+}
+
+#[test]
+fn test_save_file_round_trip() {
+    let rom_path = rom_path("round_trip");
+
+    // Missing save starts the game with empty RAM
+    let mut bus = battery_bus(true);
+    let save = SaveFile::open(&rom_path, bus.cartridge().clone()).unwrap();
+    assert_eq!(bus.mem_read(0x6000), 0);
+    bus.mem_write(0x6000, 0x34);
+    save.flush().unwrap();
+    assert_eq!(fs::read(save.path()).unwrap()[0], 0x34);
+
+    bus.mem_write(0x6001, 0x56);
+    drop(save);
+
+    let mut bus = battery_bus(true);
+    let _save = SaveFile::open(&rom_path, bus.cartridge().clone()).unwrap();
+    assert_eq!(bus.mem_read(0x6000), 0x34);
+    assert_eq!(bus.mem_read(0x6001), 0x56);
+}
+
+#[test]
+fn test_save_file_without_battery() {
+    let rom_path = rom_path("without_battery");
+    let bus = battery_bus(false);
+    let save = SaveFile::open(&rom_path, bus.cartridge().clone()).unwrap();
+    save.flush().unwrap();
+    assert!(!save.path().exists());
+}
@@ -0,0 +1,191 @@
+use crate::bus::NesBus;
+use crate::cpu::CPU;
+use crate::ppu::frame::{Frame, HEIGHT, WIDTH};
+use crate::ppu::palette::SYSTEM_PALETTE;
+use crate::rom::{Mirroring, Rom};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const BLACK: (u8, u8, u8) = SYSTEM_PALETTE[0x0F];
+const WHITE: (u8, u8, u8) = SYSTEM_PALETTE[0x30];
+
+// Sprite 0 covers left 8 pixels of these rows. Hit is detected
+// on the first one, CPU makes the split while it is drawn.
+const SPRITE_ROWS: std::ops::Range<usize> = 101..109;
+
+// Program draws vertical 8 pixel wide stripes with background,
+// waits for sprite 0 hit in every frame and runs `split` code
+fn split_screen_rom(split: [u8; 10]) -> Rom {
+    let mut program = vec![
+        0x78,             // SEI
+        0xD8,             // CLD
+        0xA2, 0xFF,       // LDX #$FF
+        0x9A,             // TXS
+        0x2C, 0x02, 0x20, // BIT $2002
+        0x10, 0xFB,       // BPL -5
+        // Palette: black backdrop, color 3 is white
+        0xA9, 0x3F,       // LDA #$3F
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x0F,       // LDA #$0F
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x07, 0x20, // STA $2007
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x30,       // LDA #$30
+        0x8D, 0x07, 0x20, // STA $2007
+        // First nametable alternates solid and empty tiles
+        0xA9, 0x20,       // LDA #$20
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA0, 0x02,       // LDY #$02
+        0xA2, 0xF0,       // LDX #$F0
+        0xA9, 0x01,       // LDA #$01
+        0x8D, 0x07, 0x20, // STA $2007
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x07, 0x20, // STA $2007
+        0xCA,             // DEX
+        0xD0, 0xF3,       // BNE -13
+        0x88,             // DEY
+        0xD0, 0xEE,       // BNE -18
+        // Attributes
+        0xA2, 0x40,       // LDX #$40
+        0x8D, 0x07, 0x20, // STA $2007
+        0xCA,             // DEX
+        0xD0, 0xFA,       // BNE -6
+        // Sprite 0 over the first stripe
+        0x8D, 0x03, 0x20, // STA $2003
+        0xA9, 0x64,       // LDA #100
+        0x8D, 0x04, 0x20, // STA $2004
+        0xA9, 0x01,       // LDA #$01
+        0x8D, 0x04, 0x20, // STA $2004
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x04, 0x20, // STA $2004
+        0x8D, 0x04, 0x20, // STA $2004
+        // Main loop, reset scroll in vertical blank
+        0x2C, 0x02, 0x20, // BIT $2002
+        0x10, 0xFB,       // BPL -5
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x00, 0x20, // STA $2000
+        0x8D, 0x05, 0x20, // STA $2005
+        0x8D, 0x05, 0x20, // STA $2005
+        0xA9, 0x1E,       // LDA #$1E
+        0x8D, 0x01, 0x20, // STA $2001
+        // Wait for hit flag to be cleared, then set
+        0x2C, 0x02, 0x20, // BIT $2002
+        0x70, 0xFB,       // BVS -5
+        0x2C, 0x02, 0x20, // BIT $2002
+        0x50, 0xFB,       // BVC -5
+    ];
+    program.extend_from_slice(&split);
+    program.extend_from_slice(&[
+        0x4C, 0x61, 0x80, // JMP $8061
+    ]);
+
+    let mut prg_rom = vec![0u8; 0x4000];
+    prg_rom[..program.len()].copy_from_slice(&program);
+    prg_rom[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+
+    // Tile 1 is filled with color 3
+    let mut chr_rom = vec![0u8; 0x2000];
+    chr_rom[0x10..0x20].fill(0xFF);
+
+    Rom {
+        prg_rom,
+        chr_rom,
+        mapper: 0,
+        screen_mirroring: Mirroring::Vertical,
+        ..Rom::default()
+    }
+}
+
+fn render_frames(rom: Rom, count: usize) -> Frame {
+    let frames = Rc::new(RefCell::new(vec![]));
+    let frames_seen = frames.clone();
+
+    let bus = NesBus::with_frame_callback(rom, move |ppu| {
+        frames_seen.borrow_mut().push(ppu.frame().data.clone());
+    })
+    .unwrap();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+
+    while frames.borrow().len() < count {
+        cpu.next();
+    }
+
+    let data = frames.borrow_mut().pop().unwrap();
+    Frame { data }
+}
+
+// Scrolled picture continues with the second, empty nametable
+fn stripes(scroll_x: usize) -> Vec<(u8, u8, u8)> {
+    (0..WIDTH)
+        .map(|x| x + scroll_x)
+        .map(|x| if x < WIDTH && (x / 8) % 2 == 0 { WHITE } else { BLACK })
+        .collect()
+}
+
+fn row(frame: &Frame, y: usize) -> Vec<(u8, u8, u8)> {
+    (0..WIDTH).map(|x| frame.pixel(x, y)).collect()
+}
+
+#[test]
+fn test_split_with_ppuscroll() {
+    let rom = split_screen_rom([
+        0xA9, 0x04,       // LDA #$04
+        0x8D, 0x05, 0x20, // STA $2005
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x05, 0x20, // STA $2005
+    ]);
+    let frame = render_frames(rom, 4);
+
+    for y in 0..SPRITE_ROWS.start {
+        assert_eq!(row(&frame, y), stripes(0), "row {}", y);
+    }
+
+    // Fine X changes immediately, coarse X is copied at the end of
+    // scanline. Vertical part of the write is ignored mid-frame.
+    assert_eq!(row(&frame, SPRITE_ROWS.start + 1)[8..], stripes(4)[8..]);
+    for y in SPRITE_ROWS.end..HEIGHT {
+        assert_eq!(row(&frame, y), stripes(4), "row {}", y);
+    }
+}
+
+#[test]
+fn test_split_with_ppuaddr() {
+    let rom = split_screen_rom([
+        0xA9, 0x24,       // LDA #$24
+        0x8D, 0x06, 0x20, // STA $2006
+        0xA9, 0x00,       // LDA #$00
+        0x8D, 0x06, 0x20, // STA $2006
+    ]);
+    let frame = render_frames(rom, 4);
+
+    for y in 0..SPRITE_ROWS.start {
+        assert_eq!(row(&frame, y), stripes(0), "row {}", y);
+    }
+
+    // Second nametable is empty, PPUADDR write switches to it at once
+    assert!(row(&frame, SPRITE_ROWS.start + 1)[8..].iter().all(|&pixel| pixel == BLACK));
+    for y in SPRITE_ROWS.end..HEIGHT {
+        assert!(row(&frame, y).iter().all(|&pixel| pixel == BLACK), "row {}", y);
+    }
+}
+
+#[test]
+fn test_frames_are_stable() {
+    let rom = split_screen_rom([0xEA; 10]);
+    let first = render_frames(rom, 4);
+    let rom = split_screen_rom([0xEA; 10]);
+    let second = render_frames(rom, 5);
+
+    assert!(first.data == second.data);
+    for y in 0..HEIGHT {
+        if !SPRITE_ROWS.contains(&y) {
+            assert_eq!(row(&first, y), stripes(0), "row {}", y);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::tests::cpu_with_program;
+
+#[test]
+fn test_jam_halts_cpu() {
+    // INX; JAM; INX
+    let mut cpu = cpu_with_program(vec![0xE8, 0x02, 0xE8]);
+
+    cpu.run();
+
+    assert!(cpu.halted);
+    assert_eq!(cpu.program_counter, 0x8001);
+    assert_eq!(cpu.register_x, 1);
+
+    // Halted CPU does not react to anything but reset
+    cpu.interrupts.set_nmi(true);
+    cpu.next();
+    let result = cpu.next();
+    assert!(result.end_of_program);
+    assert_eq!(cpu.program_counter, 0x8001);
+
+    cpu.reset();
+    assert!(!cpu.halted);
+    cpu.next();
+    assert_eq!(cpu.register_x, 1);
+}
+
+#[test]
+fn test_shx_stores_x_and_high_byte() {
+    // SHX $1280,Y
+    let mut cpu = cpu_with_program(vec![0x9E, 0x80, 0x12]);
+    cpu.register_x = 0xFF;
+    cpu.register_y = 0x10;
+
+    cpu.next();
+
+    assert_eq!(cpu.bus.mem_read(0x1290), 0x13);
+}
+
+#[test]
+fn test_shy_replaces_high_byte_on_page_cross() {
+    // SHY $12F0,X
+    let mut cpu = cpu_with_program(vec![0x9C, 0xF0, 0x12]);
+    cpu.register_x = 0x20;
+    cpu.register_y = 0x05;
+
+    cpu.next();
+
+    // 0x05 & 0x13 = 0x01 is also the new high byte of $1310
+    assert_eq!(cpu.bus.mem_read(0x0110), 0x01);
+    assert_eq!(cpu.bus.mem_read(0x1310), 0x00);
+}
+
+#[test]
+fn test_sha_indirect_y() {
+    // SHA ($40),Y
+    let mut cpu = cpu_with_program(vec![0x93, 0x40]);
+    cpu.bus.mem_write_u16(0x40, 0x3400);
+    cpu.register_a = 0b1111_0101;
+    cpu.register_x = 0b0101_1111;
+    cpu.register_y = 0x02;
+
+    cpu.next();
+
+    assert_eq!(cpu.bus.mem_read(0x3402), 0b0101_0101 & 0x35);
+}
+
+#[test]
+fn test_tas_sets_stack_pointer() {
+    // TAS $2000,Y
+    let mut cpu = cpu_with_program(vec![0x9B, 0x00, 0x20]);
+    cpu.register_a = 0xF0;
+    cpu.register_x = 0x3C;
+    cpu.register_y = 0x01;
+
+    cpu.next();
+
+    assert_eq!(cpu.stack_pointer, 0x30);
+    assert_eq!(cpu.bus.mem_read(0x2001), 0x30 & 0x21);
+}
+
+#[test]
+fn test_las_loads_memory_and_stack_pointer() {
+    // LAS $2000,Y
+    let mut cpu = cpu_with_program(vec![0xBB, 0x00, 0x20]);
+    cpu.bus.mem_write(0x2004, 0b1010_1100);
+    cpu.stack_pointer = 0b1100_1010;
+    cpu.register_y = 0x04;
+
+    cpu.next();
+
+    assert_eq!(cpu.register_a, 0b1000_1000);
+    assert_eq!(cpu.register_x, 0b1000_1000);
+    assert_eq!(cpu.stack_pointer, 0b1000_1000);
+    assert_eq!(cpu.status & 0b1000_0010, 0b1000_0000);
+}
@@ -0,0 +1,68 @@
+use crate::apu::{CPU_CLOCK, DEFAULT_SAMPLE_RATE};
+use crate::ppu::{DOTS_PER_SCANLINE, SCANLINES_PER_FRAME};
+use crate::rom::Rom;
+use crate::tests::nrom_with_program;
+use crate::wav::{capture_audio, save_wav, write_wav};
+use std::fs;
+
+#[test]
+fn test_write_wav() {
+    let mut bytes = Vec::new();
+    write_wav(&mut bytes, 48_000, &[0.0, 1.0, -1.0, 2.0, 0.5]).unwrap();
+
+    assert_eq!(bytes.len(), 44 + 5 * 2);
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(&bytes[4..8], &(36u32 + 10).to_le_bytes());
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    // PCM, mono, 48kHz, 96000 bytes per second, 2 bytes per frame, 16 bits
+    assert_eq!(&bytes[16..36], &[16, 0, 0, 0, 1, 0, 1, 0, 0x80, 0xBB, 0, 0, 0x00, 0x77, 0x01, 0, 2, 0, 16, 0]);
+    assert_eq!(&bytes[36..44], &[b'd', b'a', b't', b'a', 10, 0, 0, 0]);
+
+    let samples: Vec<i16> = bytes[44..].chunks(2).map(|pair| i16::from_le_bytes([pair[0], pair[1]])).collect();
+    assert_eq!(samples, vec![0, 32767, -32767, 32767, 16384]);
+}
+
+// Plays 50% duty square wave on pulse 1
+fn tone_rom() -> Rom {
+    let program = [
+        0xA9, 0x01,       // LDA #$01
+        0x8D, 0x15, 0x40, // STA $4015
+        0xA9, 0xBF,       // LDA #$BF
+        0x8D, 0x00, 0x40, // STA $4000
+        0xA9, 0xFD,       // LDA #$FD
+        0x8D, 0x02, 0x40, // STA $4002
+        0xA9, 0x08,       // LDA #$08
+        0x8D, 0x03, 0x40, // STA $4003
+        0x4C, 0x14, 0xC0, // JMP $C014
+    ];
+    nrom_with_program(&program, None)
+}
+
+#[test]
+fn test_capture_audio() {
+    let samples = capture_audio(tone_rom(), 30, DEFAULT_SAMPLE_RATE).unwrap();
+    let longer = capture_audio(tone_rom(), 60, DEFAULT_SAMPLE_RATE).unwrap();
+
+    // First frame ends early, as PPU starts at the top of the picture,
+    // so compare 30 frames following it
+    let frame_cycles = (DOTS_PER_SCANLINE as u64 * SCANLINES_PER_FRAME as u64) / 3;
+    let expected = 30 * frame_cycles * DEFAULT_SAMPLE_RATE as u64 / CPU_CLOCK as u64;
+    assert!(((longer.len() - samples.len()) as i64 - expected as i64).abs() < 5);
+    assert!(samples.iter().any(|&sample| sample > 0.05));
+    assert!(samples.iter().any(|&sample| sample < -0.05));
+
+    // Emulation is deterministic
+    assert_eq!(capture_audio(tone_rom(), 30, DEFAULT_SAMPLE_RATE).unwrap(), samples);
+}
+
+#[test]
+fn test_save_wav() {
+    let samples = capture_audio(tone_rom(), 10, 22_050).unwrap();
+    let path = std::env::temp_dir().join(format!("rust_nes_emu_capture_{}.wav", std::process::id()));
+    save_wav(&path, 22_050, &samples).unwrap();
+
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(bytes.len(), 44 + samples.len() * 2);
+    assert_eq!(&bytes[24..28], &22_050u32.to_le_bytes());
+}
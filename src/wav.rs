@@ -0,0 +1,79 @@
+// Audio capture into WAV files, described here:
+// http://soundfile.sapp.org/doc/WaveFormat/
+//
+// Runs emulation without a window or audio device, so output of
+// different emulator versions can be compared in regression tests.
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::bus::NesBus;
+use crate::cpu::CPU;
+use crate::rom::{Rom, RomError};
+
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+const BYTES_PER_SAMPLE: u32 = (BITS_PER_SAMPLE / 8) as u32;
+// Size of header fields following RIFF chunk size
+const HEADER_SIZE: u32 = 36;
+const FMT_CHUNK_SIZE: u32 = 16;
+const FORMAT_PCM: u16 = 1;
+
+// Runs ROM from power on for given number of frames, returns APU
+// output sampled at given rate. Stops early when CPU halts.
+pub fn capture_audio(rom: Rom, frames: usize, sample_rate: u32) -> Result<Vec<f32>, RomError> {
+    let frame_count = Rc::new(Cell::new(0));
+    let frames_seen = frame_count.clone();
+    let mut bus = NesBus::with_frame_callback(rom, move |_| frames_seen.set(frames_seen.get() + 1))?;
+    bus.apu_mut().set_sample_rate(sample_rate);
+    let samples = bus.apu().samples();
+
+    // APU keeps only recent output, so it's drained as emulation goes
+    let mut captured = Vec::new();
+    let mut cpu = CPU::new(bus);
+    cpu.reset();
+    while frame_count.get() < frames {
+        if cpu.next().end_of_program {
+            break;
+        }
+        captured.append(&mut samples.borrow_mut());
+    }
+
+    captured.append(&mut samples.borrow_mut());
+    Ok(captured)
+}
+
+// Writes mono 16-bit PCM, samples are clamped to -1.0..=1.0
+pub fn write_wav(writer: &mut impl Write, sample_rate: u32, samples: &[f32]) -> io::Result<()> {
+    let data_size = samples.len() as u32 * BYTES_PER_SAMPLE * CHANNELS as u32;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(HEADER_SIZE + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&FMT_CHUNK_SIZE.to_le_bytes())?;
+    writer.write_all(&FORMAT_PCM.to_le_bytes())?;
+    writer.write_all(&CHANNELS.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+pub fn save_wav(path: impl AsRef<Path>, sample_rate: u32, samples: &[f32]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_wav(&mut writer, sample_rate, samples)?;
+    writer.flush()
+}